@@ -0,0 +1,330 @@
+use crate::attrs::{BaseAttr, KeyAttr, KeyNameAttr, StructAttr, StructStorage};
+use crate::common::{self, EnumType};
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use std::collections::HashMap;
+use syn::{DataEnum, Fields, Ident, Variant};
+
+/// Name of the accessors trait generated for an enum
+pub(crate) fn accessors_trait_name(enum_type: &EnumType) -> Ident {
+    format_ident!("{}Accessors", enum_type.enum_name)
+}
+
+/// Patterns, types and names needed to access the payload of a single variant
+pub(crate) struct VariantAccessor {
+    /// `snake_case` name of the variant used as suffix of the accessors
+    pub(crate) suffix: String,
+
+    /// Pattern binding every field of the variant (`(__field0, ..)` or `{ name: __field0, .. }`), empty for unit variants
+    ///
+    /// It is also a valid expression building the variant from the bindings
+    pub(crate) pattern: TokenStream,
+
+    /// Expression building the variant from the parameters of the setter
+    pub(crate) rebuilt_pattern: TokenStream,
+
+    /// Names of the bindings of `pattern`
+    pub(crate) bindings: Vec<Ident>,
+
+    /// Names of the parameters of the setter
+    pub(crate) params: Vec<Ident>,
+
+    /// Types of the fields of the variant
+    pub(crate) types: Vec<syn::Type>,
+}
+
+impl VariantAccessor {
+    pub(crate) fn new(variant: &Variant) -> Self {
+        let bindings: Vec<Ident> = (0..variant.fields.len()).map(|i| format_ident!("__field{}", i)).collect();
+        let types = variant.fields.iter().map(|field| field.ty.clone()).collect();
+
+        let (pattern, rebuilt_pattern, params) = match &variant.fields {
+            Fields::Named(fields) => {
+                let names: Vec<Ident> = fields.named.iter().map(|field| field.ident.clone().unwrap()).collect();
+                (quote!({ #(#names: #bindings),* }), quote!({ #(#names),* }), names)
+            }
+            Fields::Unnamed(fields) => {
+                let params: Vec<Ident> = if fields.unnamed.len() == 1 {
+                    vec![format_ident!("value")]
+                } else {
+                    (0..fields.unnamed.len()).map(|i| format_ident!("value{}", i)).collect()
+                };
+                (quote!((#(#bindings),*)), quote!((#(#params),*)), params)
+            }
+            Fields::Unit => (quote!(), quote!(), vec![]),
+        };
+
+        VariantAccessor {
+            suffix: common::to_snake_case(&variant.ident.to_string()),
+            pattern,
+            rebuilt_pattern,
+            bindings,
+            params,
+            types,
+        }
+    }
+
+    /// Type of the payload: `()`, the type of the single field or a tuple of the types of the fields
+    pub(crate) fn payload_type(&self) -> TokenStream {
+        let types = &self.types;
+        tupled(&types.iter().map(|ty| quote!(#ty)).collect::<Vec<_>>())
+    }
+
+    /// Type of the payload borrowed field by field
+    pub(crate) fn payload_ref_type(&self) -> TokenStream {
+        let types = &self.types;
+        tupled(&types.iter().map(|ty| quote!(&#ty)).collect::<Vec<_>>())
+    }
+
+    /// Type of the payload mutably borrowed field by field
+    pub(crate) fn payload_mut_type(&self) -> TokenStream {
+        let types = &self.types;
+        tupled(&types.iter().map(|ty| quote!(&mut #ty)).collect::<Vec<_>>())
+    }
+
+    /// Payload built from (or destructured into) the bindings of `pattern`
+    pub(crate) fn payload_bindings(&self) -> TokenStream {
+        let bindings = &self.bindings;
+        tupled(&bindings.iter().map(|b| quote!(#b)).collect::<Vec<_>>())
+    }
+
+    /// Payload built from the parameters of the setter
+    fn payload_params(&self) -> TokenStream {
+        let params = &self.params;
+        tupled(&params.iter().map(|p| quote!(#p)).collect::<Vec<_>>())
+    }
+}
+
+/// Wraps the items in a tuple, unless there is exactly one
+fn tupled(items: &[TokenStream]) -> TokenStream {
+    if items.len() == 1 {
+        items[0].clone()
+    } else {
+        quote!((#(#items),*))
+    }
+}
+
+/// Names of the accessors generated for a variant, `get_v_mut` is left out for unit variants
+fn accessor_names(variant: &Variant) -> Vec<String> {
+    let suffix = common::to_snake_case(&variant.ident.to_string());
+    let get_mut = (!variant.fields.is_empty()).then(|| format!("get_{suffix}_mut"));
+
+    [Some(format!("get_{suffix}")), get_mut, Some(format!("set_{suffix}")), Some(format!("take_{suffix}"))]
+        .into_iter()
+        .flatten()
+        .collect()
+}
+
+/// Reports every variant generating an accessor already generated for a previous variant
+///
+/// For example `Value` and `ValueMut` with a payload both generate `get_value_mut`
+pub(crate) fn check_accessor_names(enum_data: &DataEnum) -> darling::Result<()> {
+    let mut errors = darling::Error::accumulator();
+    let mut names: HashMap<String, &Ident> = HashMap::new();
+
+    for variant in &enum_data.variants {
+        for name in accessor_names(variant) {
+            if let Some(previous) = names.insert(name.clone(), &variant.ident) {
+                errors.push(darling::Error::custom(
+                    format!("the accessor `{name}` of variant `{}` is also generated for variant `{previous}`", variant.ident),
+                ).with_span(&variant.ident));
+            }
+        }
+    }
+
+    errors.finish()
+}
+
+/// Generates the typed accessors of every variant payload
+///
+/// For each variant `V` of the enum, the `{Enum}Accessors` trait defines
+/// `get_v`, `get_v_mut` (except for unit variants), `set_v` and `take_v`.
+/// Payloads with several fields are returned as tuples, in the declaration order of the fields.
+/// Variants generating the same accessor name are rejected beforehand by [check_accessor_names].
+/// The accessors of a variant sharing its key only see that variant, except `set_v` which returns the replaced value of any of them.
+///
+/// The accessors go through the `VariantMap` implementation of `map`,
+/// except for a `StructMap` (given with `struct_attr`) with [StructStorage::Payload], they directly access its fields
+///
+/// # Returns
+///
+/// `(trait_definition, trait_impl)` the definition must be placed next to the Key enum,
+/// the implementation for the `map` type in the `const` block
+pub(crate) fn generate_accessors(
+    map_attr: &BaseAttr,
+    struct_attr: Option<&StructAttr>,
+    enum_type: &EnumType,
+    enum_data: &DataEnum,
+    key_enum_name: &Ident,
+    map: &TokenStream,
+) -> (TokenStream, TokenStream) {
+    let EnumType { enum_name, generics, .. } = enum_type;
+    let trait_name = accessors_trait_name(enum_type);
+
+    let (impl_generics, type_generics, where_clause) = generics.split_for_impl();
+
+    // the values of a variant keyed by one of its fields are reached through their key
+    let variants = enum_data.variants.iter().filter(|variant| KeyAttr::field_of(variant).is_none());
+    let shares_key = |variant: &Variant| KeyNameAttr::sharing_key(variant, enum_data).len() > 1;
+
+    let (signatures, bodies): (Vec<TokenStream>, Vec<TokenStream>) = variants.map(|variant| {
+        let key_name = KeyNameAttr::of(variant)
+            .key_name(variant);
+        let variant_name = &variant.ident;
+        let accessor = VariantAccessor::new(variant);
+        let VariantAccessor { suffix, pattern, rebuilt_pattern, params, types, .. } = &accessor;
+
+        let get = format_ident!("get_{}", suffix);
+        let get_mut = format_ident!("get_{}_mut", suffix);
+        let set = format_ident!("set_{}", suffix);
+        let take = format_ident!("take_{}", suffix);
+
+        let owned = accessor.payload_type();
+        let refs = accessor.payload_ref_type();
+        let refs_mut = accessor.payload_mut_type();
+        let payload = accessor.payload_bindings();
+
+        let get_doc = format!("Payload of the `{variant_name}` variant if present");
+        let get_mut_doc = format!("Mutable payload of the `{variant_name}` variant if present");
+        // the slot of a shared key may hold another variant, `set` gives it back whole as `insert` does
+        let shared = shares_key(variant);
+        let set_doc = if shared {
+            format!("Store a `{variant_name}` variant, returning the previous value of the key `{key_name}`, of any variant sharing it")
+        } else {
+            format!("Store a `{variant_name}` variant, returning the previous payload if any")
+        };
+        let set_owned = if shared { quote!(#enum_name #type_generics) } else { owned.clone() };
+        let take_doc = format!("Remove the `{variant_name}` variant, returning its payload if it was present");
+
+        let get_mut_signature = if types.is_empty() { None } else {
+            Some(quote! {
+                #[doc = #get_mut_doc]
+                fn #get_mut(&mut self) -> Option<#refs_mut>;
+            })
+        };
+
+        let signature = quote! {
+            #[doc = #get_doc]
+            fn #get(&self) -> Option<#refs>;
+
+            #get_mut_signature
+
+            #[doc = #set_doc]
+            fn #set(&mut self, #(#params: #types),*) -> Option<#set_owned>;
+
+            #[doc = #take_doc]
+            fn #take(&mut self) -> Option<#owned>;
+        };
+
+        // the slot of a shared key may hold another variant, which `take` leaves in place
+        let check_take = shared.then(|| {
+            let skip_fields = match &variant.fields {
+                Fields::Named(_) => Some(quote!({ .. })),
+                Fields::Unnamed(_) => Some(quote!((..))),
+                Fields::Unit => None,
+            };
+            quote! {
+                if !matches!(VariantMap::get(self, &#key_enum_name::#key_name), Some(#enum_name::#variant_name #skip_fields)) {
+                    return None;
+                }
+            }
+        });
+
+        let payload_struct = struct_attr.filter(|struct_attr| struct_attr.storage() == StructStorage::Payload);
+        let (get_body, get_mut_body, set_body, take_body) = match payload_struct {
+            None => (
+                quote! {
+                    match VariantMap::get(self, &#key_enum_name::#key_name) {
+                        Some(#enum_name::#variant_name #pattern) => Some(#payload),
+                        _ => None,
+                    }
+                },
+                // the accessor only exposes the payload, the value cannot be replaced with another variant
+                quote! {
+                    match _variant_map::__private::UncheckedMut::get_mut_unchecked(self, &#key_enum_name::#key_name) {
+                        Some(#enum_name::#variant_name #pattern) => Some(#payload),
+                        _ => None,
+                    }
+                },
+                if shared {
+                    quote! { VariantMap::insert(self, #enum_name::#variant_name #rebuilt_pattern) }
+                } else {
+                    quote! {
+                        match VariantMap::insert(self, #enum_name::#variant_name #rebuilt_pattern) {
+                            Some(#enum_name::#variant_name #pattern) => Some(#payload),
+                            _ => None,
+                        }
+                    }
+                },
+                quote! {
+                    #check_take
+                    match VariantMap::remove(self, &#key_enum_name::#key_name) {
+                        Some(#enum_name::#variant_name #pattern) => Some(#payload),
+                        _ => None,
+                    }
+                },
+            ),
+            Some(struct_attr) => {
+                // a single field payload is already the expected reference
+                let split_fields = if types.len() == 1 { None } else {
+                    Some(quote!(.map(|#payload| #payload)))
+                };
+                let value = accessor.payload_params();
+                let field = struct_attr.field_name(&key_name);
+                (
+                    quote! { self.#field.as_ref() #split_fields },
+                    quote! { self.#field.as_mut() #split_fields },
+                    quote! { self.#field.replace(#value) },
+                    quote! { self.#field.take() },
+                )
+            }
+        };
+
+        let get_mut_body = if types.is_empty() { None } else {
+            Some(quote! {
+                fn #get_mut(&mut self) -> Option<#refs_mut> {
+                    #get_mut_body
+                }
+            })
+        };
+
+        let body = quote! {
+            fn #get(&self) -> Option<#refs> {
+                #get_body
+            }
+
+            #get_mut_body
+
+            fn #set(&mut self, #(#params: #types),*) -> Option<#set_owned> {
+                #set_body
+            }
+
+            fn #take(&mut self) -> Option<#owned> {
+                #take_body
+            }
+        };
+
+        (signature, body)
+    }).unzip();
+
+    let vis = &map_attr.visibility;
+    let trait_doc = format!("Typed accessors to the payload of each variant of [{enum_name}] stored in its map");
+
+    let trait_definition = quote! {
+        #[doc = #trait_doc]
+        #[allow(dead_code)]
+        #vis trait #trait_name #impl_generics #where_clause {
+            #(#signatures)*
+        }
+    };
+
+    let trait_impl = quote! {
+        #[automatically_derived]
+        #[allow(unreachable_patterns)]
+        impl #impl_generics #trait_name #type_generics for #map #where_clause {
+            #(#bodies)*
+        }
+    };
+
+    (trait_definition, trait_impl)
+}
@@ -1,64 +1,272 @@
 use std::ops::Deref;
+use darling::ast::NestedMeta;
 use darling::{Error, FromDeriveInput, FromMeta, FromVariant};
-use darling::util::PathList;
-use proc_macro2::TokenStream;
+use darling::util::{Override, PathList};
+use proc_macro2::{TokenStream, TokenTree};
 use quote::{format_ident, quote, ToTokens};
-use syn::{DeriveInput, Expr, Ident, Lit, Variant, Visibility};
-use crate::common::EnumType;
+use syn::ext::IdentExt;
+use syn::parse::ParseStream;
+use syn::{parse_quote, Data, DataEnum, DeriveInput, Expr, ExprLit, Field, Ident, Lit, Member, Path, Token, Variant, Visibility};
+use crate::common::{self, EnumType};
 
 /// Attribute macro `key_name`
 /// Applied on an enum variant to specify its key's name in the `code` and when de/serialized by [serde]
 ///
+/// `skip` excludes the variant from the keys and the maps: it has no key, no `StructMap` field and no accessors.
+/// Inserting it gives it back instead of storing it, `try_insert` tells it apart with `Err`,
+/// and deserializing it fails or drops it, see the `skipped` parameter of [VariantStore][BaseAttr].
+/// `#[variant_store(skip)]` is the same, see [VariantStoreAttr]
+///
+/// Variants with the same `code` share their key: they are stored in the same slot, inserting one replaces the other.
+/// They must have the same `serde` name
+///
 /// # Example
 ///
 /// ```
 /// use variant_map_derive::VariantStore;
 ///
 /// #[derive(VariantStore)]
+/// #[VariantStore(keys(derive(::serde::Serialize)))]
 /// enum MyEnum {
 ///     A,
 ///     #[key_name(code = "Bamboo", serde = "bamboo")]
-///     B
+///     B,
+///     #[key_name(skip)]
+///     Unknown,
+///     #[key_name(code = "Network")]
+///     Wifi(String),
+///     #[key_name(code = "Network")]
+///     Ethernet,
 /// }
 ///
 /// fn main() {
+///     use variant_map::common::{MapValue, VariantMap};
+///
 ///     let key: MyEnumKey = MyEnumKey::Bamboo;
-///     assert_eq!("bamboo", serde_json::to_string(&key).unwrap().as_str());
+///     assert_eq!("\"bamboo\"", serde_json::to_string(&key).unwrap().as_str());
+///
+///     let mut map = MyEnum::make_map();
+///     assert!(matches!(map.try_insert(MyEnum::Unknown), Err(MyEnum::Unknown)));
+///     assert!(map.is_empty());
+///
+///     assert!(matches!(map.insert(MyEnum::Unknown), Some(MyEnum::Unknown)));
+///     assert!(map.is_empty());
+///
+///     map.insert(MyEnum::Wifi("home".to_string()));
+///     assert!(matches!(map.insert(MyEnum::Ethernet), Some(MyEnum::Wifi(_))));
+///     assert!(matches!(map.get(&MyEnumKey::Network), Some(MyEnum::Ethernet)));
 /// }
 /// ```
 #[derive(FromVariant, Default, Debug)]
 #[darling(default, attributes(key_name))]
 pub(crate) struct KeyNameAttr {
     /// Name of the enum variant in the code
-    code: Option<String>,
+    #[darling(with = parse_ident)]
+    code: Option<Ident>,
 
     /// Name of the enum variant when de(serialized) by [serde]
-    serde: Option<String>
+    serde: Option<String>,
+
+    /// Exclude the variant from the keys
+    skip: Option<()>,
 }
 
 impl KeyNameAttr {
+    /// Parameters of the `key_name` attribute of `variant`
+    ///
+    /// The attributes of every variant are validated by [crate::derive] before any code is generated,
+    /// so they are known to be well-formed here
+    pub(crate) fn of(variant: &Variant) -> Self {
+        let mut attr = Self::from_variant(variant).unwrap_or_default();
+        if VariantStoreAttr::from_variant(variant).is_ok_and(|variant_store| variant_store.skip.is_some()) {
+            attr.skip = Some(());
+        }
+        attr
+    }
+
     pub(crate) fn key_name(&self, variant: &Variant) -> Ident {
-        self.code
-            .as_ref()
-            .map(|code| format_ident!("{}", code))
+        KeyAttr::of(variant)
+            .and_then(|key| key.key_name())
+            .or_else(|| self.code.clone())
             .unwrap_or_else(|| variant.ident.clone())
     }
 
     pub(crate) fn serde_rename(&self) -> &Option<String> {
         &self.serde
     }
+
+    pub(crate) fn is_skipped(&self) -> bool {
+        self.skip.is_some()
+    }
+
+    /// Variants of the enum stored under the key of `variant`, including itself
+    pub(crate) fn sharing_key<'a>(variant: &Variant, enum_data: &'a DataEnum) -> Vec<&'a Variant> {
+        let key_name = Self::of(variant).key_name(variant);
+        enum_data.variants.iter()
+            .filter(|other| {
+                let other_attr = Self::of(other);
+                !other_attr.is_skipped() && other_attr.key_name(other) == key_name
+            })
+            .collect()
+    }
+
+    /// Whether variants of the enum share their key
+    pub(crate) fn any_shared(enum_data: &DataEnum) -> bool {
+        enum_data.variants.iter()
+            .filter(|variant| !Self::of(variant).is_skipped())
+            .any(|variant| Self::sharing_key(variant, enum_data).len() > 1)
+    }
+}
+
+/// Attribute macro `variant_store`
+/// Applied on an enum variant, `#[variant_store(skip)]` skips it as `#[key_name(skip)]` does, see [KeyNameAttr]
+///
+/// # Example
+///
+/// ```
+/// use variant_map::common::{MapValue, VariantMap};
+/// use variant_map_derive::VariantStore;
+///
+/// #[derive(Debug, PartialEq, VariantStore)]
+/// enum Status {
+///     Online,
+///     #[variant_store(skip)]
+///     Unknown,
+/// }
+///
+/// fn main() {
+///     assert_eq!(StatusKey::ALL, [StatusKey::Online]);
+///     assert!(Status::Unknown.try_to_key().is_err());
+///     assert_eq!(Status::make_map().try_insert(Status::Unknown), Err(Status::Unknown));
+/// }
+/// ```
+#[derive(FromVariant, Default, Debug)]
+#[darling(default, attributes(variant_store))]
+pub(crate) struct VariantStoreAttr {
+    /// Exclude the variant from the keys
+    skip: Option<()>,
+}
+
+/// Attribute macro `key`
+/// Applied on an enum variant to select its key
+///
+/// `#[key(Kind::Foo)]` : the path to a unit variant of the type given with `keys(use = "...")`, see [BaseKeysAttr].
+/// Variants with the same key share it, as with the `code` of [KeyNameAttr]
+///
+/// `#[key(by = 0)]` or `#[key(by = field)]` : the generated key variant carries the value of a field of the variant,
+/// so each value of the field gets its own slot. Only available with the `HashMap` and `BTreeMap` datastructs.
+/// The keys cannot be enumerated, so they do not implement `VariantKey`
+/// and the `strict`, `serde_format = "object"` and `total` parameters are not available.
+/// The variant has no typed accessors, its values are reached through their key
+///
+/// # Example
+///
+/// ```
+/// use variant_map::common::MapValue;
+/// use variant_map_derive::VariantStore;
+///
+/// #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+/// struct SensorId(u8);
+///
+/// #[derive(Debug, PartialEq, VariantStore)]
+/// enum Reading {
+///     #[key(by = 0)]
+///     Sensor(SensorId, f32),
+///     Offline,
+/// }
+///
+/// fn main() {
+///     let mut map = Reading::make_map();
+///     map.insert(Reading::Sensor(SensorId(1), 0.5));
+///     map.insert(Reading::Sensor(SensorId(2), 1.5));
+///     assert_eq!(Reading::Sensor(SensorId(2), 0.0).to_key(), ReadingKey::Sensor(SensorId(2)));
+///     assert_eq!(map.get(&ReadingKey::Sensor(SensorId(1))), Some(&Reading::Sensor(SensorId(1), 0.5)));
+///     assert_eq!(map.len(), 2);
+/// }
+/// ```
+pub(crate) enum KeyAttr {
+    /// Variant of a user-supplied type of keys
+    Path(Path),
+    /// Field of the variant carried by its key
+    By(Member),
+}
+
+impl KeyAttr {
+    /// Parses the `key` attribute of `variant`, if it has one
+    pub(crate) fn from_variant(variant: &Variant) -> darling::Result<Option<Self>> {
+        let mut attrs = variant.attrs.iter().filter(|attr| attr.path().is_ident("key"));
+
+        let Some(attr) = attrs.next() else {
+            return Ok(None);
+        };
+        if let Some(duplicate) = attrs.next() {
+            return Err(Error::custom("duplicate `key` attribute").with_span(duplicate));
+        }
+
+        attr.parse_args_with(|input: ParseStream| {
+            if input.peek(Ident) && input.peek2(Token![=]) {
+                let by: Ident = input.parse()?;
+                if by != "by" {
+                    return Err(syn::Error::new(by.span(), "expected `by = <field>` or the path to a key"));
+                }
+                input.parse::<Token![=]>()?;
+                input.parse().map(KeyAttr::By)
+            } else {
+                input.parse().map(KeyAttr::Path)
+            }
+        })
+            .map(Some)
+            .map_err(Error::from)
+    }
+
+    /// Key of `variant`
+    ///
+    /// The attributes of every variant are validated by [crate::derive] before any code is generated
+    pub(crate) fn of(variant: &Variant) -> Option<Self> {
+        Self::from_variant(variant).ok().flatten()
+    }
+
+    /// Name of the variant of a user-supplied key type, the last segment of the path
+    pub(crate) fn key_name(&self) -> Option<Ident> {
+        match self {
+            // a parsed path has at least one segment
+            KeyAttr::Path(path) => Some(path.segments.last().unwrap().ident.clone()),
+            KeyAttr::By(_) => None,
+        }
+    }
+
+    /// Field of `variant` carried by its key and its position in the fields
+    pub(crate) fn field_of(variant: &Variant) -> Option<(usize, &Field)> {
+        let KeyAttr::By(member) = Self::of(variant)? else {
+            return None;
+        };
+
+        variant.fields.iter().enumerate().find(|(index, field)| match (&member, &field.ident) {
+            (Member::Named(name), Some(ident)) => name == ident,
+            (Member::Unnamed(position), None) => position.index as usize == *index,
+            _ => false,
+        })
+    }
+
+    /// Whether a variant of the enum has a key carrying one of its fields
+    pub(crate) fn any_field(enum_data: &DataEnum) -> bool {
+        enum_data.variants.iter().any(|variant| matches!(Self::of(variant), Some(KeyAttr::By(_))))
+    }
 }
 
 /// Parameters of the [crate::VariantStore] macro
 ///
 /// # Arguments
 ///
-/// `datastruct` : any of { `HashMap`, `BTreeMap`, `StructMap` }
+/// `datastruct` : any of { `HashMap`, `BTreeMap`, `ArrayMap`, `StructMap`, `MultiMap` }
 ///
 /// default is `HashMap`
 ///
+/// with `MultiMap` the enum implements [variant_map::multimap::MultiMapValue],
+/// its [Map][variant_map::common::MapValue::Map] holding one value per key is an `ArrayMap`
 ///
-/// `keys` : specify the parameters for the generated enum of keys
+///
+/// `keys` : specify the parameters for the generated enum of keys, or use an existing type of keys
 ///
 /// see [crate::attrs::BaseKeysAttr]
 ///
@@ -68,6 +276,33 @@ impl KeyNameAttr {
 ///
 /// default is private
 ///
+///
+/// `serde_format` : any of { `seq`, `object` }, see [SerdeFormat]
+///
+/// default is `seq`
+///
+///
+/// `strict` : reject duplicated variants when deserializing, see [StrictAttr]
+///
+/// default is not strict
+///
+///
+/// `total` : also generate a map holding every variant, see [TotalAttr]
+///
+/// default is not generated
+///
+///
+/// `skipped` : any of { `error`, `ignore` }, whether deserializing a variant skipped with `#[key_name(skip)]` fails or drops it,
+/// see [SkipPolicy]
+///
+/// default is `error`
+///
+///
+/// `crate` : path to `variant_map` used by every generated path, including `serde`,
+/// when the crate is renamed or re-exported from another crate
+///
+/// default is `variant_map`
+///
 /// # Example
 ///
 /// ```
@@ -87,15 +322,210 @@ impl KeyNameAttr {
 #[derive(Default, Debug, FromDeriveInput)]
 #[darling(default, attributes(VariantStore))]
 pub(crate) struct BaseAttr {
-    /// [Type of the data structure][MapType] generated as a String
-    pub(crate) datastruct: Option<String>,
+    /// [Type of the data structure][MapType] generated
+    pub(crate) datastruct: Option<MapType>,
 
     /// Name of the generate Key enum
+    #[darling(with = parse_keys)]
     pub(crate) keys: Option<BaseKeysAttr>,
 
     /// Visibility of the generated Key enum and other structs
-    #[darling(with="parse_visibility")]
+    #[darling(with = parse_visibility)]
     pub(crate) visibility: OptionalVisibility,
+
+    /// [Format][SerdeFormat] of the de/serialized map
+    pub(crate) serde_format: Option<SerdeFormat>,
+
+    /// [Strict][StrictAttr] deserialization, either a single word or a list of parameters
+    pub(crate) strict: Option<Override<StrictAttr>>,
+
+    /// [Total map][TotalAttr] generation, either a single word or a list of parameters
+    pub(crate) total: Option<Override<TotalAttr>>,
+
+    /// Path to the `variant_map` crate
+    #[darling(rename = "crate", with = parse_path)]
+    pub(crate) crate_path: Option<Path>,
+
+    /// [Deserialization][SkipPolicy] of the skipped variants
+    pub(crate) skipped: Option<SkipPolicy>,
+}
+
+/// Parameters of the total map given in [BaseAttr] (`VariantStore` parameter macro)
+///
+/// A total map holds exactly one value of every variant, its lookups never fail.
+/// It is built from a builder or converted from the partial map of the enum with [TryFrom],
+/// both reporting the missing keys in a [variant_map::total::MissingKeys]
+///
+/// # Arguments
+///
+/// `name` : name of the generated struct, default is `{Enum}TotalMap`, its builder is named `{name}Builder`
+///
+/// `derive` : additional derives on the total map
+///
+/// # Example
+///
+/// ```
+/// use variant_map_derive::VariantStore;
+///
+/// #[derive(Debug, PartialEq, VariantStore)]
+/// #[VariantStore(total(name = "Regions", derive(Debug)))]
+/// enum Region {
+///     Europe(u32),
+///     Asia(u32),
+/// }
+///
+/// fn main() {
+///     let regions = Regions::builder().with(Region::Europe(1)).with(Region::Asia(2)).build().unwrap();
+///     assert_eq!(regions.get(&RegionKey::Asia), &Region::Asia(2));
+///     assert_eq!(regions.get_europe(), &1);
+///
+///     let missing = Regions::builder().with(Region::Asia(2)).build().unwrap_err();
+///     assert_eq!(missing.to_string(), "missing keys `Europe`");
+/// }
+/// ```
+#[derive(Debug, Default, Clone, FromMeta)]
+pub(crate) struct TotalAttr {
+    #[darling(with = parse_ident)]
+    pub(crate) name: Option<Ident>,
+    pub(crate) derive: Option<PathList>,
+}
+
+/// Parameters of the strict deserialization given in [BaseAttr] (`VariantStore` parameter macro)
+///
+/// A strict map fails to deserialize when a variant is present twice, the error names the duplicated key.
+/// The [Map][variant_map::common::MapValue::Map] of the enum is wrapped in a [variant_map::strict::Strict],
+/// a `StructMap` directly checks its input
+///
+/// # Arguments
+///
+/// `require` : names of the keys which must be present after deserialization
+///
+/// # Example
+///
+/// ```
+/// use serde::{Deserialize, Serialize};
+/// use variant_map_derive::VariantStore;
+///
+/// #[derive(Debug, Serialize, Deserialize, VariantStore)]
+/// #[VariantStore(strict(require(Timeout)))]
+/// enum Setting {
+///     Timeout(u32),
+///     Retries(u8),
+/// }
+///
+/// fn main() {
+///     let error = serde_json::from_str::<<Setting as variant_map::common::MapValue>::Map>(r#"[{"Retries":3}]"#)
+///         .unwrap_err();
+///     assert!(error.to_string().starts_with("missing required key `Timeout`"));
+/// }
+/// ```
+#[derive(Debug, Default, FromMeta)]
+pub(crate) struct StrictAttr {
+    pub(crate) require: Option<PathList>,
+}
+
+/// How the map is de/serialized, selected with the `serde_format` attribute of [VariantStore][BaseAttr]
+///
+/// [SerdeFormat::Seq] is from value `seq`: a sequence of the stored variants
+///
+/// [SerdeFormat::Object] is from value `object`: an object mapping the serde name of each key to the payload of its variant.
+/// The [Map][variant_map::common::MapValue::Map] of the enum is wrapped in a [variant_map::object::Object],
+/// a `StructMap` directly de/serializes as an object
+///
+/// # Example
+///
+/// ```
+/// use serde::{Deserialize, Serialize};
+/// use variant_map::common::MapValue;
+/// use variant_map_derive::VariantStore;
+///
+/// #[derive(Serialize, Deserialize, VariantStore)]
+/// #[VariantStore(serde_format = "object")]
+/// enum Setting {
+///     #[key_name(serde = "timeout")]
+///     Timeout(u32),
+/// }
+///
+/// fn main() {
+///     let mut settings = Setting::make_map();
+///     settings.insert(Setting::Timeout(30));
+///     assert_eq!(serde_json::to_string(&settings).unwrap(), r#"{"timeout":30}"#);
+/// }
+/// ```
+#[derive(Default, Debug, Clone, Copy, PartialEq)]
+pub(crate) enum SerdeFormat {
+    #[default]
+    Seq,
+    Object,
+}
+
+impl TryFrom<&String> for SerdeFormat {
+    type Error = String;
+
+    fn try_from(value: &String) -> Result<Self, Self::Error> {
+        match value.to_lowercase().as_str() {
+            "seq" => Ok(Self::Seq),
+            "object" => Ok(Self::Object),
+            _ => Err("Invalid 'serde_format' argument, available { \"seq\", \"object\" }".into()),
+        }
+    }
+}
+
+impl FromMeta for SerdeFormat {
+    fn from_string(value: &str) -> darling::Result<Self> {
+        Self::try_from(&value.to_string()).map_err(Error::custom)
+    }
+}
+
+/// How the maps deserialize a variant skipped with `#[key_name(skip)]`, selected with the `skipped` attribute of [VariantStore][BaseAttr]
+///
+/// [SkipPolicy::Error] is from value `error`: the deserialization fails
+///
+/// [SkipPolicy::Ignore] is from value `ignore`: the variant is dropped
+///
+/// # Example
+///
+/// ```
+/// use serde::{Deserialize, Serialize};
+/// use variant_map::common::MapValue;
+/// use variant_map_derive::VariantStore;
+///
+/// #[derive(Debug, Serialize, Deserialize, VariantStore)]
+/// #[VariantStore(skipped = "ignore")]
+/// enum Setting {
+///     Timeout(u32),
+///     #[key_name(skip)]
+///     Unknown,
+/// }
+///
+/// fn main() {
+///     let settings: <Setting as MapValue>::Map = serde_json::from_str(r#"[{"Timeout":3},"Unknown"]"#).unwrap();
+///     assert_eq!(settings.len(), 1);
+/// }
+/// ```
+#[derive(Default, Debug, Clone, Copy, PartialEq)]
+pub(crate) enum SkipPolicy {
+    #[default]
+    Error,
+    Ignore,
+}
+
+impl TryFrom<&String> for SkipPolicy {
+    type Error = String;
+
+    fn try_from(value: &String) -> Result<Self, String> {
+        match value.to_lowercase().as_str() {
+            "error" => Ok(Self::Error),
+            "ignore" => Ok(Self::Ignore),
+            _ => Err("Invalid 'skipped' argument, available { \"error\", \"ignore\" }".into()),
+        }
+    }
+}
+
+impl FromMeta for SkipPolicy {
+    fn from_string(value: &str) -> darling::Result<Self> {
+        Self::try_from(&value.to_string()).map_err(Error::custom)
+    }
 }
 
 /// Either an [OptionalVisibility::OutOfScope] or a classic [Visibility]
@@ -149,12 +579,51 @@ pub(crate) fn parse_visibility(meta: &syn::Meta) -> Result<OptionalVisibility, E
 
     let optional_visibility = match literal_str.as_str() {
         "out-of-scope" => OptionalVisibility::OutOfScope,
-        _ => OptionalVisibility::Specified(Visibility::from_expr(&value)?)
+        _ => OptionalVisibility::Specified(Visibility::from_expr(value)?)
     };
 
     Ok(optional_visibility)
 }
 
+/// Parses an identifier given as a String literal, such as the name of a generated type or of a key
+///
+/// The error points to the literal when it is not a valid identifier
+pub(crate) fn parse_ident(meta: &syn::Meta) -> Result<Option<Ident>, Error> {
+    let name = String::from_meta(meta)?;
+    let value = &meta.require_name_value()?.value;
+
+    syn::parse_str::<Ident>(&name)
+        .map(Some)
+        .map_err(|_| Error::custom(format!("`{name}` is not a valid identifier")).with_span(value))
+}
+
+/// Parses a path given as a String literal, such as the path to a crate
+///
+/// The path is spanned on the literal, so errors in the generated code point to the attribute
+pub(crate) fn parse_path(meta: &syn::Meta) -> Result<Option<Path>, Error> {
+    let value = &meta.require_name_value()?.value;
+
+    match value {
+        Expr::Lit(ExprLit { lit: Lit::Str(literal), .. }) => literal
+            .parse::<Path>()
+            .map(Some)
+            .map_err(|_| Error::custom(format!("`{}` is not a valid path", literal.value())).with_span(value)),
+        _ => Err(Error::unexpected_expr_type(value)),
+    }
+}
+
+/// Parses the [BaseKeysAttr] of [BaseAttr]
+///
+/// `use` is a keyword which cannot name a parameter, it is read as the raw identifier `r#use`
+pub(crate) fn parse_keys(meta: &syn::Meta) -> Result<Option<BaseKeysAttr>, Error> {
+    let tokens = meta.require_list()?.tokens.clone().into_iter().map(|token| match token {
+        TokenTree::Ident(ident) if ident == "use" => TokenTree::Ident(Ident::new_raw("use", ident.span())),
+        token => token,
+    }).collect::<TokenStream>();
+
+    BaseKeysAttr::from_list(&NestedMeta::parse_meta_list(tokens)?).map(Some)
+}
+
 /// Parameters of the Key enum given in [BaseAttr] (`VariantStore` parameter macro)
 ///
 /// # Arguments
@@ -163,6 +632,19 @@ pub(crate) fn parse_visibility(meta: &syn::Meta) -> Result<OptionalVisibility, E
 ///
 /// `derive` : additional derives on the Key Enum
 ///
+/// `use` : path to an existing enum used as keys instead of generating one,
+/// each variant selects its key with a [`key`][KeyAttr] attribute, two variants cannot share a key.
+/// The keys must cover every variant of the type, which the compiler checks.
+/// The type must implement `VariantKey` and the key trait of the `datastruct` (`HashKey`, `OrdHashKey`, ...).
+/// Its [index][variant_map::common::VariantKey::index] may differ from the order of the variants,
+/// so `use` is only combined with `serde_format = "object"` together with `implement`
+///
+/// `implement` : with `use`, also implements `VariantKey` and the key trait of the `datastruct` on the type,
+/// in the order of the variants of the enum
+///
+/// `rename_all` : [case][RenameRule] of the serde names of the keys, `rename_all(serde = "...", code = "...")`
+/// also converts their names in the code. The `serde` and `code` of a [key_name][KeyNameAttr] take precedence
+///
 /// # Example
 ///
 /// ```
@@ -178,43 +660,474 @@ pub(crate) fn parse_visibility(meta: &syn::Meta) -> Result<OptionalVisibility, E
 ///     let key: MySuperKeys = MySuperKeys::A;
 ///
 ///     // Thanks to the "derive(::serde::Serialize)"
-///     println!("{}", serde_json::to_string(&key));
+///     println!("{}", serde_json::to_string(&key).unwrap());
 ///     // see macro expansion to check that the used inner map is a BTreeMap
 ///     // and that the keys have pub(crate) visibility
 /// }
+/// ```
+///
+/// With an existing type of keys
+///
+/// ```
+/// use variant_map::common::MapValue;
+/// use variant_map_derive::VariantStore;
+///
+/// #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+/// enum Kind {
+///     Text,
+///     Number,
+/// }
+///
+/// #[derive(Debug, PartialEq, VariantStore)]
+/// #[VariantStore(keys(use = "Kind", implement), datastruct = "BTreeMap")]
+/// enum Value {
+///     #[key(Kind::Text)]
+///     Label(String),
+///     #[key(Kind::Number)]
+///     Count(u32),
+/// }
+///
+/// fn main() {
+///     let mut map = Value::make_map();
+///     map.insert(Value::Count(2));
+///     assert_eq!(map.get(&Kind::Number), Some(&Value::Count(2)));
+/// }
 #[derive(Debug, Default, FromMeta)]
 pub(crate) struct BaseKeysAttr {
-    pub(crate) name: Option<String>,
-    pub(crate) derive: Option<PathList>
+    #[darling(with = parse_ident)]
+    pub(crate) name: Option<Ident>,
+    pub(crate) derive: Option<PathList>,
+    #[darling(rename = "r#use", with = parse_path)]
+    pub(crate) path: Option<Path>,
+    pub(crate) implement: Option<()>,
+    pub(crate) rename_all: Option<RenameAllAttr>,
+}
+
+/// Cases of the names of the keys given with `keys(rename_all = ...)`, see [BaseKeysAttr]
+///
+/// A single case converts the serde names, `rename_all(serde = "...", code = "...")` selects the names it converts
+///
+/// # Example
+///
+/// ```
+/// use variant_map::common::VariantKey;
+/// use variant_map_derive::VariantStore;
+///
+/// #[derive(VariantStore)]
+/// #[VariantStore(keys(rename_all(serde = "kebab-case", code = "SCREAMING_SNAKE_CASE")))]
+/// enum Setting {
+///     DarkMode(bool),
+///     #[key_name(serde = "size")]
+///     FontSize(u8),
+/// }
+///
+/// fn main() {
+///     assert_eq!(SettingKey::DARK_MODE.name(), "dark-mode");
+///     assert_eq!(SettingKey::FONT_SIZE.name(), "size");
+/// }
+/// ```
+#[derive(Debug, Default, Clone, Copy)]
+pub(crate) struct RenameAllAttr {
+    serde: Option<RenameRule>,
+    code: Option<RenameRule>,
+}
+
+impl FromMeta for RenameAllAttr {
+    fn from_string(value: &str) -> darling::Result<Self> {
+        Ok(Self { serde: Some(RenameRule::from_string(value)?), code: None })
+    }
+
+    fn from_list(items: &[NestedMeta]) -> darling::Result<Self> {
+        #[derive(FromMeta)]
+        struct Rules {
+            serde: Option<RenameRule>,
+            code: Option<RenameRule>,
+        }
+
+        let Rules { serde, code } = Rules::from_list(items)?;
+        Ok(Self { serde, code })
+    }
+}
+
+/// Case conversion of a name, with the conventions of `rename_all` in serde
+///
+/// Names are converted from `PascalCase` exactly as serde converts the names of the variants:
+/// each uppercase letter starts a word, so `HTTPServer` is `h_t_t_p_server` in `snake_case` as in the names of the accessors,
+/// see [common::to_snake_case]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum RenameRule {
+    Lower,
+    Upper,
+    Pascal,
+    Camel,
+    Snake,
+    ScreamingSnake,
+    Kebab,
+    ScreamingKebab,
+}
+
+impl RenameRule {
+    /// `name` written in the case
+    pub(crate) fn apply(self, name: &str) -> String {
+        match self {
+            Self::Pascal => name.to_owned(),
+            Self::Lower => name.to_ascii_lowercase(),
+            Self::Upper => name.to_ascii_uppercase(),
+            Self::Camel => {
+                let mut chars = name.chars();
+                chars.next().map(|first| first.to_ascii_lowercase().to_string() + chars.as_str()).unwrap_or_default()
+            }
+            Self::Snake => common::to_snake_case(name),
+            Self::ScreamingSnake => Self::Snake.apply(name).to_ascii_uppercase(),
+            Self::Kebab => Self::Snake.apply(name).replace('_', "-"),
+            Self::ScreamingKebab => Self::ScreamingSnake.apply(name).replace('_', "-"),
+        }
+    }
+
+    /// `ident` written in the case, as a raw identifier if it becomes a keyword
+    ///
+    /// Fails for the kebab cases, which cannot write identifiers
+    pub(crate) fn apply_to_ident(self, ident: &Ident) -> Result<Ident, String> {
+        if matches!(self, Self::Kebab | Self::ScreamingKebab) {
+            return Err(format!("`{}` cannot write identifiers", self.as_str()));
+        }
+
+        let name = self.apply(&ident.unraw().to_string());
+        Ok(syn::parse_str::<Ident>(&name).unwrap_or_else(|_| Ident::new_raw(&name, ident.span())))
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Lower => "lowercase",
+            Self::Upper => "UPPERCASE",
+            Self::Pascal => "PascalCase",
+            Self::Camel => "camelCase",
+            Self::Snake => "snake_case",
+            Self::ScreamingSnake => "SCREAMING_SNAKE_CASE",
+            Self::Kebab => "kebab-case",
+            Self::ScreamingKebab => "SCREAMING-KEBAB-CASE",
+        }
+    }
+}
+
+impl TryFrom<&String> for RenameRule {
+    type Error = String;
+
+    fn try_from(value: &String) -> Result<Self, Self::Error> {
+        let rules = [
+            Self::Lower, Self::Upper, Self::Pascal, Self::Camel,
+            Self::Snake, Self::ScreamingSnake, Self::Kebab, Self::ScreamingKebab,
+        ];
+        rules.into_iter().find(|rule| rule.as_str() == value).ok_or_else(|| {
+            let names = rules.map(|rule| format!("\"{}\"", rule.as_str())).join(", ");
+            format!("Invalid case argument, available {{ {names} }}")
+        })
+    }
+}
+
+impl FromMeta for RenameRule {
+    fn from_string(value: &str) -> darling::Result<Self> {
+        Self::try_from(&value.to_string()).map_err(Error::custom)
+    }
 }
 
 impl BaseAttr {
+    /// Name of the Key enum in the generated code
+    ///
+    /// A user-supplied type is brought in scope of the `const` block under a private name by [BaseAttr::keys_import]
     pub(crate) fn keys_name(&self, enum_name: Ident) -> Ident {
+        if self.keys_path().is_some() {
+            return user_keys_alias();
+        }
+
         self.keys
             .as_ref()
-            .map(|attrs| attrs.name.as_ref())
-            .flatten()
-            .map(|name| format_ident!("{}", name))
+            .and_then(|attrs| attrs.name.clone())
             .unwrap_or(enum_name)
     }
 
+    /// Path to the user-supplied type of keys, none when the Key enum is generated
+    pub(crate) fn keys_path(&self) -> Option<&Path> {
+        self.keys
+            .as_ref()
+            .and_then(|attrs| attrs.path.as_ref())
+    }
+
+    /// Brings the user-supplied type of keys in scope of the `const` block
+    pub(crate) fn keys_import(&self) -> Option<TokenStream> {
+        let alias = user_keys_alias();
+        self.keys_path().map(|path| quote!(use #path as #alias;))
+    }
+
+    /// Whether the derive implements `VariantKey` and the key trait of the map on the keys,
+    /// always for the generated Key enum, only with `implement` for a user-supplied type
+    pub(crate) fn implements_keys(&self) -> bool {
+        self.keys
+            .as_ref()
+            .map_or(true, |attrs| attrs.path.is_none() || attrs.implement.is_some())
+    }
+
     pub(crate) fn keys_derive(&self) -> Option<TokenStream> {
         let path_list = self.keys
             .as_ref()
-            .map(|attrs| attrs.derive.as_ref())
-            .flatten();
+            .and_then(|attrs| attrs.derive.as_ref());
 
         get_derives(path_list)
 
     }
 
+    /// Whether the Key enum derives [Serialize][serde::Serialize] or [Deserialize][serde::Deserialize]
+    pub(crate) fn keys_derive_serde(&self) -> bool {
+        self.keys
+            .as_ref()
+            .and_then(|attrs| attrs.derive.as_ref())
+            .is_some_and(|list| list.iter().any(|path| {
+                path.segments.last().is_some_and(|segment| segment.ident == "Serialize" || segment.ident == "Deserialize")
+            }))
+    }
+
+    pub(crate) fn is_strict(&self) -> bool {
+        self.strict.is_some()
+    }
+
+    /// Names of the keys required by the [strict][StrictAttr] deserialization
+    pub(crate) fn required_keys(&self) -> Vec<&Path> {
+        match &self.strict {
+            Some(Override::Explicit(StrictAttr { require: Some(list) })) => list.iter().collect(),
+            _ => vec![],
+        }
+    }
+
+    /// Parameters of the [total map][TotalAttr], if it is generated
+    pub(crate) fn total(&self) -> Option<TotalAttr> {
+        self.total.clone().map(Override::unwrap_or_default)
+    }
+
+    pub(crate) fn serde_format(&self) -> SerdeFormat {
+        self.serde_format.unwrap_or_default()
+    }
+
+    fn rename_all(&self) -> RenameAllAttr {
+        self.keys
+            .as_ref()
+            .and_then(|attrs| attrs.rename_all)
+            .unwrap_or_default()
+    }
+
+    /// Whether the names of the keys in the code may not be `UpperCamelCase`
+    pub(crate) fn allows_non_camel_keys(&self) -> bool {
+        self.rename_all().code.is_some_and(|case| case != RenameRule::Pascal)
+    }
+
+    /// Applies `keys(rename_all = ...)` to the variants, as the `key_name` attributes they would have been given
+    ///
+    /// The variants already naming their key are left as is, so the generated code only reads [KeyNameAttr]
+    pub(crate) fn rename_keys(&self, ast: &DeriveInput, enum_data: &DataEnum) -> darling::Result<DataEnum> {
+        let RenameAllAttr { serde, code } = self.rename_all();
+        if let Some(Err(error)) = code.map(|case| case.apply_to_ident(&format_ident!("Key"))) {
+            return Err(error_on_attr(ast, "VariantStore", Error::custom(format!("`code` of rename_all {error}"))));
+        }
+
+        let variants = enum_data.variants.iter().map(|variant| {
+            let key_name_attr = KeyNameAttr::of(variant);
+            if key_name_attr.is_skipped() {
+                return variant.clone();
+            }
+
+            let key_name = key_name_attr.key_name(variant);
+            let mut renamed = variant.clone();
+            if let Some(serde) = serde.filter(|_| key_name_attr.serde.is_none()) {
+                let name = serde.apply(&key_name.unraw().to_string());
+                renamed.attrs.push(parse_quote!(#[key_name(serde = #name)]));
+            }
+            if let Some(ident) = self.renamed_code(&key_name_attr, variant) {
+                let name = ident.to_string();
+                renamed.attrs.push(parse_quote!(#[key_name(code = #name)]));
+            }
+            renamed
+        }).collect();
+
+        Ok(DataEnum {
+            variants,
+            ..enum_data.clone()
+        })
+    }
+
+    /// Name of the key of `variant` in the code converted by `rename_all(code = ...)`,
+    /// none without a case or when the variant names its key
+    fn renamed_code(&self, key_name_attr: &KeyNameAttr, variant: &Variant) -> Option<Ident> {
+        // the case is checked by [BaseAttr::rename_keys]
+        self.rename_all().code
+            .filter(|_| key_name_attr.code.is_none())
+            .and_then(|case| case.apply_to_ident(&key_name_attr.key_name(variant)).ok())
+    }
+
+    pub(crate) fn skip_policy(&self) -> SkipPolicy {
+        self.skipped.unwrap_or_default()
+    }
+
     pub(crate) fn map_type(&self) -> MapType {
-        if let Some(name) = &self.datastruct {
-            MapType::try_from(name).unwrap()
-        } else {
-            MapType::default()
+        self.datastruct.unwrap_or_default()
+    }
+
+    /// Brings the `variant_map` crate in scope of the generated code as `_variant_map`
+    pub(crate) fn crate_import(&self) -> TokenStream {
+        match &self.crate_path {
+            Some(path) => quote!(use #path as _variant_map;),
+            None => quote! {
+                #[allow(unused_extern_crates, clippy::useless_attribute)]
+                extern crate variant_map as _variant_map;
+            },
         }
     }
+
+    /// Rejects the parameters only available with the `serde` feature when it is disabled
+    pub(crate) fn check_serde(&self, ast: &DeriveInput) -> darling::Result<()> {
+        if self.strict.is_some() {
+            require_serde(ast, "VariantStore", "`strict`")?;
+        }
+        if self.serde_format.is_some() {
+            require_serde(ast, "VariantStore", "`serde_format`")?;
+        }
+        if self.skipped.is_some() {
+            require_serde(ast, "VariantStore", "`skipped`")?;
+        }
+        Ok(())
+    }
+
+    /// Validates the `key` attribute of every variant against the `keys` parameters
+    ///
+    /// With `keys(use = "...")` every variant has a distinct key of that type,
+    /// otherwise a variant may only have a key carrying one of its fields
+    pub(crate) fn check_keys(&self, ast: &DeriveInput, enum_data: &DataEnum) -> darling::Result<()> {
+        let mut errors = Error::accumulator();
+
+        if let Some(keys) = &self.keys {
+            if keys.path.is_some() && (keys.name.is_some() || keys.derive.is_some() || keys.rename_all.is_some()) {
+                let error = Error::custom("`name`, `derive` and `rename_all` of keys cannot be combined with `use`, the Key enum is not generated");
+                errors.push(error_on_attr(ast, "VariantStore", error));
+            }
+            if keys.path.is_none() && keys.implement.is_some() {
+                errors.push(error_on_attr(ast, "VariantStore", Error::custom("`implement` of keys requires `use`")));
+            }
+            // the object format reads each payload as the variant at the index of its key
+            if keys.path.is_some() && keys.implement.is_none() && self.serde_format == Some(SerdeFormat::Object) {
+                let error = Error::custom(
+                    "`serde_format = \"object\"` requires the keys to follow the order of the variants, \
+                    combine `use` with `implement` to let the derive implement them",
+                );
+                errors.push(error_on_attr(ast, "VariantStore", error));
+            }
+        }
+
+        let keys_path = self.keys_path();
+        let keys_type = keys_path.and_then(|path| path.segments.last()).map(|segment| &segment.ident);
+
+        // the skipped variants have no key
+        let variants = enum_data.variants.iter().filter(|variant| !KeyNameAttr::of(variant).is_skipped());
+
+        for variant in variants {
+            let sharing = KeyNameAttr::sharing_key(variant, enum_data);
+            if sharing.len() > 1 {
+                let key_name = KeyNameAttr::of(variant).key_name(variant);
+                if KeyAttr::field_of(variant).is_some() {
+                    let error = Error::custom(format!("variant `{}` is keyed by a field, it cannot share the key `{key_name}`", variant.ident));
+                    errors.push(error.with_span(&variant.ident));
+                }
+                // the first variant of the key gives its serde name
+                let serde_name = KeyNameAttr::of(sharing[0]).serde;
+                if KeyNameAttr::of(variant).serde != serde_name {
+                    let error = Error::custom(format!("variants sharing the key `{key_name}` must have the same serde name"));
+                    errors.push(error.with_span(&variant.ident));
+                }
+            }
+
+            match (keys_type, KeyAttr::of(variant)) {
+                (None, None) => {}
+                (_, Some(KeyAttr::By(member))) => {
+                    if keys_type.is_some() {
+                        errors.push(Error::custom("`key(by = ...)` cannot be combined with the `use` parameter of keys").with_span(&member));
+                    } else if !matches!(self.map_type(), MapType::HashMap | MapType::BTreeMap) {
+                        errors.push(Error::custom("`key(by = ...)` is only available with the `HashMap` and `BTreeMap` datastructs").with_span(&member));
+                    } else if KeyAttr::field_of(variant).is_none() {
+                        let field = match &member {
+                            Member::Named(name) => name.to_string(),
+                            Member::Unnamed(index) => index.index.to_string(),
+                        };
+                        errors.push(Error::custom(format!("variant `{}` has no field `{field}`", variant.ident)).with_span(&member));
+                    }
+                }
+                (None, Some(KeyAttr::Path(path))) => {
+                    errors.push(Error::custom("`key` requires the `use` parameter of keys in VariantStore").with_span(&path));
+                }
+                (Some(keys_type), None) => {
+                    let error = Error::custom(format!("variant `{}` has no `key` of `{keys_type}`", variant.ident));
+                    errors.push(error.with_span(&variant.ident));
+                }
+                (Some(keys_type), Some(KeyAttr::Path(path))) => {
+                    // a parsed path has at least one segment
+                    let key_name = path.segments.last().unwrap().ident.clone();
+                    let type_name = path.segments.iter().nth_back(1).map(|segment| &segment.ident);
+                    if type_name != Some(keys_type) {
+                        let error = Error::custom(format!("expected a variant of `{keys_type}` such as `{keys_type}::{key_name}`"));
+                        errors.push(error.with_span(&path));
+                    }
+
+                    if KeyNameAttr::of(variant).code.is_some() {
+                        errors.push(Error::custom("`code` of key_name cannot be combined with `key`").with_span(&path));
+                    }
+                }
+            }
+        }
+
+        // keys carrying a field cannot be enumerated
+        if KeyAttr::any_field(enum_data) {
+            let parameters = [
+                ("`strict`", self.strict.is_some()),
+                ("`serde_format = \"object\"`", self.serde_format() == SerdeFormat::Object),
+                ("`total`", self.total.is_some()),
+            ];
+            for (parameter, _) in parameters.iter().filter(|(_, used)| *used) {
+                let error = Error::custom(format!("{parameter} of VariantStore is not available with keys carrying a field"));
+                errors.push(error_on_attr(ast, "VariantStore", error));
+            }
+        }
+
+        // the object format finds the variant of a payload by the index of its key
+        if self.serde_format() == SerdeFormat::Object && enum_data.variants.iter().any(|variant| KeyNameAttr::of(variant).is_skipped()) {
+            let error = Error::custom("`serde_format = \"object\"` of VariantStore is not available with skipped variants");
+            errors.push(error_on_attr(ast, "VariantStore", error));
+        }
+        if self.serde_format() == SerdeFormat::Object && KeyNameAttr::any_shared(enum_data) {
+            let error = Error::custom("`serde_format = \"object\"` of VariantStore is not available with variants sharing a key");
+            errors.push(error_on_attr(ast, "VariantStore", error));
+        }
+
+        errors.finish()
+    }
+}
+
+/// Name under which a user-supplied type of keys is imported in the `const` block of the generated code
+fn user_keys_alias() -> Ident {
+    format_ident!("__VariantStore__Key")
+}
+
+/// Error spanned on the parameter macro `attribute` of the derive input, if it is present
+fn error_on_attr(ast: &DeriveInput, attribute: &str, error: Error) -> Error {
+    match ast.attrs.iter().find(|attr| attr.path().is_ident(attribute)) {
+        Some(attr) => error.with_span(attr.path()),
+        None => error,
+    }
+}
+
+/// Fails unless the `serde` feature is enabled, `parameter` generates de/serialization code
+fn require_serde(ast: &DeriveInput, attribute: &str, parameter: &str) -> darling::Result<()> {
+    if cfg!(feature = "serde") {
+        return Ok(());
+    }
+
+    let error = Error::custom(format!("{parameter} of {attribute} requires the `serde` feature"));
+    Err(error_on_attr(ast, attribute, error))
 }
 
 pub(crate) fn get_derives(path_list: Option<&PathList>) -> Option<TokenStream> {
@@ -251,11 +1164,14 @@ pub(crate) struct MapAttr {
 }
 
 impl MapAttr {
-    pub(crate) fn new(ast: &DeriveInput) -> Self {
-        Self {
-            base: BaseAttr::from_derive_input(&ast).expect("Wrong VariantStore parameters"),
-            ..Self::from_derive_input(&ast).expect("Wrong VariantMap parameters")
-        }
+    // the struct update still validates the `VariantMap` attribute even though it has no fields yet
+    #[allow(clippy::needless_update)]
+    pub(crate) fn new(ast: &DeriveInput, base: BaseAttr) -> darling::Result<Self> {
+        base.check_serde(ast)?;
+        Ok(Self {
+            base,
+            ..Self::from_derive_input(ast)?
+        })
     }
 }
 
@@ -285,12 +1201,17 @@ impl Deref for MapAttr {
 ///
 /// `features` : list of features (see [features][StructMapFeaturesAttr])
 ///
+/// `storage` : what each field stores, `enum` (default) or `payload` which requires [Clone] payloads (see [StructStorage])
+///
+/// `field_case` : [case][RenameRule] of the names of the fields, the names of the keys by default.
+/// It converts the names of the keys before `keys(rename_all(code = ...))` of [VariantStore][BaseAttr]
+///
 /// # Example
 ///
 /// ```
 /// use variant_map_derive::VariantStore;
 ///
-/// #[derive(Clone, VariantStore)]
+/// #[derive(Debug, Clone, serde::Serialize, VariantStore)]
 /// #[VariantStore(datastruct = "StructMap")]
 /// #[VariantStruct(name = "MySuperStruct", features(serialize, index), derive(Clone))]
 /// enum MyEnum {
@@ -309,22 +1230,67 @@ impl Deref for MapAttr {
 pub(crate) struct StructAttr {
     #[darling(skip)]
     base: BaseAttr,
-    name: Option<String>,
+    #[darling(with = parse_ident)]
+    name: Option<Ident>,
     derive: Option<PathList>,
     pub(crate) features: StructMapFeaturesAttr,
+    storage: Option<StructStorage>,
+    field_case: Option<RenameRule>,
+    /// Names of the keys and of their fields converted by `field_case`
+    #[darling(skip)]
+    fields: Vec<(Ident, Ident)>,
 }
 
 impl StructAttr {
-    pub(crate) fn new(ast: &DeriveInput) -> Self {
-        Self {
-            base: BaseAttr::from_derive_input(&ast).expect("Wrong VariantStore parameters"),
-            ..StructAttr::from_derive_input(&ast).expect("Wrong VariantStruct parameters")
+    pub(crate) fn new(ast: &DeriveInput, base: BaseAttr) -> darling::Result<Self> {
+        let mut struct_attr = Self {
+            base,
+            ..StructAttr::from_derive_input(ast)?
+        };
+
+        if let Some(Err(error)) = struct_attr.field_case.map(|case| case.apply_to_ident(&format_ident!("Key"))) {
+            return Err(error_on_attr(ast, "VariantStruct", Error::custom(format!("`field_case` {error}"))));
+        }
+        // the case converts the names of the keys before `rename_all(code = ...)`, as serde converts the variants
+        if let (Some(case), Data::Enum(enum_data)) = (struct_attr.field_case, &ast.data) {
+            struct_attr.fields = enum_data.variants.iter().filter_map(|variant| {
+                let key_name_attr = KeyNameAttr::of(variant);
+                if key_name_attr.is_skipped() {
+                    return None;
+                }
+
+                let key_name = key_name_attr.key_name(variant);
+                let field = case.apply_to_ident(&key_name).ok()?;
+                let renamed = struct_attr.base.renamed_code(&key_name_attr, variant).unwrap_or(key_name);
+                Some((renamed, field))
+            }).collect();
         }
+        if struct_attr.storage() == StructStorage::Payload && struct_attr.features.use_index() {
+            let error = Error::custom("the `index` feature of VariantStruct is not available with `storage = \"payload\"`");
+            return Err(error_on_attr(ast, "VariantStruct", error));
+        }
+        // a field of the payload storage holds the payload of a single variant
+        if let Data::Enum(enum_data) = &ast.data {
+            if struct_attr.storage() == StructStorage::Payload && KeyNameAttr::any_shared(enum_data) {
+                let error = Error::custom("variants sharing a key are not available with `storage = \"payload\"`");
+                return Err(error_on_attr(ast, "VariantStruct", error));
+            }
+        }
+
+        struct_attr.check_serde(ast)?;
+        if struct_attr.features.use_serialize() {
+            require_serde(ast, "VariantStruct", "the `serialize` feature")?;
+        }
+        if struct_attr.features.use_deserialize() {
+            require_serde(ast, "VariantStruct", "the `deserialize` feature")?;
+        }
+
+        Ok(struct_attr)
     }
 
     pub(crate) fn struct_name(&self, enum_type: &EnumType) -> Ident {
         if let Some(ref name) = self.name {
-            Ident::from_string(name.as_str()).unwrap()
+            name.clone()
         } else {
             format_ident!("{}StructMap", enum_type.enum_name)
         }
@@ -333,6 +1299,86 @@ impl StructAttr {
     pub(crate) fn derives(&self) -> Option<TokenStream> {
         get_derives(self.derive.as_ref())
     }
+
+    pub(crate) fn storage(&self) -> StructStorage {
+        self.storage.unwrap_or_default()
+    }
+
+    /// Name of the field of the `StructMap` holding the key `key_name`
+    pub(crate) fn field_name(&self, key_name: &Ident) -> Ident {
+        self.fields
+            .iter()
+            .find(|(key, _)| key == key_name)
+            .map_or_else(|| key_name.clone(), |(_, field)| field.clone())
+    }
+
+    /// Whether the names of the fields may not be `snake_case`
+    pub(crate) fn allows_non_snake_fields(&self) -> bool {
+        self.field_case != Some(RenameRule::Snake)
+    }
+}
+
+/// What the fields of a `StructMap` store, selected with the `storage` attribute of [VariantStruct][StructAttr]
+///
+/// [StructStorage::Enum] is from value `enum`: each field is an `Option<Enum>`
+///
+/// [StructStorage::Payload] is from value `payload`: each field is an `Option` of the variant's payload,
+/// `()` for unit variants, the field type for single field variants and a tuple of the field types otherwise.
+/// Values are converted to and from the enum on `insert` and `remove`.
+///
+/// As the enum is never stored, `VariantMap::get` and `iter` give a `Rebuilt` value, cloning the payload the first time
+/// it is dereferenced, and `get_mut` gives a `Reinsert` guard, storing the value back once dropped.
+/// The `VariantMap` and `MapValue` implementations, and thus this storage, require every payload type to be [Clone],
+/// a payload which is not is reported on its field.
+/// The `index` feature is not available and `serialize` clones the payloads
+///
+/// # Example
+///
+/// ```
+/// use variant_map::common::VariantMap;
+/// use variant_map_derive::VariantStore;
+///
+/// #[derive(Debug, PartialEq, VariantStore)]
+/// #[VariantStore(datastruct = "StructMap")]
+/// #[VariantStruct(storage = "payload")]
+/// enum MyEnum {
+///     A,
+///     B(i32),
+///     C { x: u8, y: u8 },
+/// }
+///
+/// fn main() {
+///     let mut map = MyEnumStructMap::default();
+///     map.insert(MyEnum::B(1));
+///     map.C = Some((1, 2));
+///     assert_eq!(map.B, Some(1));
+///     assert_eq!(map.get(&MyEnumKey::C).as_deref(), Some(&MyEnum::C { x: 1, y: 2 }));
+///     assert_eq!(map.remove(&MyEnumKey::A), None);
+/// }
+/// ```
+#[derive(Default, Debug, Clone, Copy, PartialEq)]
+pub(crate) enum StructStorage {
+    #[default]
+    Enum,
+    Payload,
+}
+
+impl TryFrom<&String> for StructStorage {
+    type Error = String;
+
+    fn try_from(value: &String) -> Result<Self, Self::Error> {
+        match value.to_lowercase().as_str() {
+            "enum" => Ok(Self::Enum),
+            "payload" => Ok(Self::Payload),
+            _ => Err("Invalid 'storage' argument, available { \"enum\", \"payload\" }".into()),
+        }
+    }
+}
+
+impl FromMeta for StructStorage {
+    fn from_string(value: &str) -> darling::Result<Self> {
+        Self::try_from(&value.to_string()).map_err(Error::custom)
+    }
 }
 
 impl Deref for StructAttr {
@@ -351,7 +1397,7 @@ impl Deref for StructAttr {
 ///
 /// `deserialize` if present, will derive [Deserialize][serde::Deserialize] for the struct
 ///
-/// `index` if present, will derive [Index][std::ops::Index] and [IndexMut][std::ops::IndexMut] for the struct
+/// `index` if present, will derive [Index][std::ops::Index] for the struct, values are modified through `VariantMap::get_mut`
 ///
 /// # Example
 ///
@@ -385,14 +1431,18 @@ impl StructMapFeaturesAttr {
 ///
 /// [MapType::HashMap] is from value `HashMap`
 /// [MapType::BTreeMap] is from value `BTreeMap`
+/// [MapType::ArrayMap] is from value `ArrayMap`
 /// [MapType::Struct] is from value `StructMap`
+/// [MapType::MultiMap] is from value `MultiMap`
 ///
-#[derive(Default, Debug)]
+#[derive(Default, Debug, Clone, Copy)]
 pub(crate) enum MapType {
     #[default]
     HashMap,
     BTreeMap,
+    ArrayMap,
     Struct,
+    MultiMap,
 }
 
 impl TryFrom<&String> for MapType {
@@ -402,12 +1452,20 @@ impl TryFrom<&String> for MapType {
         match value.to_lowercase().as_str() {
             "hashmap" => Ok(Self::HashMap),
             "btreemap" => Ok(Self::BTreeMap),
+            "arraymap" => Ok(Self::ArrayMap),
             "structmap" => Ok(Self::Struct),
-            _ => Err("Invalid 'map' argument, available {{ \"hashmap\", \"btreemap\" }}".into()),
+            "multimap" => Ok(Self::MultiMap),
+            _ => Err("Invalid 'map' argument, available { \"hashmap\", \"btreemap\", \"arraymap\", \"structmap\", \"multimap\" }".into()),
         }
     }
 }
 
+impl FromMeta for MapType {
+    fn from_string(value: &str) -> darling::Result<Self> {
+        Self::try_from(&value.to_string()).map_err(Error::custom)
+    }
+}
+
 impl ToTokens for MapType {
     fn to_tokens(&self, tokens: &mut proc_macro2::TokenStream) {
         let token = match self {
@@ -417,9 +1475,15 @@ impl ToTokens for MapType {
             MapType::BTreeMap => {
                 quote!(btreemap)
             }
+            MapType::ArrayMap => {
+                quote!(arraymap)
+            }
             MapType::Struct => {
                 quote!()
             }
+            MapType::MultiMap => {
+                quote!(multimap)
+            }
         };
 
         token.to_tokens(tokens);
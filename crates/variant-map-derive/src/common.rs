@@ -1,37 +1,80 @@
-use crate::attrs::{KeyNameAttr, MapType, BaseAttr, OptionalVisibility};
-use darling::FromVariant;
+use crate::attrs::{KeyAttr, KeyNameAttr, MapType, BaseAttr, OptionalVisibility, SkipPolicy};
 use proc_macro2::TokenStream;
-use quote::quote;
-use syn::{DataEnum, Generics, Ident, Variant, WhereClause, WherePredicate};
+use quote::{quote, quote_spanned};
+use syn::{parse_quote, DataEnum, Fields, Generics, Ident, Variant, WhereClause, WherePredicate};
+use syn::spanned::Spanned;
 use syn::TypeParamBound::Verbatim;
 
 /// All required information about the type of an enum
 pub struct EnumType<'a> {
     pub(crate) generics: &'a Generics,
     pub(crate) enum_name: &'a Ident,
+    /// Variants skipped with `#[key_name(skip)]`, the code is generated for the other variants
+    pub(crate) skipped: Vec<&'a Variant>,
 }
 
-/// Generates an enum of Keys for the specified enum
+/// Match arms of the variants skipped with `#[key_name(skip)]`, each evaluating to `to(name of the variant)`
+pub(crate) fn skipped_arms<F>(enum_type: &EnumType, to: F) -> TokenStream
+where
+    F: Fn(&str) -> TokenStream,
+{
+    let EnumType { enum_name, skipped, .. } = enum_type;
+
+    let arms = skipped.iter().map(|variant| {
+        let variant_name = &variant.ident;
+        let skip_fields = match &variant.fields {
+            Fields::Named(_) => Some(quote!({ .. })),
+            Fields::Unnamed(_) => Some(quote!((..))),
+            Fields::Unit => None,
+        };
+        let body = to(&variant_name.to_string());
+
+        quote! {
+            #enum_name::#variant_name #skip_fields => #body,
+        }
+    });
+
+    quote!(#(#arms)*)
+}
+
+/// Gives `value` back on a variant skipped with `#[key_name(skip)]`, it cannot be stored
+pub(crate) fn give_back_skipped(_variant_name: &str) -> TokenStream {
+    quote!(Some(value))
+}
+
+/// Generates an enum of Keys for the specified enum, unless the keys are a user-supplied type
 pub(crate) fn generate_key_enum(
     map_type: &MapType,
     map_attr: &BaseAttr,
     enum_data: &DataEnum,
     key_enum_name: &Ident,
-) -> TokenStream {
+) -> Option<TokenStream> {
+    if map_attr.keys_path().is_some() {
+        return None;
+    }
+
+    // the `serde` attribute only exists when the keys derive a serde trait
+    let keys_derive_serde = map_attr.keys_derive_serde();
+    let enum_data = &keys_data(enum_data);
+
     let key_variants = enum_data.variants.iter().map(|variant| {
-        let key_name_attr = KeyNameAttr::from_variant(variant).expect("Wrong key_name options");
+        let key_name_attr = KeyNameAttr::of(variant);
 
         // Useful in case of variant identifier renaming
         let key_name = key_name_attr.key_name(variant);
-        let serde_rename = key_name_attr.serde_rename().as_ref().map(|name| quote!{
+        let serde_rename = key_name_attr.serde_rename().as_ref().filter(|_| keys_derive_serde).map(|name| quote!{
             #[serde(rename=#name)]
         });
+        let key_field = KeyAttr::field_of(variant).map(|(_, field)| {
+            let ty = &field.ty;
+            quote!((#ty))
+        });
 
         quote! {
             #serde_rename
 
 
-            #key_name
+            #key_name #key_field
         }
     });
 
@@ -43,18 +86,296 @@ pub(crate) fn generate_key_enum(
         MapType::BTreeMap => {
             quote! { #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, #derives)] }
         }
+        MapType::ArrayMap | MapType::MultiMap => {
+            quote! { #[derive(Debug, PartialEq, Eq, #derives)] }
+        }
         MapType::Struct => {
             quote! {#[derive(Debug, #derives)] }
         }
     };
 
+    let allow_case = map_attr.allows_non_camel_keys().then(|| quote!(#[allow(non_camel_case_types)]));
+
     let vis = &map_attr.visibility;
-    quote! {
+    Some(quote! {
         #[automatically_derived]
         #derives_quote
+        #allow_case
         #vis enum #key_enum_name {
             #(#key_variants),*
         }
+    })
+}
+
+/// Generates the `ALL` and `COUNT` constants of the Key enum and implements `VariantKey` (from variant_map) on it
+///
+/// Keys are indexed in the order of the variants of the enum, a key shared by several variants at its first one.
+/// A user-supplied type of keys gets no constants and implements `VariantKey` only if requested,
+/// keys carrying a field cannot be enumerated and do not implement it
+pub(crate) fn generate_impl_variant_key(
+    map_attr: &BaseAttr,
+    enum_data: &DataEnum,
+    key_enum_name: &Ident,
+) -> Option<TokenStream> {
+    if !map_attr.implements_keys() || KeyAttr::any_field(enum_data) {
+        return None;
+    }
+    let enum_data = &keys_data(enum_data);
+
+    let keys = enum_data.variants.iter().map(|variant| {
+        let key_name_attr = KeyNameAttr::of(variant);
+        let key_name = key_name_attr.key_name(variant);
+        let serde_name = key_name_attr
+            .serde_rename()
+            .clone()
+            .unwrap_or_else(|| key_name.to_string());
+
+        (key_name, serde_name)
+    }).collect::<Vec<_>>();
+
+    let all_keys = keys.iter().map(|(key_name, _)| quote! { #key_enum_name::#key_name }).collect::<Vec<_>>();
+    let name_match_body = keys.iter().map(|(key_name, serde_name)| quote! {
+        #key_enum_name::#key_name => #serde_name,
+    });
+    let index_match_body = keys.iter().enumerate().map(|(index, (key_name, _))| quote! {
+        #key_enum_name::#key_name => #index,
+    });
+    let from_index_match_body = keys.iter().enumerate().map(|(index, (key_name, _))| quote! {
+        #index => Some(#key_enum_name::#key_name),
+    });
+
+    let required_keys = map_attr.required_keys();
+    let required = if required_keys.is_empty() { None } else {
+        Some(quote! {
+            const REQUIRED: &'static [Self] = &[#(#key_enum_name::#required_keys),*];
+        })
+    };
+
+    let count = keys.len();
+    let vis = &map_attr.visibility;
+    let constants = map_attr.keys_path().is_none().then(|| quote! {
+        #[automatically_derived]
+        #[allow(dead_code)]
+        impl #key_enum_name {
+            /// Every key, ordered as the variants of the enum
+            #vis const ALL: [#key_enum_name; #count] = [#(#all_keys),*];
+
+            /// Number of keys
+            #vis const COUNT: usize = #count;
+        }
+    });
+
+    Some(quote! {
+        #constants
+
+        #[automatically_derived]
+        impl VariantKey for #key_enum_name {
+            const COUNT: usize = #count;
+            const ALL: &'static [Self] = &[#(#all_keys),*];
+
+            fn name(&self) -> &'static str {
+                match self {
+                    #(#name_match_body)*
+                }
+            }
+
+            fn index(&self) -> usize {
+                match self {
+                    #(#index_match_body)*
+                }
+            }
+
+            fn from_index(index: usize) -> Option<Self> {
+                match index {
+                    #(#from_index_match_body)*
+                    _ => None,
+                }
+            }
+
+            #required
+        }
+    })
+}
+
+/// Checks that the keys of the variants cover every variant of a user-supplied type of keys
+///
+/// The match is spanned on the path to the type, so the compiler reports the keys left out on the `use` parameter
+pub(crate) fn generate_keys_exhaustiveness_check(
+    map_attr: &BaseAttr,
+    enum_data: &DataEnum,
+    key_enum_name: &Ident,
+) -> Option<TokenStream> {
+    let keys_path = map_attr.keys_path()?;
+    let key_names = keys_data(enum_data).variants.into_iter().map(|variant| KeyNameAttr::of(&variant).key_name(&variant));
+
+    Some(quote_spanned! {keys_path.span()=>
+        #[allow(dead_code)]
+        fn __variant_store_exhaustive_keys(key: &#key_enum_name) {
+            match key {
+                #(#key_enum_name::#key_names => {})*
+            }
+        }
+    })
+}
+
+/// Implements `VariantDefault` (from variant_map) on the enum, building each variant with the default value of its fields
+///
+/// A key shared by several variants builds the first one
+///
+/// The bounds requiring the fields to implement [Default] are higher-ranked so that they are checked where the trait is used,
+/// enums with fields not implementing [Default] are still derived, without the implementation.
+/// `field_bound` is the bound required on every field, `Default` plus the bounds of the `MapValue` implementation
+pub(crate) fn generate_impl_variant_default(
+    enum_type: &EnumType,
+    enum_data: &DataEnum,
+    key_enum_name: &Ident,
+    field_bound: TokenStream,
+) -> TokenStream {
+    let EnumType { enum_name, generics, .. } = enum_type;
+    let enum_data = &keys_data(enum_data);
+
+    let match_body = enum_data.variants.iter().map(|variant| {
+        let key_name = KeyNameAttr::of(variant).key_name(variant);
+        let variant_name = &variant.ident;
+        let fields = match &variant.fields {
+            Fields::Named(fields) => {
+                let names = fields.named.iter().map(|field| &field.ident);
+                quote!({ #(#names: Default::default()),* })
+            }
+            Fields::Unnamed(fields) => {
+                let defaults = fields.unnamed.iter().map(|_| quote!(Default::default()));
+                quote!((#(#defaults),*))
+            }
+            Fields::Unit => quote!(),
+        };
+
+        quote! {
+            #key_enum_name::#key_name => #enum_name::#variant_name #fields,
+        }
+    });
+
+    let mut generics = (*generics).clone();
+    let where_clause = generics.make_where_clause();
+    for ty in enum_data.variants.iter().flat_map(|variant| variant.fields.iter().map(|field| &field.ty)) {
+        where_clause.predicates.push(parse_quote!(for<'_variant_map_default> #ty: #field_bound));
+    }
+    let (impl_generics, type_generics, where_clause) = generics.split_for_impl();
+
+    quote! {
+        #[automatically_derived]
+        impl #impl_generics VariantDefault for #enum_name #type_generics #where_clause {
+            fn variant_default(key: &#key_enum_name) -> Self {
+                match key {
+                    #(#match_body)*
+                }
+            }
+        }
+    }
+}
+
+/// Implements `to_key` of `MapValue` (from variant_map)
+///
+/// With skipped variants, `try_to_key` reports them and `to_key` panics on them
+pub(crate) fn generate_fn_to_key(
+    map_attr: &BaseAttr,
+    enum_type: &EnumType,
+    enum_data: &DataEnum,
+    key_enum_name: &Ident,
+) -> TokenStream {
+    let enum_name = enum_type.enum_name;
+
+    let match_body = enum_data.variants.iter().map(|variant| {
+        let variant_name = &variant.ident;
+        let key_name = KeyNameAttr::of(variant).key_name(variant);
+        let (pattern, key_payload) = key_pattern(variant);
+
+        quote! {
+            #enum_name::#variant_name #pattern => #key_enum_name::#key_name #key_payload,
+        }
+    }).collect::<Vec<_>>();
+
+    if enum_type.skipped.is_empty() {
+        return quote! {
+            fn to_key(&self) -> Self::Key {
+                match self {
+                    #(#match_body)*
+                }
+            }
+        };
+    }
+
+    let skipped_arms = skipped_arms(enum_type, |variant_name| quote! {
+        return Err(_variant_map::common::Skipped { variant: #variant_name })
+    });
+    let ignore_skipped = (map_attr.skip_policy() == SkipPolicy::Ignore).then(|| quote! {
+        const IGNORE_SKIPPED: bool = true;
+    });
+
+    quote! {
+        fn to_key(&self) -> Self::Key {
+            match self.try_to_key() {
+                Ok(key) => key,
+                Err(skipped) => ::core::panic!("{}", skipped),
+            }
+        }
+
+        fn try_to_key(&self) -> Result<Self::Key, _variant_map::common::Skipped> {
+            Ok(match self {
+                #(#match_body)*
+                #skipped_arms
+            })
+        }
+
+        #ignore_skipped
+    }
+}
+
+/// The enum reduced to the first variant of each key, in the order of the variants
+///
+/// Variants sharing their key are stored in the same slot, the code generated per key follows the first one
+pub(crate) fn keys_data(enum_data: &DataEnum) -> DataEnum {
+    let mut key_names = vec![];
+    let variants = enum_data.variants.iter()
+        .filter(|variant| {
+            let key_name = KeyNameAttr::of(variant).key_name(variant);
+            let first = !key_names.contains(&key_name);
+            key_names.push(key_name);
+            first
+        })
+        .cloned()
+        .collect();
+
+    DataEnum {
+        variants,
+        ..enum_data.clone()
+    }
+}
+
+/// Pattern matching the fields of `variant` and the payload of its key
+///
+/// The field carried by the key, if any, is bound by the pattern and cloned into the payload
+pub(crate) fn key_pattern(variant: &Variant) -> (Option<TokenStream>, Option<TokenStream>) {
+    match KeyAttr::field_of(variant) {
+        Some((index, field)) => {
+            let pattern = match &field.ident {
+                Some(name) => quote!({ #name: __key_field, .. }),
+                None => {
+                    let skipped = (0..index).map(|_| quote!(_));
+                    quote!((#(#skipped,)* __key_field, ..))
+                }
+            };
+
+            (Some(pattern), Some(quote!((::core::clone::Clone::clone(__key_field)))))
+        }
+        None => {
+            let pattern = match &variant.fields {
+                Fields::Named(_) => Some(quote!({ .. })),
+                Fields::Unnamed(_) => Some(quote!((..))),
+                Fields::Unit => None,
+            };
+
+            (pattern, None)
+        }
     }
 }
 
@@ -90,8 +411,7 @@ where
     F: Fn(&Ident, &Ident, Option<TokenStream>, &Ident, &Ident) -> TokenStream,
 {
     let match_cases = enum_data.variants.iter().map(|variant| {
-        let key_name = &KeyNameAttr::from_variant(variant)
-            .expect("Wrong key_name options")
+        let key_name = &KeyNameAttr::of(variant)
             .key_name(variant);
 
         let Variant {
@@ -101,10 +421,10 @@ where
             discriminant: _,
         } = variant;
 
-        let skip_fields = if !fields.is_empty() {
-            Some(quote!((..)))
-        } else {
-            None
+        let skip_fields = match fields {
+            Fields::Named(_) => Some(quote!({ .. })),
+            Fields::Unnamed(_) => Some(quote!((..))),
+            Fields::Unit => None,
         };
 
         to(enum_name, ident, skip_fields, key_enum_name, key_name)
@@ -125,4 +445,22 @@ pub(crate) fn where_clause_add_trait(where_clause: &WhereClause, the_trait: Toke
     }
 
     cloned
-}
\ No newline at end of file
+}
+/// Converts an identifier written in `UpperCamelCase` to `snake_case`
+///
+/// Follows serde's `rename_all = "snake_case"`: an underscore is inserted before every uppercase letter but the first
+///
+/// # Example
+///
+/// `Config` => `config`, `MyVariant` => `my_variant`, `HTTPServer` => `h_t_t_p_server`
+pub(crate) fn to_snake_case(ident: &str) -> String {
+    let mut snake = String::with_capacity(ident.len() + 4);
+    for (i, c) in ident.char_indices() {
+        if i > 0 && c.is_uppercase() {
+            snake.push('_');
+        }
+        snake.push(c.to_ascii_lowercase());
+    }
+
+    snake
+}
@@ -8,6 +8,9 @@
 //!
 //! Con: Restricted API
 //!
+//! The de/serialization of the maps is generated with the `serde` feature, which the `serde` feature
+//! of `variant_map` enables when the derive is used through its `derive` feature
+//!
 //! # Example
 //!
 //! ```
@@ -29,6 +32,9 @@ pub(crate) mod attrs;
 /// Helper functions for the [maps] and [structs] implementations
 pub(crate) mod common;
 
+/// Implementation of the typed accessors to the payload of each variant, shared by all maps
+pub(crate) mod accessors;
+
 /// Implementation of the derive for variant_map
 pub(crate) mod maps;
 
@@ -41,20 +47,21 @@ pub(crate) mod maps;
 /// It also features implementation of the same traits as a normal variant Map
 pub(crate) mod structs;
 
-use crate::attrs::{MapType, BaseAttr};
+/// Implementation of the total map, generated next to any data structure
+///
+/// The total map holds a value of every variant, it is built from a builder or converted from the map of the enum
+pub(crate) mod total;
+
+use crate::attrs::{BaseAttr, KeyAttr, KeyNameAttr, MapAttr, MapType, StructAttr, VariantStoreAttr};
 use crate::common::EnumType;
-use darling::FromDeriveInput;
+use darling::{Error, FromDeriveInput, FromVariant};
 use proc_macro::TokenStream;
 use quote::{format_ident, quote};
-use syn::{parse_macro_input, DeriveInput};
-use syn::spanned::Spanned;
+use syn::{parse_macro_input, Data, DataEnum, DeriveInput};
 
 // TODO fix "private documentation" rustdoc
 // TODO publish
-// TODO allow using user generated (possibly generic or tuple variant) keys
-// TODO [1/2] add struct and array versions of the "map"
-// TODO? trait for all maps to reduce duplicate code
-// TODO? tight couple Map trait and MapValue if possible
+// TODO allow keys carrying a field whose type depends on the generics of the enum
 
 /// The only derive macro of this crate
 ///
@@ -66,56 +73,106 @@ use syn::spanned::Spanned;
 ///
 /// See other attributes in [attrs]
 ///
-#[proc_macro_derive(VariantStore, attributes(VariantStore, VariantMap, VariantStruct, key_name))]
+#[proc_macro_derive(VariantStore, attributes(VariantStore, VariantMap, VariantStruct, variant_store, key_name, key))]
 pub fn derive(input: TokenStream) -> TokenStream {
     let ast = parse_macro_input!(input as DeriveInput);
 
-    let enum_name = ast.ident.clone();
+    expand(&ast)
+        .unwrap_or_else(Error::write_errors)
+        .into()
+}
+
+/// Validates every attribute of the derive input then generates the code
+///
+/// Attribute errors are accumulated and reported together, each spanned on the offending attribute
+fn expand(ast: &DeriveInput) -> darling::Result<proc_macro2::TokenStream> {
+    let enum_data = match &ast.data {
+        Data::Enum(enum_data) => enum_data,
+        Data::Struct(data) => return Err(Error::custom("VariantStore works only on enums").with_span(&data.struct_token)),
+        Data::Union(data) => return Err(Error::custom("VariantStore works only on enums").with_span(&data.union_token)),
+    };
+
+    let mut errors = Error::accumulator();
 
-    // VariantStore attribute parameters
-    let (key_enum_name, map_type) = {
-        let base_attr = BaseAttr::from_derive_input(&ast).expect("Wrong VariantStore parameters");
+    let base_attr = errors.handle(BaseAttr::from_derive_input(ast));
 
-        (
-            base_attr.keys_name(format_ident!("{}Key", &enum_name)),
-            base_attr.map_type()
-        )
+    for variant in &enum_data.variants {
+        errors.handle(KeyNameAttr::from_variant(variant));
+        errors.handle(VariantStoreAttr::from_variant(variant));
+        errors.handle(KeyAttr::from_variant(variant));
+    }
+
+    // the keys are renamed before their names are read
+    let renamed_data = base_attr.as_ref().and_then(|base_attr| errors.handle(base_attr.rename_keys(ast, enum_data)));
+    let enum_data = renamed_data.as_ref().unwrap_or(enum_data);
+
+    if let Some(base_attr) = &base_attr {
+        errors.handle(base_attr.check_keys(ast, enum_data));
+    }
+
+    // the skipped variants have no key, the code is generated for the other ones
+    let (skipped, keyed): (Vec<_>, Vec<_>) = enum_data.variants.iter()
+        .partition(|variant| KeyNameAttr::of(variant).is_skipped());
+    let keyed_data = &DataEnum {
+        variants: keyed.into_iter().cloned().collect(),
+        ..enum_data.clone()
     };
 
+    let enum_name = &ast.ident;
     let enum_type = &EnumType {
-        enum_name: &enum_name,
+        enum_name,
         generics: &ast.generics,
+        skipped,
     };
 
-    let result = match map_type {
-        MapType::HashMap | MapType::BTreeMap => {
-            maps::generate_map_code(&ast, &map_type, enum_type, &key_enum_name)
+    errors.handle(accessors::check_accessor_names(keyed_data));
+
+    // read before the base attribute is moved into the attribute of the selected map type
+    let crate_import = base_attr.as_ref().map(BaseAttr::crate_import);
+    let keys_import = base_attr.as_ref().and_then(BaseAttr::keys_import);
+
+    let generated = match base_attr {
+        Some(base_attr) => {
+            let key_enum_name = base_attr.keys_name(format_ident!("{}Key", enum_name));
+            let map_type = base_attr.map_type();
+
+            match map_type {
+                MapType::HashMap | MapType::BTreeMap | MapType::ArrayMap | MapType::MultiMap => {
+                    errors.handle(MapAttr::new(ast, base_attr))
+                        .map(|map_attr| maps::generate_map_code(&map_attr, &map_type, enum_type, keyed_data, &key_enum_name))
+                }
+                MapType::Struct => {
+                    errors.handle(StructAttr::new(ast, base_attr))
+                        .map(|struct_attr| structs::generate_struct_code(&struct_attr, &map_type, enum_type, keyed_data, &key_enum_name))
+                }
+            }
         }
-        MapType::Struct => {
-            structs::generate_struct_code(&ast, &map_type, enum_type, &key_enum_name)
+        None => {
+            // the map type is unknown, the parameters of every map type are checked
+            errors.handle(MapAttr::from_derive_input(ast));
+            errors.handle(StructAttr::from_derive_input(ast));
+            None
         }
     };
 
-    let (out_of_const, inside_const) = match result {
-        Ok( tup ) => tup,
-        Err(_) => (Some(syn::Error::new(ast.span(), "VariantStore works only on enums").into_compile_error()), None)
-    };
+    // without any error, the code has been generated
+    let (out_of_const, inside_const) = errors.finish_with(generated)?.unwrap_or_default();
 
-    let result = quote! {
+    let use_serde = cfg!(feature = "serde").then(|| quote!(use _variant_map::serde;));
+
+    Ok(quote! {
 
         #out_of_const
 
         #[doc(hidden)]
         #[allow(non_upper_case_globals, unused_attributes, unused_qualifications)]
         const _: () = {
-            #[allow(unused_extern_crates, clippy::useless_attribute)]
-            extern crate variant_map as _variant_map;
+            #crate_import
             use _variant_map::common::*;
-            use _variant_map::serde;
+            #use_serde
+            #keys_import
 
             #inside_const
         };
-    };
-
-    result.into()
-}
\ No newline at end of file
+    })
+}
@@ -1,68 +1,136 @@
-use crate::attrs::{MapType, MapAttr};
-use crate::common;
+use crate::attrs::{KeyAttr, MapType, MapAttr, SerdeFormat};
+use crate::{accessors, common, total};
 use crate::common::EnumType;
 use proc_macro2::TokenStream;
 use quote::quote;
-use syn::{DataEnum, DeriveInput, Ident};
+use syn::{DataEnum, Ident};
 
 /// Generates an implementation of the key trait
 ///
 /// Each map (excepted `StructMap`) has associated bounds for its keys
-/// This function implements the corresponding trait for the generated Key Enum
+/// This function implements the corresponding trait for the generated Key Enum, or a user-supplied type if requested
 pub(crate) fn generate_impl_key_trait_for_key_enum(
+    map_attr: &MapAttr,
     map_type: &MapType,
     key_enum_name: &Ident,
 ) -> Option<TokenStream> {
-    if let Some(key_trait_impl) = match map_type {
+    if !map_attr.implements_keys() {
+        return None;
+    }
+
+    match map_type {
         MapType::HashMap => Some(quote! {impl HashKey for #key_enum_name {}}),
         MapType::BTreeMap => Some(quote! {impl OrdHashKey for #key_enum_name {}}),
+        MapType::ArrayMap => Some(quote! {impl ArrayKey for #key_enum_name {}}),
         MapType::Struct => None,
-    } {
-        Some(quote! {
+        MapType::MultiMap => Some(quote! {
+            impl MultiKey for #key_enum_name {}
             #[automatically_derived]
-            #key_trait_impl
-        })
-    } else {
-        None
+            impl _variant_map::arraymap::ArrayKey for #key_enum_name {}
+        }),
     }
+    .map(|key_trait_impl| {
+        quote! {
+            #[automatically_derived]
+            #key_trait_impl
+        }
+    })
 }
 
 /// Main function generating all of the code necessary for maps
 pub(crate) fn generate_map_code(
-    ast: &DeriveInput,
+    map_attr: &MapAttr,
     map_type: &MapType,
     enum_type: &EnumType,
+    enum_data: &DataEnum,
     key_enum_name: &Ident,
-) -> Result<(Option<TokenStream>, Option<TokenStream>), ()> {
-    let map_attr = &MapAttr::new(ast);
+) -> (Option<TokenStream>, Option<TokenStream>) {
+    let key_enum_quote = common::generate_key_enum(map_type, map_attr, enum_data, key_enum_name);
+
+    let impl_variant_key_quote = common::generate_impl_variant_key(map_attr, enum_data, key_enum_name);
+
+    let keys_exhaustiveness_quote = common::generate_keys_exhaustiveness_check(map_attr, enum_data, key_enum_name);
+
+    let map = map_type_tokens(map_type, enum_type, enum_data, key_enum_name);
+    let map = if map_attr.is_strict() { quote!(_variant_map::strict::Strict<#map>) } else { map };
+    let map = match map_attr.serde_format() {
+        SerdeFormat::Seq => map,
+        SerdeFormat::Object => quote!(_variant_map::object::Object<#map>),
+    };
+
+    let impl_map_value_for_enum_quote =
+        generate_impl_map_value(map_attr, &map, enum_type, enum_data, key_enum_name);
+
+    // a key carrying a field does not hold the other fields of its variant
+    let impl_variant_default_quote = (!KeyAttr::any_field(enum_data))
+        .then(|| common::generate_impl_variant_default(enum_type, enum_data, key_enum_name, quote!(Default)));
 
-    match &ast.data {
-        syn::Data::Enum(ref enum_data) => {
-            let key_enum_quote = common::generate_key_enum(map_type, map_attr, enum_data, key_enum_name);
+    let (accessors_trait_quote, impl_accessors_quote) =
+        accessors::generate_accessors(map_attr, None, enum_type, enum_data, key_enum_name, &map);
 
-            let impl_map_value_for_enum_quote =
-                generate_impl_map_value(map_type, enum_type, enum_data, key_enum_name);
+    let impl_hash_key_for_enum_key_quote =
+        generate_impl_key_trait_for_key_enum(map_attr, map_type, key_enum_name);
 
-            let impl_hash_key_for_enum_key_quote =
-                generate_impl_key_trait_for_key_enum(map_type, key_enum_name);
+    let impl_multimap_value_quote = matches!(map_type, MapType::MultiMap)
+        .then(|| generate_impl_multimap_value(enum_type));
 
-            let (outside_const, inside_const) = common::in_or_out_scope(&map_attr.visibility, quote! {
-                #key_enum_quote
-            });
+    let (total_struct_quote, impl_total_quote) =
+        total::generate_total_code(map_attr, enum_type, enum_data, key_enum_name, &map).unzip();
 
-            let inside_const = quote! {
-                use _variant_map::#map_type::*;
+    let (outside_const, inside_const) = common::in_or_out_scope(&map_attr.visibility, quote! {
+        #key_enum_quote
 
-                #inside_const
+        #accessors_trait_quote
 
-                #impl_map_value_for_enum_quote
+        #total_struct_quote
+    });
 
-                #impl_hash_key_for_enum_key_quote
-            };
+    let inside_const = quote! {
+        use _variant_map::#map_type::*;
+
+        #inside_const
+
+        #keys_exhaustiveness_quote
+
+        #impl_variant_key_quote
+
+        #impl_map_value_for_enum_quote
+
+        #impl_variant_default_quote
+
+        #impl_accessors_quote
+
+        #impl_hash_key_for_enum_key_quote
+
+        #impl_multimap_value_quote
+
+        #impl_total_quote
+    };
+
+    (outside_const, Some(inside_const))
+}
 
-            Ok((outside_const, Some(inside_const)))
+/// Type of the Map associated to the target Enum
+pub(crate) fn map_type_tokens(
+    map_type: &MapType,
+    enum_type: &EnumType,
+    enum_data: &DataEnum,
+    key_enum_name: &Ident,
+) -> TokenStream {
+    let EnumType { enum_name, generics, .. } = enum_type;
+    let (_, ty_generics, _) = generics.split_for_impl();
+
+    match map_type {
+        MapType::ArrayMap => {
+            let key_count = common::keys_data(enum_data).variants.len();
+            quote!(Map<#key_enum_name, #enum_name #ty_generics, #key_count>)
         }
-        _ => Err(()),
+        // the multimap is not a `VariantMap`, the enum keeps a map holding one value per key
+        MapType::MultiMap => {
+            let key_count = common::keys_data(enum_data).variants.len();
+            quote!(_variant_map::arraymap::Map<#key_enum_name, #enum_name #ty_generics, #key_count>)
+        }
+        _ => quote!(Map<#key_enum_name, #enum_name #ty_generics>),
     }
 }
 
@@ -70,7 +138,8 @@ pub(crate) fn generate_map_code(
 ///
 /// This binds the enum to its associated Map and Key types
 pub(crate) fn generate_impl_map_value(
-    _map_type: &MapType,
+    map_attr: &MapAttr,
+    map: &TokenStream,
     enum_type: &EnumType,
     enum_data: &DataEnum,
     key_enum_name: &Ident,
@@ -78,31 +147,19 @@ pub(crate) fn generate_impl_map_value(
     let EnumType {
         enum_name,
         generics,
+        ..
     } = enum_type;
 
-    let match_body = common::enum_entries_map_to(
-        enum_name,
-        enum_data,
-        key_enum_name,
-        |enum_name, variant_name, skip_fields, key_enum_name, key_name| {
-            quote! {
-                #enum_name::#variant_name #skip_fields => #key_enum_name::#key_name,
-            }
-        },
-    );
+    let fn_to_key = common::generate_fn_to_key(map_attr, enum_type, enum_data, key_enum_name);
 
     let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
     quote! {
         #[automatically_derived]
         impl #impl_generics MapValue for #enum_name #ty_generics #where_clause {
             type Key = #key_enum_name;
-            type Map = Map<Self::Key, Self>;
+            type Map = #map;
 
-            fn to_key(&self) -> Self::Key {
-                match self {
-                   #match_body
-                }
-            }
+            #fn_to_key
 
             fn make_map() -> Self::Map {
                Self::Map::default()
@@ -110,3 +167,20 @@ pub(crate) fn generate_impl_map_value(
         }
     }
 }
+
+/// Generates an implementation of `MultiMapValue` (from variant_map) for the target Enum
+///
+/// This binds the enum to its multimap
+pub(crate) fn generate_impl_multimap_value(enum_type: &EnumType) -> TokenStream {
+    let EnumType {
+        enum_name,
+        generics,
+        ..
+    } = enum_type;
+
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    quote! {
+        #[automatically_derived]
+        impl #impl_generics MultiMapValue for #enum_name #ty_generics #where_clause {}
+    }
+}
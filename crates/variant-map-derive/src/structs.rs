@@ -1,74 +1,113 @@
-use crate::attrs::{StructAttr, MapType};
-use crate::common;
+use crate::accessors::VariantAccessor;
+use crate::attrs::{KeyNameAttr, MapType, SerdeFormat, SkipPolicy, StructAttr, StructStorage};
+use crate::{accessors, common, total};
 use crate::common::EnumType;
 use proc_macro2::{Ident, TokenStream};
-use quote::{format_ident, quote};
+use quote::{format_ident, quote, quote_spanned};
 use syn::spanned::Spanned;
-use syn::{Data, DataEnum, DeriveInput, GenericParam, Lifetime, LifetimeParam, TypeGenerics, WhereClause};
+use syn::{DataEnum, GenericParam, Lifetime, LifetimeParam, TypeGenerics, WhereClause};
 
 /// Main function generating the entire code for a `StructMap`
 pub(crate) fn generate_struct_code(
-    ast: &DeriveInput,
+    struct_attr: &StructAttr,
     map_type: &MapType,
     enum_type: &EnumType,
+    enum_data: &DataEnum,
     key_enum_name: &Ident,
-) -> Result<(Option<TokenStream>, Option<TokenStream>), ()> {
+) -> (Option<TokenStream>, Option<TokenStream>) {
 
-    let struct_attr = &StructAttr::new(&ast);
+    let struct_name = &struct_attr.struct_name(enum_type);
 
-    match &ast.data {
-        Data::Enum(ref enum_data) => {
+    let key_enum_quote = common::generate_key_enum(map_type, struct_attr, enum_data, key_enum_name);
 
-            let struct_name = &struct_attr.struct_name(enum_type);
+    let impl_variant_key_quote = common::generate_impl_variant_key(struct_attr, enum_data, key_enum_name);
 
-            let key_enum_quote = common::generate_key_enum(map_type, struct_attr, enum_data, key_enum_name);
+    let keys_exhaustiveness_quote = common::generate_keys_exhaustiveness_check(struct_attr, enum_data, key_enum_name);
 
-            let enum_struct_quote =
-                generate_enum_struct_code(struct_attr, enum_type, enum_data, key_enum_name, struct_name);
+    let storage = struct_attr.storage();
 
-            let impl_struct_map_functions_quote =
-                generate_enum_struct_impl(enum_type, enum_data, key_enum_name, struct_name);
+    let enum_struct_quote =
+        generate_enum_struct_code(struct_attr, &storage, enum_type, enum_data, key_enum_name, struct_name);
 
-            let impl_map_value =
-                generate_impl_map_value(struct_name, enum_type, enum_data, key_enum_name);
+    let (impl_struct_map_functions_quote, impl_variant_map) = match storage {
+        StructStorage::Enum => (
+            generate_enum_struct_impl(struct_attr, enum_type, enum_data, key_enum_name, struct_name),
+            generate_impl_variant_map(struct_attr, enum_type, enum_data, key_enum_name, struct_name),
+        ),
+        StructStorage::Payload => (
+            generate_payload_struct_impl(struct_attr, enum_type, enum_data, key_enum_name, struct_name),
+            generate_impl_payload_variant_map(struct_attr, enum_type, enum_data, key_enum_name, struct_name),
+        ),
+    };
 
-            let impl_index =
-                if !struct_attr.features.use_index() { None }
-                else { Some(generate_impl_index(enum_type, enum_data, key_enum_name, struct_name)) };
+    let impl_map_value =
+        generate_impl_map_value(struct_attr, struct_name, &storage, enum_type, enum_data, key_enum_name);
 
-            let impl_serialize =
-                if !struct_attr.features.use_serialize() { None }
-                else { Some(generate_impl_serialize(struct_name, enum_type, enum_data, key_enum_name)) };
+    // the `MapValue` implementation of the payload storage requires payloads implementing `Clone`
+    let default_field_bound = match storage {
+        StructStorage::Enum => quote!(Default),
+        StructStorage::Payload => quote!(Default + ::core::clone::Clone),
+    };
+    let impl_variant_default = common::generate_impl_variant_default(enum_type, enum_data, key_enum_name, default_field_bound);
 
-            let impl_deserialize =
-                if !struct_attr.features.use_deserialize() { None }
-                else { Some(generate_impl_deserialize(struct_name, enum_type, enum_data, key_enum_name)) };
+    let (_, type_generics, _) = enum_type.generics.split_for_impl();
+    let (accessors_trait_quote, impl_accessors_quote) =
+        accessors::generate_accessors(struct_attr, Some(struct_attr), enum_type, enum_data, key_enum_name, &quote!(#struct_name #type_generics));
 
+    // the payload storage rejects the index feature when its attribute is parsed
+    let impl_index =
+        if !struct_attr.features.use_index() { None }
+        else { Some(generate_impl_index(struct_attr, enum_type, enum_data, key_enum_name, struct_name)) };
 
-            let (outside_const, inside_const) = common::in_or_out_scope(&struct_attr.visibility, quote! {
-                #key_enum_quote
+    let impl_serialize =
+        if !struct_attr.features.use_serialize() { None }
+        else { Some(generate_impl_serialize(struct_attr, struct_name, &storage, &struct_attr.serde_format(), enum_type, enum_data, key_enum_name)) };
 
-                #enum_struct_quote
-            });
+    let impl_deserialize =
+        if !struct_attr.features.use_deserialize() { None }
+        else { Some(generate_impl_deserialize(struct_name, &struct_attr.serde_format(), struct_attr.is_strict(), struct_attr.skip_policy(), enum_type, enum_data, key_enum_name)) };
 
-            let inside_const = quote! {
-                #inside_const
 
-                #impl_struct_map_functions_quote
+    let (total_struct_quote, impl_total_quote) =
+        total::generate_total_code(struct_attr, enum_type, enum_data, key_enum_name, &quote!(#struct_name #type_generics)).unzip();
 
-                #impl_index
+    let (outside_const, inside_const) = common::in_or_out_scope(&struct_attr.visibility, quote! {
+        #key_enum_quote
 
-                #impl_map_value
+        #enum_struct_quote
 
-                #impl_serialize
+        #accessors_trait_quote
 
-                #impl_deserialize
-            };
+        #total_struct_quote
+    });
 
-            Ok((outside_const, Some(inside_const)))
-        }
-        _ => Err(()),
-    }
+    let inside_const = quote! {
+        #inside_const
+
+        #keys_exhaustiveness_quote
+
+        #impl_variant_key_quote
+
+        #impl_struct_map_functions_quote
+
+        #impl_variant_map
+
+        #impl_index
+
+        #impl_map_value
+
+        #impl_variant_default
+
+        #impl_accessors_quote
+
+        #impl_serialize
+
+        #impl_deserialize
+
+        #impl_total_quote
+    };
+
+    (outside_const, Some(inside_const))
 }
 
 /// Adds a trait as bound for each type in the where clause and a bound on the enum type
@@ -79,32 +118,49 @@ pub(crate) fn generate_struct_code(
 /// `None` => `where Enum: NewBound`
 pub(crate) fn where_clause_add_enum_bound(where_clause: Option<&WhereClause>, enum_name: &Ident, type_generics: &TypeGenerics, bound: TokenStream) -> TokenStream {
     let new_enum_bound = quote! { #enum_name #type_generics: #bound };
-    let where_clause = where_clause.map(|where_clause| {
+
+    where_clause.map(|where_clause| {
         let where_clause = common::where_clause_add_trait(where_clause, bound);
         quote! {
             #where_clause, #new_enum_bound
         }
     }).unwrap_or_else(|| {
         quote! { where #new_enum_bound }
-    });
-
-    where_clause
+    })
 }
 
 /// Generates an implementation of [Serialize][serde::Serialize] for the target enum
 ///
 /// Adds a bound on generics for [Serialize][serde::Serialize] on the impl block
 ///
+/// The [format][SerdeFormat] is either a sequence of variants or an object of payloads keyed by key name
+///
 /// Implemented only if the `StructMap` has the [feature][crate::attrs::StructMapFeaturesAttr] `serialize`
-pub(crate) fn generate_impl_serialize(struct_name: &Ident, enum_type: &EnumType, enum_data: &DataEnum, key_enum_name: &Ident) -> TokenStream {
+pub(crate) fn generate_impl_serialize(map_attr: &StructAttr, struct_name: &Ident, storage: &StructStorage, format: &SerdeFormat, enum_type: &EnumType, enum_data: &DataEnum, key_enum_name: &Ident) -> TokenStream {
     let EnumType {
         enum_name,
-        generics
+        generics,
+        ..
     } = enum_type;
+    let enum_data = &common::keys_data(enum_data);
 
-    let serialize_fields = common::enum_entries_map_to(enum_name, enum_data, key_enum_name, |_enum_name, _variant_name, _skip_fields, _key_enum_name, key_name| {
-        quote! {
-            if let Some(ref value) = self.#key_name { seq.serialize_element(value)? }
+    let serialize_fields = common::enum_entries_map_to(enum_name, enum_data, key_enum_name, |_enum_name, _variant_name, _skip_fields, key_enum_name, key_name| {
+        let value = match storage {
+            StructStorage::Enum => {
+                let field = map_attr.field_name(key_name);
+                quote!(self.#field.as_ref())
+            }
+            StructStorage::Payload => quote!(VariantMap::get(self, &#key_enum_name::#key_name)),
+        };
+        match format {
+            SerdeFormat::Seq => quote! {
+                if let Some(value) = #value { state.serialize_element(&*value)? }
+            },
+            SerdeFormat::Object => quote! {
+                if let Some(value) = #value {
+                    state.serialize_entry(VariantKey::name(&#key_enum_name::#key_name), &_variant_map::object::Payload(&*value))?
+                }
+            },
         }
     });
     let fields_len = enum_data.variants.len();
@@ -112,21 +168,33 @@ pub(crate) fn generate_impl_serialize(struct_name: &Ident, enum_type: &EnumType,
     let (impl_generics, type_generics, where_clause) = generics.split_for_impl();
 
     // Update where clause with Serialize trait
-    let where_clause = where_clause_add_enum_bound(where_clause, enum_name, &type_generics, quote!(::serde::Serialize));
+    let where_clause = where_clause_add_enum_bound(where_clause, enum_name, &type_generics, quote!(_variant_map::serde::Serialize));
+    let where_clause = match storage {
+        StructStorage::Enum => where_clause,
+        StructStorage::Payload => {
+            let clone_bounds = payload_clone_bounds(enum_data);
+            quote!(#where_clause, #clone_bounds)
+        }
+    };
+
+    let serialize_start = match format {
+        SerdeFormat::Seq => quote!(serializer.serialize_seq(Some(#fields_len))),
+        SerdeFormat::Object => quote!(serializer.serialize_map(Some(self.len()))),
+    };
 
     quote! {
-        use ::serde::ser::SerializeSeq;
+        use _variant_map::serde::ser::{SerializeMap, SerializeSeq};
         #[automatically_derived]
-        impl #impl_generics ::serde::Serialize for #struct_name #type_generics #where_clause {
+        impl #impl_generics _variant_map::serde::Serialize for #struct_name #type_generics #where_clause {
             fn serialize<__serde_S>(&self, serializer: __serde_S) -> Result<__serde_S::Ok, __serde_S::Error>
             where
-                __serde_S: ::serde::Serializer,
+                __serde_S: _variant_map::serde::Serializer,
             {
-                let mut seq = serializer.serialize_seq(Some(#fields_len))?;
+                let mut state = #serialize_start?;
 
                 #serialize_fields
 
-                seq.end()
+                state.end()
             }
         }
     }
@@ -141,13 +209,71 @@ pub(crate) fn generate_impl_serialize(struct_name: &Ident, enum_type: &EnumType,
 ///
 /// The visitor for the deserialization is called `__VariantStore__StructMap__{EnumName}__Visitor`, make sure there are collisions too
 ///
+/// The expected [format][SerdeFormat] is either a sequence of variants or an object of payloads keyed by key name
+///
+/// When [strict][crate::attrs::StrictAttr], duplicated variants and missing required keys are errors
+///
+/// A variant skipped with `#[key_name(skip)]` fails the deserialization, unless the [policy][SkipPolicy] ignores it
+///
 /// Implemented only if the `StructMap` has the [feature][crate::attrs::StructMapFeaturesAttr] `deserialize`
-fn generate_impl_deserialize(struct_name: &Ident, enum_type: &EnumType, enum_data: &DataEnum, key_enum_name: &Ident) -> TokenStream {
+fn generate_impl_deserialize(struct_name: &Ident, format: &SerdeFormat, strict: bool, skip_policy: SkipPolicy, enum_type: &EnumType, enum_data: &DataEnum, key_enum_name: &Ident) -> TokenStream {
     let EnumType {
         enum_name,
-        generics
+        generics,
+        ..
     } = enum_type;
 
+    let (check_duplicate, check_required) = if strict {
+        (
+            Some(quote! {
+                if result.contains_key(&key) {
+                    return Err(_variant_map::strict::duplicate_key(&key));
+                }
+            }),
+            Some(quote! {
+                for key in <#key_enum_name as VariantKey>::REQUIRED {
+                    if !result.contains_key(key) {
+                        return Err(_variant_map::strict::missing_key(key));
+                    }
+                }
+            }),
+        )
+    } else {
+        (None, None)
+    };
+
+    // a skipped variant has no field, it is dropped or rejected before being inserted
+    let check_skipped = (!enum_type.skipped.is_empty()).then(|| {
+        let skipped_arms = common::skipped_arms(enum_type, |variant_name| match skip_policy {
+            SkipPolicy::Ignore => quote!(continue),
+            SkipPolicy::Error => quote! {
+                return Err(_variant_map::serde::de::Error::custom(_variant_map::common::Skipped { variant: #variant_name }))
+            },
+        });
+        quote! {
+            match &value {
+                #skipped_arms
+                _ => {}
+            }
+        }
+    });
+
+    // the key of a value is only needed to check it is not a duplicate
+    let key_of_value = if strict {
+        let key_match_body = common::enum_entries_map_to(enum_name, enum_data, key_enum_name, |enum_name, variant_name, skip_fields, key_enum_name, key_name| {
+            quote! { #enum_name::#variant_name #skip_fields => #key_enum_name::#key_name, }
+        });
+        let skipped_arms = common::skipped_arms(enum_type, |_| quote!(unreachable!()));
+        Some(quote! {
+            let key = match &value {
+                #key_match_body
+                #skipped_arms
+            };
+        })
+    } else {
+        None
+    };
+
     let (impl_generics, type_generics, where_clause) = generics.split_for_impl();
     let visitor = format_ident!("__VariantStore__StructMap__{}__Visitor", key_enum_name);
     let phantom = if generics.params.is_empty() { None } else {
@@ -160,6 +286,7 @@ fn generate_impl_deserialize(struct_name: &Ident, enum_type: &EnumType, enum_dat
 
     let visitor_quote = quote! {
         use core::marker::PhantomData;
+        #[allow(non_camel_case_types)]
         struct #visitor #type_generics #phantom;
     };
 
@@ -168,39 +295,61 @@ fn generate_impl_deserialize(struct_name: &Ident, enum_type: &EnumType, enum_dat
     generics.params.push(GenericParam::Lifetime(LifetimeParam::new(Lifetime::new("'_serde_deserializer_lifetime_de", impl_generics.span()))));
 
     // Update where clause with Deserialize trait
-    let where_clause = where_clause_add_enum_bound(where_clause, enum_name, &type_generics, quote!(::serde::Deserialize<#deser_lifetime>));
+    let where_clause = where_clause_add_enum_bound(where_clause, enum_name, &type_generics, quote!(_variant_map::serde::Deserialize<#deser_lifetime>));
 
     let (impl_generics, _, _) = generics.split_for_impl();
 
-    let deser_match = common::enum_entries_map_to(enum_name, enum_data, key_enum_name, |enum_name, variant_name, skip_fields, _key_enum_name, key_name| {
-        quote! { Some(#enum_name::#variant_name #skip_fields) => result.#key_name = elem, }
-    });
-
     let expected_msg = format!("{visitor} expects a {struct_name} holding {enum_name} variants");
 
-    let impl_visitor = quote! {
-        impl #impl_generics ::serde::de::Visitor<#deser_lifetime> for #visitor #type_generics #where_clause {
-            type Value = #struct_name #type_generics;
-
-            fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
-                formatter.write_str(#expected_msg)
-            }
-
+    let visit = match format {
+        SerdeFormat::Seq => quote! {
             fn visit_seq<__serde__A>(self, mut seq: __serde__A) -> core::result::Result<Self::Value, __serde__A::Error>
                 where
-                    __serde__A: ::serde::de::SeqAccess<#deser_lifetime>,
+                    __serde__A: _variant_map::serde::de::SeqAccess<#deser_lifetime>,
             {
                 let mut result = #struct_name::default();
 
                 while let Some(elem) = seq.next_element::<Option<#enum_name #type_generics>>()? {
-                    match elem {
-                        #deser_match
-                        None => {}
+                    if let Some(value) = elem {
+                        #check_skipped
+                        #key_of_value
+                        #check_duplicate
+                        result.insert(value);
                     }
                 }
 
+                #check_required
                 Ok(result)
             }
+        },
+        SerdeFormat::Object => quote! {
+            fn visit_map<__serde__A>(self, mut map: __serde__A) -> core::result::Result<Self::Value, __serde__A::Error>
+                where
+                    __serde__A: _variant_map::serde::de::MapAccess<#deser_lifetime>,
+            {
+                let mut result = #struct_name::default();
+
+                while let Some(key) = map.next_key_seed(_variant_map::object::KeySeed::<#key_enum_name>::new())? {
+                    #check_duplicate
+                    let value = map.next_value_seed(_variant_map::object::PayloadSeed::<#enum_name #type_generics>::new(VariantKey::index(&key)))?;
+                    result.insert(value);
+                }
+
+                #check_required
+                Ok(result)
+            }
+        },
+    };
+
+    let impl_visitor = quote! {
+        impl #impl_generics _variant_map::serde::de::Visitor<#deser_lifetime> for #visitor #type_generics #where_clause {
+            type Value = #struct_name #type_generics;
+
+            fn expecting(&self, formatter: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+                formatter.write_str(#expected_msg)
+            }
+
+            #visit
         }
     };
 
@@ -210,13 +359,18 @@ fn generate_impl_deserialize(struct_name: &Ident, enum_type: &EnumType, enum_dat
         quote!( #visitor {} )
     };
 
+    let deserialize_fn = match format {
+        SerdeFormat::Seq => format_ident!("deserialize_seq"),
+        SerdeFormat::Object => format_ident!("deserialize_map"),
+    };
+
     let impl_deserialize_struct = quote! {
         impl #impl_generics Deserialize<#deser_lifetime> for #struct_name #type_generics #where_clause {
             fn deserialize<__serde_D>(deserializer: __serde_D) -> core::result::Result<Self, __serde_D::Error>
             where
-                __serde_D: ::serde::de::Deserializer<#deser_lifetime>,
+                __serde_D: _variant_map::serde::de::Deserializer<#deser_lifetime>,
             {
-                deserializer.deserialize_seq( #visitor_init )
+                deserializer.#deserialize_fn( #visitor_init )
             }
         }
     };
@@ -235,22 +389,27 @@ fn generate_impl_deserialize(struct_name: &Ident, enum_type: &EnumType, enum_dat
 
 /// Implements base methods on the `StructMap`
 ///
-/// Defines `get` `get_mut` `insert` `remove`
+/// Defines `get` `insert` `remove`
+///
+/// The `VariantMap` implementation (from variant_map) delegates to them
 pub(crate) fn generate_enum_struct_impl(
+    map_attr: &StructAttr,
     enum_type: &EnumType,
     enum_data: &DataEnum,
     key_enum_name: &Ident,
     struct_name: &Ident,
 ) -> TokenStream {
     let EnumType { enum_name, .. } = enum_type;
+    let keys_data = &common::keys_data(enum_data);
 
     let fn_remove_match_body = common::enum_entries_map_to(
         enum_name,
-        enum_data,
+        keys_data,
         key_enum_name,
         |_enum_name, _variant_name, _skip_fields, key_enum_name, key_name| {
+            let field = map_attr.field_name(key_name);
             quote! {
-                #key_enum_name::#key_name => std::mem::take(&mut self.#key_name),
+                #key_enum_name::#key_name => self.#field.take(),
             }
         },
     );
@@ -260,19 +419,93 @@ pub(crate) fn generate_enum_struct_impl(
         enum_data,
         key_enum_name,
         |enum_name, variant_name, skip_fields, _key_enum_name, key_name| {
+            let field = map_attr.field_name(key_name);
             quote! {
-                #enum_name::#variant_name #skip_fields => std::mem::replace(&mut self.#key_name, Some(value)),
+                #enum_name::#variant_name #skip_fields => self.#field.replace(value),
             }
         },
     );
+    let fn_insert_skipped_arms = common::skipped_arms(enum_type, common::give_back_skipped);
 
     let fn_get_match_body = common::enum_entries_map_to(
+        enum_name,
+        keys_data,
+        key_enum_name,
+        |_enum_name, _variant_name, _skip_fields, key_enum_name, key_name| {
+            let field = map_attr.field_name(key_name);
+            quote! {
+                #key_enum_name::#key_name => self.#field.as_ref(),
+            }
+        },
+    );
+
+    let (impl_generics, type_generics, where_clause) = enum_type.generics.split_for_impl();
+    let enum_name_w_generics = quote! {
+        #enum_name #type_generics
+    };
+
+    quote! {
+        #[automatically_derived]
+        #[allow(dead_code)]
+        impl #impl_generics #struct_name #type_generics #where_clause {
+            fn remove(&mut self, key: &#key_enum_name) -> Option<#enum_name_w_generics> {
+                match key {
+                    #fn_remove_match_body
+                }
+            }
+
+            fn insert(&mut self, value: #enum_name_w_generics) -> Option<#enum_name_w_generics> {
+                match value {
+                    #fn_insert_match_body
+                    #fn_insert_skipped_arms
+                }
+            }
+
+            fn get(&self, key: &#key_enum_name) -> Option<&#enum_name_w_generics> {
+                match key {
+                    #fn_get_match_body
+                }
+            }
+        }
+    }
+}
+
+/// Implements `VariantMap` (from variant_map) on the `StructMap`
+///
+/// Lookups delegate to the inherent methods generated by [generate_enum_struct_impl],
+/// the mutable references are `ValueMut` guards over the unchecked references of the `UncheckedMut` implementation
+///
+/// The lifetime used by the iterator is always `'_variant_map_iter` make sure there are no collisions
+pub(crate) fn generate_impl_variant_map(
+    map_attr: &StructAttr,
+    enum_type: &EnumType,
+    enum_data: &DataEnum,
+    key_enum_name: &Ident,
+    struct_name: &Ident,
+) -> TokenStream {
+    let EnumType { enum_name, .. } = enum_type;
+    let enum_data = &common::keys_data(enum_data);
+
+    let fields_as_ref = common::enum_entries_map_to(
+        enum_name,
+        enum_data,
+        key_enum_name,
+        |_enum_name, _variant_name, _skip_fields, _key_enum_name, key_name| {
+            let field = map_attr.field_name(key_name);
+            quote! {
+                self.#field.as_ref(),
+            }
+        },
+    );
+
+    let fields_keyed = common::enum_entries_map_to(
         enum_name,
         enum_data,
         key_enum_name,
         |_enum_name, _variant_name, _skip_fields, key_enum_name, key_name| {
+            let field = map_attr.field_name(key_name);
             quote! {
-                #key_enum_name::#key_name => &self.#key_name,
+                (&#key_enum_name::#key_name, self.#field.as_ref()),
             }
         },
     );
@@ -282,12 +515,38 @@ pub(crate) fn generate_enum_struct_impl(
         enum_data,
         key_enum_name,
         |_enum_name, _variant_name, _skip_fields, key_enum_name, key_name| {
+            let field = map_attr.field_name(key_name);
+            quote! {
+                #key_enum_name::#key_name => self.#field.as_mut(),
+            }
+        },
+    );
+
+    let fields_is_some = common::enum_entries_map_to(
+        enum_name,
+        enum_data,
+        key_enum_name,
+        |_enum_name, _variant_name, _skip_fields, _key_enum_name, key_name| {
+            let field = map_attr.field_name(key_name);
+            quote! {
+                + self.#field.is_some() as usize
+            }
+        },
+    );
+
+    let fields_clear = common::enum_entries_map_to(
+        enum_name,
+        enum_data,
+        key_enum_name,
+        |_enum_name, _variant_name, _skip_fields, _key_enum_name, key_name| {
+            let field = map_attr.field_name(key_name);
             quote! {
-                #key_enum_name::#key_name => &mut self.#key_name,
+                self.#field = None;
             }
         },
     );
 
+    let fields_len = enum_data.variants.len();
     let (impl_generics, type_generics, where_clause) = enum_type.generics.split_for_impl();
     let enum_name_w_generics = quote! {
         #enum_name #type_generics
@@ -295,75 +554,331 @@ pub(crate) fn generate_enum_struct_impl(
 
     quote! {
         #[automatically_derived]
-        #[allow(dead_code)]
+        impl #impl_generics VariantMap for #struct_name #type_generics #where_clause {
+            type Key = #key_enum_name;
+            type Value = #enum_name_w_generics;
+            type Ref<'_variant_map_iter> = &'_variant_map_iter #enum_name_w_generics
+            where
+                Self: '_variant_map_iter;
+            type Mut<'_variant_map_iter> = ValueMut<'_variant_map_iter, #enum_name_w_generics>
+            where
+                Self: '_variant_map_iter;
+            type Iter<'_variant_map_iter> = ::core::iter::Flatten<::core::array::IntoIter<Option<&'_variant_map_iter #enum_name_w_generics>, #fields_len>>
+            where
+                Self: '_variant_map_iter;
+            type KeyedIter<'_variant_map_iter> = ::core::iter::FilterMap<
+                ::core::array::IntoIter<(&'_variant_map_iter #key_enum_name, Option<&'_variant_map_iter #enum_name_w_generics>), #fields_len>,
+                fn((&'_variant_map_iter #key_enum_name, Option<&'_variant_map_iter #enum_name_w_generics>)) -> Option<(&'_variant_map_iter #key_enum_name, &'_variant_map_iter #enum_name_w_generics)>,
+            >
+            where
+                Self: '_variant_map_iter;
+
+            fn get(&self, key: &Self::Key) -> Option<&Self::Value> {
+                #struct_name::get(self, key)
+            }
+
+            fn get_mut(&mut self, key: &Self::Key) -> Option<Self::Mut<'_>> {
+                _variant_map::__private::UncheckedMut::get_mut_unchecked(self, key).map(ValueMut::indexed)
+            }
+
+            fn insert(&mut self, value: Self::Value) -> Option<Self::Value> {
+                #struct_name::insert(self, value)
+            }
+
+            fn remove(&mut self, key: &Self::Key) -> Option<Self::Value> {
+                #struct_name::remove(self, key)
+            }
+
+            fn len(&self) -> usize {
+                0 #fields_is_some
+            }
+
+            fn iter(&self) -> Self::Iter<'_> {
+                [#fields_as_ref].into_iter().flatten()
+            }
+
+            fn iter_keyed(&self) -> Self::KeyedIter<'_> {
+                [#fields_keyed].into_iter().filter_map(|(key, value)| value.map(|value| (key, value)))
+            }
+
+            fn clear(&mut self) {
+                #fields_clear
+            }
+        }
+
+        #[automatically_derived]
+        impl #impl_generics _variant_map::__private::UncheckedMut for #struct_name #type_generics #where_clause {
+            fn get_mut_unchecked(&mut self, key: &Self::Key) -> Option<&mut Self::Value> {
+                match key {
+                    #fn_get_mut_match_body
+                }
+            }
+        }
+    }
+}
+
+/// Bounds requiring every payload type of the enum to be [Clone]
+///
+/// Each bound is spanned on the type of its field, so a payload which is not [Clone] is reported on that field
+fn payload_clone_bounds(enum_data: &DataEnum) -> TokenStream {
+    let clone_bounds = enum_data.variants.iter()
+        .flat_map(|variant| variant.fields.iter().map(|field| &field.ty))
+        .map(|ty| quote_spanned!(ty.span()=> #ty: ::core::clone::Clone));
+
+    quote! {
+        #(#clone_bounds),*
+    }
+}
+
+/// Appends the [payload_clone_bounds] to the where clause of the enum
+fn where_clause_add_clone_bounds(where_clause: Option<&WhereClause>, enum_data: &DataEnum) -> TokenStream {
+    let clone_bounds = payload_clone_bounds(enum_data);
+    let predicates = where_clause.into_iter().flat_map(|where_clause| where_clause.predicates.iter());
+
+    quote! {
+        where #(#predicates,)* #clone_bounds
+    }
+}
+
+/// Key name, variant name and accessor of each variant stored by a `StructMap` with the payload [storage][StructStorage]
+fn payload_variants<'a>(map_attr: &StructAttr, enum_data: &'a DataEnum) -> Vec<(Ident, Ident, &'a Ident, VariantAccessor)> {
+    enum_data.variants.iter().map(|variant| {
+        let key_name = KeyNameAttr::of(variant)
+            .key_name(variant);
+        let field = map_attr.field_name(&key_name);
+        (key_name, field, &variant.ident, VariantAccessor::new(variant))
+    }).collect()
+}
+
+/// Implements base methods on a `StructMap` with the payload [storage][StructStorage]
+///
+/// Defines `insert` `remove` `contains_key` used by the `VariantMap` implementation and the deserialization,
+/// they convert the values to and from the enum and need no [Clone] payload
+pub(crate) fn generate_payload_struct_impl(
+    map_attr: &StructAttr,
+    enum_type: &EnumType,
+    enum_data: &DataEnum,
+    key_enum_name: &Ident,
+    struct_name: &Ident,
+) -> TokenStream {
+    let EnumType { enum_name, .. } = enum_type;
+
+    let variants = payload_variants(map_attr, enum_data);
+
+    let fn_remove_match_body = variants.iter().map(|(key_name, field, variant_name, accessor)| {
+        let VariantAccessor { pattern, .. } = accessor;
+        let payload = accessor.payload_bindings();
+        quote! {
+            #key_enum_name::#key_name => self.#field.take().map(|#payload| #enum_name::#variant_name #pattern),
+        }
+    });
+
+    let fn_insert_match_body = variants.iter().map(|(_, field, variant_name, accessor)| {
+        let VariantAccessor { pattern, .. } = accessor;
+        let payload = accessor.payload_bindings();
+        quote! {
+            #enum_name::#variant_name #pattern => self.#field.replace(#payload).map(|#payload| #enum_name::#variant_name #pattern),
+        }
+    });
+
+    let fn_insert_skipped_arms = common::skipped_arms(enum_type, common::give_back_skipped);
+
+    let fn_contains_key_match_body = variants.iter().map(|(key_name, field, _, _)| {
+        quote! {
+            #key_enum_name::#key_name => self.#field.is_some(),
+        }
+    });
+
+    let (impl_generics, type_generics, where_clause) = enum_type.generics.split_for_impl();
+    let enum_name_w_generics = quote! {
+        #enum_name #type_generics
+    };
+
+    quote! {
+        #[automatically_derived]
+        #[allow(dead_code, clippy::unused_unit)]
         impl #impl_generics #struct_name #type_generics #where_clause {
             fn remove(&mut self, key: &#key_enum_name) -> Option<#enum_name_w_generics> {
                 match key {
-                    #fn_remove_match_body
+                    #(#fn_remove_match_body)*
                 }
             }
 
             fn insert(&mut self, value: #enum_name_w_generics) -> Option<#enum_name_w_generics> {
                 match value {
-                    #fn_insert_match_body
+                    #(#fn_insert_match_body)*
+                    #fn_insert_skipped_arms
                 }
             }
 
-            fn get(&self, key: &#key_enum_name) -> &Option<#enum_name_w_generics> {
+            fn contains_key(&self, key: &#key_enum_name) -> bool {
                 match key {
-                    #fn_get_match_body
+                    #(#fn_contains_key_match_body)*
                 }
             }
+        }
+    }
+}
 
-            fn get_mut(&mut self, key: &#key_enum_name) -> &mut Option<#enum_name_w_generics> {
+/// Implements `VariantMap` (from variant_map) on a `StructMap` with the payload [storage][StructStorage]
+///
+/// A [Ref][_variant_map::common::Rebuilt] rebuilds the value from a clone of the payload once dereferenced,
+/// a [Mut][_variant_map::common::Reinsert] is taken out of the map then inserted back once dropped.
+/// The implementation requires every payload to be [Clone]
+pub(crate) fn generate_impl_payload_variant_map(
+    map_attr: &StructAttr,
+    enum_type: &EnumType,
+    enum_data: &DataEnum,
+    key_enum_name: &Ident,
+    struct_name: &Ident,
+) -> TokenStream {
+    let EnumType { enum_name, .. } = enum_type;
+
+    let variants = payload_variants(map_attr, enum_data);
+
+    let fn_rebuild_match_body = variants.iter().map(|(key_name, field, variant_name, accessor)| {
+        let VariantAccessor { pattern, .. } = accessor;
+        let payload = accessor.payload_bindings();
+        quote! {
+            #key_enum_name::#key_name => map.#field.clone().map(|#payload| #enum_name::#variant_name #pattern),
+        }
+    });
+
+    let fn_get_match_body = variants.iter().map(|(key_name, field, _, _)| {
+        quote! {
+            #key_enum_name::#key_name => self.#field.is_some().then(|| Rebuilt::new(self, &#key_enum_name::#key_name, Self::rebuild)),
+        }
+    });
+    let fields_is_some = variants.iter().map(|(_, field, _, _)| quote!(self.#field.is_some() as usize));
+    let fields_clear = variants.iter().map(|(_, field, _, _)| quote!(self.#field = None;));
+
+    let (impl_generics, type_generics, where_clause) = enum_type.generics.split_for_impl();
+    let where_clause = where_clause_add_clone_bounds(where_clause, enum_data);
+    let enum_name_w_generics = quote! {
+        #enum_name #type_generics
+    };
+
+    quote! {
+        #[automatically_derived]
+        impl #impl_generics #struct_name #type_generics #where_clause {
+            fn rebuild(map: &Self, key: &#key_enum_name) -> Option<#enum_name_w_generics> {
                 match key {
-                    #fn_get_mut_match_body
+                    #(#fn_rebuild_match_body)*
                 }
             }
         }
+
+        #[automatically_derived]
+        impl #impl_generics VariantMap for #struct_name #type_generics #where_clause {
+            type Key = #key_enum_name;
+            type Value = #enum_name_w_generics;
+            type Ref<'_variant_map_iter> = Rebuilt<'_variant_map_iter, Self>
+            where
+                Self: '_variant_map_iter;
+            type Mut<'_variant_map_iter> = Reinsert<'_variant_map_iter, Self>
+            where
+                Self: '_variant_map_iter;
+            type Iter<'_variant_map_iter> = RebuiltIter<'_variant_map_iter, Self>
+            where
+                Self: '_variant_map_iter;
+            type KeyedIter<'_variant_map_iter> = ::core::iter::Map<
+                RebuiltIter<'_variant_map_iter, Self>,
+                fn(Rebuilt<'_variant_map_iter, Self>) -> (&'_variant_map_iter #key_enum_name, Rebuilt<'_variant_map_iter, Self>),
+            >
+            where
+                Self: '_variant_map_iter;
+
+            fn get(&self, key: &Self::Key) -> Option<Self::Ref<'_>> {
+                match key {
+                    #(#fn_get_match_body)*
+                }
+            }
+
+            fn get_mut(&mut self, key: &Self::Key) -> Option<Self::Mut<'_>> {
+                #struct_name::remove(self, key).map(|value| Reinsert::new(self, value))
+            }
+
+            fn insert(&mut self, value: Self::Value) -> Option<Self::Value> {
+                #struct_name::insert(self, value)
+            }
+
+            fn remove(&mut self, key: &Self::Key) -> Option<Self::Value> {
+                #struct_name::remove(self, key)
+            }
+
+            fn contains_key(&self, key: &Self::Key) -> bool {
+                #struct_name::contains_key(self, key)
+            }
+
+            fn len(&self) -> usize {
+                0 #(+ #fields_is_some)*
+            }
+
+            fn iter(&self) -> Self::Iter<'_> {
+                RebuiltIter::new(self, Self::rebuild)
+            }
+
+            fn iter_keyed(&self) -> Self::KeyedIter<'_> {
+                RebuiltIter::new(self, Self::rebuild).map(|value| (value.key(), value))
+            }
+
+            fn clear(&mut self) {
+                #(#fields_clear)*
+            }
+        }
     }
 }
 
 /// Generates the code for the `StructMap` definition and implements [Default][std::default::Default] on it
 pub(crate) fn generate_enum_struct_code(
     map_attr: &StructAttr,
+    storage: &StructStorage,
     enum_type: &EnumType,
     enum_data: &DataEnum,
     key_enum_name: &Ident,
     struct_name: &Ident,
 ) -> TokenStream {
     let EnumType { enum_name, .. } = enum_type;
+    let enum_data = &common::keys_data(enum_data);
 
     let (impl_generics, type_generics, where_clause) = enum_type.generics.split_for_impl();
 
-    let fields = common::enum_entries_map_to(
-        enum_name,
-        enum_data,
-        key_enum_name,
-        |enum_name, _variant_name, _skip_fields, _key_enum_name, key_name| {
-            quote! {
-                #key_name: Option<#enum_name #type_generics>,
-            }
-        },
-    );
+    let fields = enum_data.variants.iter().map(|variant| {
+        let key_name = KeyNameAttr::of(variant)
+            .key_name(variant);
+
+        let field = map_attr.field_name(&key_name);
+        let field_type = match storage {
+            StructStorage::Enum => quote!(#enum_name #type_generics),
+            StructStorage::Payload => VariantAccessor::new(variant).payload_type(),
+        };
+
+        quote! {
+            #field: Option<#field_type>,
+        }
+    });
+    let fields = quote!(#(#fields)*);
 
     let fields_none = common::enum_entries_map_to(
         enum_name,
         enum_data,
         key_enum_name,
         |_enum_name, _variant_name, _skip_fields, _key_enum_name, key_name| {
+            let field = map_attr.field_name(key_name);
             quote! {
-                #key_name: None,
+                #field: None,
             }
         },
     );
 
+    let allow_case = map_attr.allows_non_snake_fields().then(|| quote!(#[allow(non_snake_case)]));
+
     let vis = &map_attr.visibility;
     let derives = map_attr.derives();
     quote! {
         #[automatically_derived]
         #[derive(Debug, #derives)]
-        #[allow(non_snake_case)]
+        #allow_case
         #vis struct #struct_name #type_generics #where_clause  {
             #fields
         }
@@ -379,16 +894,20 @@ pub(crate) fn generate_enum_struct_code(
     }
 }
 
-/// Implements [Index][std::ops::Index] and [IndexMut][std::ops::IndexMut] for the `StructMap`
+/// Implements [Index][std::ops::Index] for the `StructMap`, by key and by reference to a key
+///
+/// [IndexMut][std::ops::IndexMut] is not implemented, a mutable `Option` could store a variant under the key of another one
 ///
 /// Implemented only if the `StructMap` has the [feature][crate::attrs::StructMapFeaturesAttr] `index`
 pub(crate) fn generate_impl_index(
+    map_attr: &StructAttr,
     enum_type: &EnumType,
     enum_data: &DataEnum,
     key_enum_name: &Ident,
     struct_name: &Ident,
 ) -> TokenStream {
     let EnumType { enum_name, .. } = enum_type;
+    let enum_data = &common::keys_data(enum_data);
 
     let (impl_generics, type_generics, where_clause) = enum_type.generics.split_for_impl();
 
@@ -398,8 +917,9 @@ pub(crate) fn generate_impl_index(
             enum_data,
             key_enum_name,
             |_enum_name, _variant_name, _skip_fields, key_enum_name, key_name| {
+                let field = map_attr.field_name(key_name);
                 quote! {
-                    #key_enum_name::#key_name => &self.#key_name,
+                    #key_enum_name::#key_name => &self.#field,
                 }
             },
         );
@@ -414,24 +934,12 @@ pub(crate) fn generate_impl_index(
                     }
                 }
             }
-        }
-    };
 
-    let impl_index_mut = {
-        let match_body = common::enum_entries_map_to(
-            enum_name,
-            enum_data,
-            key_enum_name,
-            |_enum_name, _variant_name, _skip_fields, key_enum_name, key_name| {
-                quote! {
-                    #key_enum_name::#key_name => &mut self.#key_name,
-                }
-            },
-        );
+            #[automatically_derived]
+            impl #impl_generics Index<&#key_enum_name> for #struct_name #type_generics #where_clause {
+                type Output = Option<#enum_name #type_generics>;
 
-        quote! {
-            impl #impl_generics IndexMut<#key_enum_name> for #struct_name #type_generics #where_clause {
-                fn index_mut(&mut self, index: #key_enum_name) -> &mut Self::Output {
+                fn index(&self, index: &#key_enum_name) -> &Self::Output {
                     match index {
                         #match_body
                     }
@@ -441,13 +949,9 @@ pub(crate) fn generate_impl_index(
     };
 
     quote! {
-        use std::ops::{Index, IndexMut};
+        use ::core::ops::Index;
         #[automatically_derived]
         #impl_index
-
-
-        #[automatically_derived]
-        #impl_index_mut
     }
 }
 
@@ -455,7 +959,9 @@ pub(crate) fn generate_impl_index(
 ///
 /// This binds the Enum to its `StructMap` and its Key enum
 pub(crate) fn generate_impl_map_value(
+    map_attr: &StructAttr,
     struct_name: &Ident,
+    storage: &StructStorage,
     enum_type: &EnumType,
     enum_data: &DataEnum,
     key_enum_name: &Ident,
@@ -463,32 +969,24 @@ pub(crate) fn generate_impl_map_value(
     let EnumType {
         generics,
         enum_name,
+        ..
     } = enum_type;
 
-    let match_body = common::enum_entries_map_to(
-        enum_name,
-        enum_data,
-        key_enum_name,
-        |enum_name, variant_name, skip_fields, key_enum_name, key_name| {
-            quote! {
-                #enum_name::#variant_name #skip_fields => #key_enum_name::#key_name,
-            }
-        },
-    );
+    let fn_to_key = common::generate_fn_to_key(map_attr, enum_type, enum_data, key_enum_name);
 
     let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    // the payload storage is only a `VariantMap` with Clone payloads
+    let where_clause = match storage {
+        StructStorage::Enum => quote!(#where_clause),
+        StructStorage::Payload => where_clause_add_clone_bounds(where_clause, enum_data),
+    };
     quote! {
         #[automatically_derived]
         impl #impl_generics MapValue for #enum_name #ty_generics #where_clause {
             type Key = #key_enum_name;
             type Map = #struct_name #ty_generics;
 
-            fn to_key(&self) -> Self::Key {
-                match self {
-                   #match_body
-                }
-            }
-
+            #fn_to_key
 
             fn make_map() -> Self::Map {
                Self::Map::default()
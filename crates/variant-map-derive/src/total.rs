@@ -0,0 +1,337 @@
+use crate::accessors::VariantAccessor;
+use crate::attrs::{get_derives, BaseAttr, KeyNameAttr, TotalAttr};
+use crate::common::{self, EnumType};
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{DataEnum, Ident};
+
+/// Main function generating the total map and its builder, if requested with the `total` attribute
+///
+/// `partial_map` is the type of the map storing the variants of the enum (its `Map` or `StructMap`),
+/// the total map is convertible from and into it
+///
+/// # Returns
+///
+/// `(definitions, impls)` the definitions must be placed next to the Key enum, the implementations in the `const` block
+pub(crate) fn generate_total_code(
+    map_attr: &BaseAttr,
+    enum_type: &EnumType,
+    enum_data: &DataEnum,
+    key_enum_name: &Ident,
+    partial_map: &TokenStream,
+) -> Option<(TokenStream, TokenStream)> {
+    let total_attr = map_attr.total()?;
+
+    let total_name = total_attr.name.clone().unwrap_or_else(|| format_ident!("{}TotalMap", enum_type.enum_name));
+    let builder_name = format_ident!("{}Builder", total_name);
+
+    let definitions = generate_total_struct_code(map_attr, &total_attr, enum_type, enum_data, &total_name, &builder_name);
+    let impls = [
+        generate_total_impl(map_attr, enum_type, enum_data, key_enum_name, &total_name, &builder_name),
+        generate_builder_impl(map_attr, enum_type, enum_data, key_enum_name, &total_name, &builder_name),
+        generate_impl_conversions(enum_type, enum_data, key_enum_name, &total_name, &builder_name, partial_map),
+    ];
+
+    Some((definitions, quote!(#(#impls)*)))
+}
+
+/// Name of the variable bound to the value of `key_name` when destructuring the builder
+fn binding(key_name: &Ident) -> Ident {
+    format_ident!("__field_{}", key_name)
+}
+
+/// Defines the total map, a field holding the enum per variant, and its builder, an optional field per variant
+pub(crate) fn generate_total_struct_code(
+    map_attr: &BaseAttr,
+    total_attr: &TotalAttr,
+    enum_type: &EnumType,
+    enum_data: &DataEnum,
+    total_name: &Ident,
+    builder_name: &Ident,
+) -> TokenStream {
+    let EnumType { enum_name, generics, .. } = enum_type;
+    let (impl_generics, type_generics, where_clause) = generics.split_for_impl();
+
+    let key_names = common::keys_data(enum_data).variants.iter().map(|variant| KeyNameAttr::of(variant).key_name(variant)).collect::<Vec<_>>();
+
+    let total_doc = format!("Map holding a value of every variant of [{enum_name}], built with [{builder_name}]");
+    let builder_doc = format!("Builder of a [{total_name}], fails to build unless every variant of [{enum_name}] is inserted");
+
+    let vis = &map_attr.visibility;
+    let derives = get_derives(total_attr.derive.as_ref()).map(|derives| quote!(#[derive(#derives)]));
+    quote! {
+        #[doc = #total_doc]
+        #[automatically_derived]
+        #derives
+        #[allow(non_snake_case)]
+        #vis struct #total_name #type_generics #where_clause {
+            #(#key_names: #enum_name #type_generics,)*
+        }
+
+        #[doc = #builder_doc]
+        #[automatically_derived]
+        #[allow(non_snake_case)]
+        #vis struct #builder_name #type_generics #where_clause {
+            #(#key_names: Option<#enum_name #type_generics>,)*
+        }
+
+        #[automatically_derived]
+        impl #impl_generics Default for #builder_name #type_generics #where_clause {
+            fn default() -> Self {
+                #builder_name {
+                    #(#key_names: None,)*
+                }
+            }
+        }
+    }
+}
+
+/// Implements the lookups of the total map
+///
+/// Defines `builder` `get` `replace` `try_replace` `iter` and, for each variant `V`, the typed accessors `get_v` `get_v_mut` `set_v`.
+/// The payload accessors cannot fail, as the field of a key always holds its variant.
+/// Variants sharing their key have none, the field holds any of them
+pub(crate) fn generate_total_impl(
+    map_attr: &BaseAttr,
+    enum_type: &EnumType,
+    enum_data: &DataEnum,
+    key_enum_name: &Ident,
+    total_name: &Ident,
+    builder_name: &Ident,
+) -> TokenStream {
+    let EnumType { enum_name, generics, .. } = enum_type;
+    let (impl_generics, type_generics, where_clause) = generics.split_for_impl();
+
+    let keys_data = &common::keys_data(enum_data);
+
+    let get_match_body = common::enum_entries_map_to(enum_name, keys_data, key_enum_name, |_enum_name, _variant_name, _skip_fields, key_enum_name, key_name| {
+        quote! { #key_enum_name::#key_name => &self.#key_name, }
+    });
+
+    let replace_match_body = common::enum_entries_map_to(enum_name, enum_data, key_enum_name, |enum_name, variant_name, skip_fields, _key_enum_name, key_name| {
+        quote! { #enum_name::#variant_name #skip_fields => Ok(::core::mem::replace(&mut self.#key_name, value)), }
+    });
+    let replace_skipped_arms = common::skipped_arms(enum_type, |_| quote!(Err(value)));
+
+    let key_names = keys_data.variants.iter().map(|variant| KeyNameAttr::of(variant).key_name(variant)).collect::<Vec<_>>();
+    let count = key_names.len();
+
+    // the field of a shared key may hold any of its variants, their payloads are reached through `get`
+    let vis = &map_attr.visibility;
+    let accessors = enum_data.variants.iter().filter(|variant| KeyNameAttr::sharing_key(variant, enum_data).len() == 1).map(|variant| {
+        let key_name = KeyNameAttr::of(variant).key_name(variant);
+        let variant_name = &variant.ident;
+        let accessor = VariantAccessor::new(variant);
+        let VariantAccessor { suffix, pattern, rebuilt_pattern, params, types, .. } = &accessor;
+
+        let get = format_ident!("get_{}", suffix);
+        let get_mut = format_ident!("get_{}_mut", suffix);
+        let set = format_ident!("set_{}", suffix);
+
+        let owned = accessor.payload_type();
+        let refs = accessor.payload_ref_type();
+        let refs_mut = accessor.payload_mut_type();
+        let payload = accessor.payload_bindings();
+
+        let get_doc = format!("Payload of the `{variant_name}` variant");
+        let get_mut_doc = format!("Mutable payload of the `{variant_name}` variant");
+        let set_doc = format!("Store a `{variant_name}` variant, returning the previous payload");
+
+        let get_mut = if types.is_empty() { None } else {
+            Some(quote! {
+                #[doc = #get_mut_doc]
+                #vis fn #get_mut(&mut self) -> #refs_mut {
+                    match &mut self.#key_name {
+                        #enum_name::#variant_name #pattern => #payload,
+                        _ => unreachable!(),
+                    }
+                }
+            })
+        };
+
+        quote! {
+            #[doc = #get_doc]
+            #vis fn #get(&self) -> #refs {
+                match &self.#key_name {
+                    #enum_name::#variant_name #pattern => #payload,
+                    _ => unreachable!(),
+                }
+            }
+
+            #get_mut
+
+            #[doc = #set_doc]
+            #vis fn #set(&mut self, #(#params: #types),*) -> #owned {
+                match ::core::mem::replace(&mut self.#key_name, #enum_name::#variant_name #rebuilt_pattern) {
+                    #enum_name::#variant_name #pattern => #payload,
+                    _ => unreachable!(),
+                }
+            }
+        }
+    });
+
+    let enum_name_w_generics = quote!(#enum_name #type_generics);
+    quote! {
+        #[automatically_derived]
+        #[allow(dead_code, unreachable_patterns, clippy::unused_unit)]
+        impl #impl_generics #total_name #type_generics #where_clause {
+            /// Builder collecting the variants of the map
+            #vis fn builder() -> #builder_name #type_generics {
+                #builder_name::default()
+            }
+
+            /// Value stored under `key`
+            #vis fn get(&self, key: &#key_enum_name) -> &#enum_name_w_generics {
+                match key {
+                    #get_match_body
+                }
+            }
+
+            /// Store `value` under its key, returning the value it replaces, or `value` itself if it is a skipped variant
+            ///
+            /// See `try_replace` to tell a skipped variant apart from a replaced value
+            #vis fn replace(&mut self, value: #enum_name_w_generics) -> #enum_name_w_generics {
+                self.try_replace(value).unwrap_or_else(|value| value)
+            }
+
+            /// Store `value` under its key, returning the value it replaces, fails with `value` given back if it is a skipped variant
+            #vis fn try_replace(&mut self, value: #enum_name_w_generics) -> Result<#enum_name_w_generics, #enum_name_w_generics> {
+                match value {
+                    #replace_match_body
+                    #replace_skipped_arms
+                }
+            }
+
+            /// Iterate over every value, ordered as the variants of the enum
+            #vis fn iter(&self) -> ::core::array::IntoIter<&#enum_name_w_generics, #count> {
+                [#(&self.#key_names),*].into_iter()
+            }
+
+            #(#accessors)*
+        }
+
+        #[automatically_derived]
+        impl #impl_generics ::core::ops::Index<#key_enum_name> for #total_name #type_generics #where_clause {
+            type Output = #enum_name_w_generics;
+
+            fn index(&self, key: #key_enum_name) -> &Self::Output {
+                self.get(&key)
+            }
+        }
+    }
+}
+
+/// Implements the builder of the total map
+///
+/// Defines `insert` `try_insert` `with` `missing_keys` and `build`, which fails with the missing keys
+pub(crate) fn generate_builder_impl(
+    map_attr: &BaseAttr,
+    enum_type: &EnumType,
+    enum_data: &DataEnum,
+    key_enum_name: &Ident,
+    total_name: &Ident,
+    builder_name: &Ident,
+) -> TokenStream {
+    let EnumType { enum_name, generics, .. } = enum_type;
+    let (impl_generics, type_generics, where_clause) = generics.split_for_impl();
+
+    let insert_match_body = common::enum_entries_map_to(enum_name, enum_data, key_enum_name, |enum_name, variant_name, skip_fields, _key_enum_name, key_name| {
+        quote! { #enum_name::#variant_name #skip_fields => Ok(self.#key_name.replace(value)), }
+    });
+    let insert_skipped_arms = common::skipped_arms(enum_type, |_| quote!(Err(value)));
+
+    let key_names = common::keys_data(enum_data).variants.iter().map(|variant| KeyNameAttr::of(variant).key_name(variant)).collect::<Vec<_>>();
+    let bindings = key_names.iter().map(binding).collect::<Vec<_>>();
+
+    let vis = &map_attr.visibility;
+    let enum_name_w_generics = quote!(#enum_name #type_generics);
+    quote! {
+        #[automatically_derived]
+        #[allow(dead_code, non_snake_case)]
+        impl #impl_generics #builder_name #type_generics #where_clause {
+            /// Insert `value` under its key, returning the value previously inserted under it, or `value` itself if it is a skipped variant
+            ///
+            /// See `try_insert` to tell a skipped variant apart from a replaced value
+            #vis fn insert(&mut self, value: #enum_name_w_generics) -> Option<#enum_name_w_generics> {
+                self.try_insert(value).unwrap_or_else(Some)
+            }
+
+            /// Insert `value` under its key, returning the value previously inserted under it,
+            /// fails with `value` given back if it is a skipped variant
+            #vis fn try_insert(&mut self, value: #enum_name_w_generics) -> Result<Option<#enum_name_w_generics>, #enum_name_w_generics> {
+                match value {
+                    #insert_match_body
+                    #insert_skipped_arms
+                }
+            }
+
+            /// Insert `value` under its key, replacing the value previously inserted under it, a skipped variant is left out
+            #vis fn with(mut self, value: #enum_name_w_generics) -> Self {
+                self.insert(value);
+                self
+            }
+
+            /// Keys without a value yet, ordered as the variants of the enum
+            #vis fn missing_keys(&self) -> _variant_map::keyset::KeySet<#key_enum_name> {
+                let mut missing = _variant_map::keyset::KeySet::new();
+                #(
+                    if self.#key_names.is_none() {
+                        missing.insert(#key_enum_name::#key_names);
+                    }
+                )*
+                missing
+            }
+
+            /// Build the total map, fails with the missing keys unless every variant was inserted
+            #vis fn build(self) -> Result<#total_name #type_generics, _variant_map::total::MissingKeys<#key_enum_name>> {
+                match self {
+                    #builder_name { #(#key_names: Some(#bindings)),* } => Ok(#total_name { #(#key_names: #bindings),* }),
+                    builder => Err(_variant_map::total::MissingKeys::new(builder.missing_keys())),
+                }
+            }
+        }
+    }
+}
+
+/// Implements the conversion of the total map from the partial map of the enum, with [TryFrom], and back into it
+pub(crate) fn generate_impl_conversions(
+    enum_type: &EnumType,
+    enum_data: &DataEnum,
+    key_enum_name: &Ident,
+    total_name: &Ident,
+    builder_name: &Ident,
+    partial_map: &TokenStream,
+) -> TokenStream {
+    let EnumType { generics, .. } = enum_type;
+    let (impl_generics, type_generics, where_clause) = generics.split_for_impl();
+
+    let key_names = common::keys_data(enum_data).variants.iter().map(|variant| KeyNameAttr::of(variant).key_name(variant)).collect::<Vec<_>>();
+
+    quote! {
+        #[automatically_derived]
+        impl #impl_generics TryFrom<#partial_map> for #total_name #type_generics #where_clause {
+            type Error = _variant_map::total::MissingKeys<#key_enum_name>;
+
+            fn try_from(mut partial: #partial_map) -> Result<Self, Self::Error> {
+                let mut builder = #builder_name::default();
+                #(
+                    if let Some(value) = partial.remove(&#key_enum_name::#key_names) {
+                        builder.insert(value);
+                    }
+                )*
+                builder.build()
+            }
+        }
+
+        #[automatically_derived]
+        impl #impl_generics From<#total_name #type_generics> for #partial_map #where_clause {
+            fn from(total: #total_name #type_generics) -> Self {
+                let mut partial = <#partial_map>::default();
+                #(
+                    partial.insert(total.#key_names);
+                )*
+                partial
+            }
+        }
+    }
+}
@@ -0,0 +1,9 @@
+use variant_map_derive::VariantStore;
+
+#[derive(VariantStore)]
+enum MyEnum {
+    Value(i32),
+    ValueMut(i32),
+}
+
+fn main() {}
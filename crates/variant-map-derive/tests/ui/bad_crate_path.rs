@@ -0,0 +1,10 @@
+use variant_map_derive::VariantStore;
+
+#[derive(VariantStore)]
+#[VariantStore(crate = "platform::variant_map")]
+enum MyEnum {
+    A,
+    B(i32),
+}
+
+fn main() {}
@@ -0,0 +1,21 @@
+use variant_map_derive::VariantStore;
+
+#[derive(VariantStore)]
+#[VariantStore(datastruct = "ArrayMap")]
+enum ArrayEnum {
+    #[key(by = 0)]
+    A(u8),
+}
+
+#[derive(VariantStore)]
+#[VariantStore(total, strict)]
+enum MyEnum {
+    #[key(by = 1)]
+    A(u8),
+    #[key(by = id)]
+    B { name: String },
+    #[key(with = 0)]
+    C(u8),
+}
+
+fn main() {}
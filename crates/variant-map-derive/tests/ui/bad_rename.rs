@@ -0,0 +1,34 @@
+use variant_map_derive::VariantStore;
+
+#[derive(VariantStore)]
+#[VariantStore(keys(rename_all = "Title Case"))]
+enum InvalidCase {
+    A,
+}
+
+#[derive(VariantStore)]
+#[VariantStore(keys(rename_all(code = "kebab-case")))]
+enum KebabCode {
+    A,
+}
+
+#[derive(PartialEq, Eq, Hash)]
+enum Kind {
+    A,
+}
+
+#[derive(VariantStore)]
+#[VariantStore(keys(use = "Kind", rename_all = "snake_case"))]
+enum UserKeys {
+    #[key(Kind::A)]
+    A,
+}
+
+#[derive(VariantStore)]
+#[VariantStore(datastruct = "StructMap")]
+#[VariantStruct(field_case = "kebab-case")]
+enum KebabFields {
+    A,
+}
+
+fn main() {}
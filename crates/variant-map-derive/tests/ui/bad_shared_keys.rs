@@ -0,0 +1,25 @@
+use variant_map_derive::VariantStore;
+
+#[derive(VariantStore)]
+#[VariantStore(datastruct = "BTreeMap", serde_format = "object")]
+enum ObjectEnum {
+    #[key_name(code = "Network", serde = "network")]
+    Wifi(String),
+    #[key_name(code = "Network")]
+    Ethernet,
+    #[key(by = 0)]
+    #[key_name(code = "Network")]
+    Tethering(u8),
+}
+
+#[derive(VariantStore)]
+#[VariantStore(datastruct = "StructMap")]
+#[VariantStruct(storage = "payload")]
+enum PayloadEnum {
+    #[key_name(code = "Network")]
+    Wifi(String),
+    #[key_name(code = "Network")]
+    Ethernet,
+}
+
+fn main() {}
@@ -0,0 +1,26 @@
+use variant_map_derive::VariantStore;
+
+#[derive(VariantStore)]
+#[VariantStore(skipped = "drop")]
+enum MyEnum {
+    A,
+    #[key_name(skip = true)]
+    Unknown,
+}
+
+#[derive(VariantStore)]
+#[VariantStore(serde_format = "object")]
+enum ObjectEnum {
+    #[key_name(skip)]
+    Unknown,
+    A(u8),
+}
+
+#[derive(VariantStore)]
+enum AliasEnum {
+    A,
+    #[variant_store(skip, code = "B")]
+    Unknown,
+}
+
+fn main() {}
@@ -0,0 +1,27 @@
+use variant_map_derive::VariantStore;
+
+#[derive(Debug, PartialEq, Eq, Hash)]
+enum Kind {
+    Text,
+    Number,
+}
+
+#[derive(VariantStore)]
+#[VariantStore(keys(use = "Kind", name = "Keys"))]
+enum MyEnum {
+    #[key(Kind::Text)]
+    A,
+    #[key(Other::Number)]
+    B(i32),
+    #[key(Kind::Text)]
+    C,
+    D,
+}
+
+#[derive(VariantStore)]
+enum NoUse {
+    #[key(Kind::Text)]
+    A,
+}
+
+fn main() {}
@@ -0,0 +1,10 @@
+use variant_map_derive::VariantStore;
+
+#[derive(VariantStore)]
+#[VariantStore(visibility = "pub(nowhere)")]
+enum MyEnum {
+    A,
+    B(i32),
+}
+
+fn main() {}
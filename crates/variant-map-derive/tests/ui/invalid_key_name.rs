@@ -0,0 +1,10 @@
+use variant_map_derive::VariantStore;
+
+#[derive(VariantStore)]
+enum MyEnum {
+    A,
+    #[key_name(code = "not an identifier")]
+    B(i32),
+}
+
+fn main() {}
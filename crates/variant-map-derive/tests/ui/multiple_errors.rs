@@ -0,0 +1,13 @@
+use variant_map_derive::VariantStore;
+
+#[derive(VariantStore)]
+#[VariantStore(datastruct = "StructMap", keys(name = "My Keys"))]
+#[VariantStruct(name = "1Struct", storage = "fields")]
+enum MyEnum {
+    #[key_name(code = "1A")]
+    A,
+    #[key_name(serde = 2)]
+    B(i32),
+}
+
+fn main() {}
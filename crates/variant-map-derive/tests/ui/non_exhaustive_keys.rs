@@ -0,0 +1,19 @@
+use variant_map_derive::VariantStore;
+
+#[derive(Debug, PartialEq, Eq, Hash)]
+enum Kind {
+    Text,
+    Number,
+    Flag,
+}
+
+#[derive(VariantStore)]
+#[VariantStore(keys(use = "Kind", implement))]
+enum MyEnum {
+    #[key(Kind::Text)]
+    A,
+    #[key(Kind::Number)]
+    B(i32),
+}
+
+fn main() {}
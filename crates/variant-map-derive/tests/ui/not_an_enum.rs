@@ -0,0 +1,8 @@
+use variant_map_derive::VariantStore;
+
+#[derive(VariantStore)]
+struct MyStruct {
+    a: i32,
+}
+
+fn main() {}
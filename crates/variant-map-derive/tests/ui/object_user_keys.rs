@@ -0,0 +1,20 @@
+use serde::{Deserialize, Serialize};
+use variant_map_derive::VariantStore;
+
+// declared in another order than the variants using them
+#[derive(Debug, PartialEq, Eq, Hash)]
+enum Kind {
+    Text,
+    Number,
+}
+
+#[derive(Serialize, Deserialize, VariantStore)]
+#[VariantStore(keys(use = "Kind"), serde_format = "object")]
+enum MyEnum {
+    #[key(Kind::Number)]
+    Count(u32),
+    #[key(Kind::Text)]
+    Size(u32),
+}
+
+fn main() {}
@@ -0,0 +1,11 @@
+use variant_map_derive::VariantStore;
+
+#[derive(VariantStore)]
+#[VariantStore(datastruct = "StructMap")]
+#[VariantStruct(storage = "payload", features(index))]
+enum MyEnum {
+    A,
+    B(i32),
+}
+
+fn main() {}
@@ -0,0 +1,14 @@
+use variant_map_derive::VariantStore;
+
+#[derive(Debug)]
+struct Handle(u32);
+
+#[derive(VariantStore)]
+#[VariantStore(datastruct = "StructMap")]
+#[VariantStruct(storage = "payload")]
+enum MyEnum {
+    A,
+    B(Handle),
+}
+
+fn main() {}
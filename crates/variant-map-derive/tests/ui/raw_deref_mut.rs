@@ -0,0 +1,15 @@
+use variant_map_derive::VariantStore;
+use variant_map::common::MapValue;
+
+#[derive(VariantStore)]
+#[VariantStore(datastruct = "BTreeMap")]
+enum MyEnum {
+    A,
+    B(i32),
+}
+
+fn main() {
+    let mut map = MyEnum::make_map();
+    let inner: &mut std::collections::BTreeMap<MyEnumKey, MyEnum> = &mut map;
+    inner.insert(MyEnumKey::A, MyEnum::B(1));
+}
@@ -0,0 +1,14 @@
+use variant_map_derive::VariantStore;
+
+#[derive(Debug, VariantStore)]
+#[VariantStore(datastruct = "StructMap")]
+#[VariantStruct(features(index))]
+enum MyEnum {
+    A,
+    B(i32),
+}
+
+fn main() {
+    let map = MyEnumStructMap::default();
+    map[MyEnumKey::A] = Some(MyEnum::B(1));
+}
@@ -0,0 +1,14 @@
+use variant_map_derive::VariantStore;
+use variant_map::common::MapValue;
+
+#[derive(VariantStore)]
+#[VariantStore(datastruct = "HashMap")]
+enum MyEnum {
+    A,
+    B(i32),
+}
+
+fn main() {
+    let mut map = MyEnum::make_map();
+    map.insert(MyEnumKey::A, MyEnum::B(1));
+}
@@ -0,0 +1,395 @@
+#[cfg(feature = "serde")]
+use serde::de::{DeserializeOwned, SeqAccess, Visitor};
+#[cfg(feature = "serde")]
+use serde::ser::SerializeSeq;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use core::fmt::Debug;
+#[cfg(feature = "serde")]
+use core::fmt::Formatter;
+use core::marker::PhantomData;
+use core::ops::Index;
+
+use crate::__private::UncheckedMut;
+use crate::common::{IterMut, KeyMismatch, MapValue, Skipped, ValueMut, VariantKey, VariantMap};
+#[cfg(feature = "serde")]
+use crate::common::deserialize_key;
+use crate::entry::Entry;
+
+/// Trait to implement on your Enum [Keys][crate::common::MapValue::Key]
+/// Required to be a key of a [Map]
+///
+/// The [index][VariantKey::index] of the key is its position in the [Map]'s array
+pub trait ArrayKey: VariantKey {}
+
+/// [Map] wrapping an array of `N` [Option] used as associated [Map][crate::common::MapValue::Map]
+/// Keys must implement [ArrayKey]
+///
+/// Lookups are a direct access at the [index][VariantKey::index] of the key, no hashing is involved
+///
+/// The mutable references are [ValueMut] guards checking the key of the modified value, see also [VariantMap::validate]
+#[derive(Debug, Clone)]
+pub struct Map<Key, Value, const N: usize>
+where
+    Key: ArrayKey,
+{
+    inner: [Option<Value>; N],
+    marker: PhantomData<fn() -> Key>,
+}
+
+impl<Key, Value, const N: usize> Map<Key, Value, N>
+where
+    Key: ArrayKey,
+{
+    /// Insert `value` under its own key, returning the value previously stored under it
+    ///
+    /// A variant skipped with `#[key_name(skip)]` has no key, it is given back instead of being stored,
+    /// see [try_insert][Map::try_insert] to tell it apart from a replaced value
+    pub fn insert(&mut self, value: Value) -> Option<Value>
+    where
+        Value: MapValue<Key = Key>,
+    {
+        self.try_insert(value).unwrap_or_else(Some)
+    }
+
+    /// [Insert][Map::insert] `value` under its own key, giving it back with `Err` if it is a skipped variant without key
+    pub fn try_insert(&mut self, value: Value) -> Result<Option<Value>, Value>
+    where
+        Value: MapValue<Key = Key>,
+    {
+        match value.try_to_key() {
+            Ok(key) => Ok(self.inner[key.index()].replace(value)),
+            Err(_) => Err(value),
+        }
+    }
+
+    pub fn get(&self, key: &Key) -> Option<&Value> {
+        self.inner[key.index()].as_ref()
+    }
+
+    /// Mutable reference to the value stored under `key`, checked to keep its key once dropped
+    pub fn get_mut(&mut self, key: &Key) -> Option<ValueMut<'_, Value>>
+    where
+        Value: MapValue<Key = Key>,
+    {
+        self.inner[key.index()].as_mut().map(ValueMut::indexed)
+    }
+
+    pub fn remove(&mut self, key: &Key) -> Option<Value> {
+        self.inner[key.index()].take()
+    }
+
+    pub fn contains_key(&self, key: &Key) -> bool {
+        self.inner[key.index()].is_some()
+    }
+
+    pub fn len(&self) -> usize {
+        self.inner.iter().filter(|slot| slot.is_some()).count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.inner.iter().all(|slot| slot.is_none())
+    }
+
+    pub fn clear(&mut self) {
+        self.inner.iter_mut().for_each(|slot| *slot = None);
+    }
+
+    /// Iterate over the stored values in [index][VariantKey::index] order
+    pub fn iter(&self) -> core::iter::Flatten<core::slice::Iter<'_, Option<Value>>> {
+        self.inner.iter().flatten()
+    }
+
+    /// Iterate mutably over the stored values in [index][VariantKey::index] order, each checked to keep its key once dropped
+    pub fn iter_mut<'a>(&'a mut self) -> IterMut<'a, core::iter::Flatten<core::slice::IterMut<'a, Option<Value>>>, Value>
+    where
+        Value: MapValue<Key = Key>,
+    {
+        self.inner.iter_mut().flatten().map(ValueMut::indexed)
+    }
+
+    /// Keep only the values for which `f` returns `true`
+    pub fn retain<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&Value) -> bool,
+    {
+        for slot in self.inner.iter_mut() {
+            if slot.as_ref().is_some_and(|value| !f(value)) {
+                *slot = None;
+            }
+        }
+    }
+
+    /// Check that every value is stored under its own key, see [VariantMap::validate]
+    pub fn validate(&self) -> Result<(), KeyMismatch<'_, Key>>
+    where
+        Key: PartialEq,
+        Value: MapValue<Key = Key>,
+    {
+        VariantMap::validate(self)
+    }
+
+    /// [Entry] of `key`
+    pub fn entry(&mut self, key: Key) -> Entry<'_, Self>
+    where
+        Value: MapValue<Key = Key>,
+    {
+        Entry::new(self, key)
+    }
+
+    /// [Entry] of the key of `value`, fails if `value` is a skipped variant without key
+    pub fn entry_for(&mut self, value: &Value) -> Result<Entry<'_, Self>, Skipped>
+    where
+        Value: MapValue<Key = Key>,
+    {
+        Ok(Entry::new(self, value.try_to_key()?))
+    }
+}
+
+impl<Key, Value, const N: usize> VariantMap for Map<Key, Value, N>
+where
+    Key: ArrayKey,
+    Value: MapValue<Key = Key>,
+{
+    type Key = Key;
+    type Value = Value;
+    type Ref<'a> = &'a Value
+    where
+        Self: 'a;
+    type Mut<'a> = ValueMut<'a, Value>
+    where
+        Self: 'a;
+    type Iter<'a> = core::iter::Flatten<core::slice::Iter<'a, Option<Value>>>
+    where
+        Self: 'a;
+    type KeyedIter<'a> = core::iter::FilterMap<
+        core::iter::Zip<core::slice::Iter<'a, Key>, core::slice::Iter<'a, Option<Value>>>,
+        fn((&'a Key, &'a Option<Value>)) -> Option<(&'a Key, &'a Value)>,
+    >
+    where
+        Self: 'a;
+
+    fn get(&self, key: &Key) -> Option<&Value> {
+        Map::get(self, key)
+    }
+
+    fn get_mut(&mut self, key: &Key) -> Option<ValueMut<'_, Value>> {
+        Map::get_mut(self, key)
+    }
+
+    fn insert(&mut self, value: Value) -> Option<Value> {
+        Map::insert(self, value)
+    }
+
+    fn remove(&mut self, key: &Key) -> Option<Value> {
+        Map::remove(self, key)
+    }
+
+    fn contains_key(&self, key: &Key) -> bool {
+        Map::contains_key(self, key)
+    }
+
+    fn len(&self) -> usize {
+        Map::len(self)
+    }
+
+    fn is_empty(&self) -> bool {
+        Map::is_empty(self)
+    }
+
+    fn iter(&self) -> Self::Iter<'_> {
+        Map::iter(self)
+    }
+
+    fn iter_keyed(&self) -> Self::KeyedIter<'_> {
+        Key::ALL
+            .iter()
+            .zip(self.inner.iter())
+            .filter_map(|(key, slot)| slot.as_ref().map(|value| (key, value)))
+    }
+
+    fn clear(&mut self) {
+        Map::clear(self)
+    }
+}
+
+impl<Key, Value, const N: usize> UncheckedMut for Map<Key, Value, N>
+where
+    Key: ArrayKey,
+    Value: MapValue<Key = Key>,
+{
+    fn get_mut_unchecked(&mut self, key: &Key) -> Option<&mut Value> {
+        self.inner[key.index()].as_mut()
+    }
+}
+
+/// Skipped variants without key are silently left out of the map, see [Map::try_insert]
+impl<Key, Value, const N: usize> Extend<Value> for Map<Key, Value, N>
+where
+    Key: ArrayKey,
+    Value: MapValue<Key = Key>,
+{
+    fn extend<I>(&mut self, iter: I)
+    where
+        I: IntoIterator<Item = Value>,
+    {
+        for value in iter {
+            self.insert(value);
+        }
+    }
+}
+
+/// Skipped variants without key are silently left out of the map, see [Map::try_insert]
+impl<Key, Value, const N: usize> FromIterator<Value> for Map<Key, Value, N>
+where
+    Key: ArrayKey,
+    Value: MapValue<Key = Key>,
+{
+    fn from_iter<I>(iter: I) -> Self
+    where
+        I: IntoIterator<Item = Value>,
+    {
+        let mut map = Map::default();
+        map.extend(iter);
+        map
+    }
+}
+
+impl<Key, Value, const N: usize> Default for Map<Key, Value, N>
+where
+    Key: ArrayKey,
+{
+    fn default() -> Self {
+        Map {
+            inner: core::array::from_fn(|_| None),
+            marker: PhantomData,
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<Key, Value, const N: usize> Serialize for Map<Key, Value, N>
+where
+    Key: ArrayKey,
+    Value: Serialize,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut map = serializer.serialize_seq(Some(self.len()))?;
+
+        for v in self.iter() {
+            map.serialize_element(v)?
+        }
+
+        map.end()
+    }
+}
+
+#[cfg(feature = "serde")]
+struct MapVisitor<Key, Value, const N: usize>
+where
+    Key: ArrayKey,
+{
+    marker: PhantomData<fn() -> Map<Key, Value, N>>,
+}
+
+#[cfg(feature = "serde")]
+impl<'de, Key, Value, const N: usize> Visitor<'de> for MapVisitor<Key, Value, N>
+where
+    Key: ArrayKey,
+    Value: MapValue<Key = Key> + DeserializeOwned,
+{
+    type Value = Map<Key, Value, N>;
+
+    fn expecting(&self, formatter: &mut Formatter) -> core::fmt::Result {
+        write!(formatter, "MapVisitor expects to receive a sequence of untagged Enum variants")
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let mut map = Map::<Key, Value, N>::default();
+
+        while let Some(value) = seq.next_element()? {
+            let variant: Value = value;
+            if let Some(key) = deserialize_key(&variant)? {
+                map.inner[key.index()] = Some(variant);
+            }
+        }
+
+        Ok(map)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, Key, Value, const N: usize> Deserialize<'de> for Map<Key, Value, N>
+where
+    Key: ArrayKey,
+    Value: MapValue<Key = Key> + DeserializeOwned,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let visitor = MapVisitor::<Key, Value, N> {
+            marker: PhantomData,
+        };
+        deserializer.deserialize_seq(visitor)
+    }
+}
+
+/// # Panics
+///
+/// If no value is stored under the key
+impl<Key, Value, const N: usize> Index<Key> for Map<Key, Value, N>
+where
+    Key: ArrayKey + Debug,
+{
+    type Output = Value;
+
+    fn index(&self, index: Key) -> &Self::Output {
+        &self[&index]
+    }
+}
+
+/// # Panics
+///
+/// If no value is stored under the key
+impl<'a, Key, Value, const N: usize> Index<&'a Key> for Map<Key, Value, N>
+where
+    Key: ArrayKey + Debug,
+{
+    type Output = Value;
+
+    fn index(&self, index: &'a Key) -> &Self::Output {
+        self.get(index).unwrap_or_else(|| panic!("no value stored under key `{:?}`", index))
+    }
+}
+
+impl<'a, Key, Value, const N: usize> IntoIterator for &'a Map<Key, Value, N>
+where
+    Key: ArrayKey,
+{
+    type Item = &'a Value;
+    type IntoIter = core::iter::Flatten<core::slice::Iter<'a, Option<Value>>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, Key, Value, const N: usize> IntoIterator for &'a mut Map<Key, Value, N>
+where
+    Key: ArrayKey,
+    Value: MapValue<Key = Key>,
+{
+    type Item = ValueMut<'a, Value>;
+    type IntoIter = IterMut<'a, core::iter::Flatten<core::slice::IterMut<'a, Option<Value>>>, Value>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
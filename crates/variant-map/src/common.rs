@@ -1,3 +1,11 @@
+use crate::entry::Entry;
+use crate::keyset::KeySet;
+use crate::merge::{self, MergeStrategy};
+use core::cell::OnceCell;
+use core::fmt::{Debug, Display, Formatter};
+use core::error::Error;
+use core::ops::{Deref, DerefMut};
+
 /// Implement this trait on your enum to bind a [Map][MapValue::Map] and [Key][MapValue::Key] type to it
 pub trait MapValue: Sized {
 
@@ -5,11 +13,623 @@ pub trait MapValue: Sized {
     type Key;
 
     /// Map allowing 1-to-1 mapping between [Keys][MapValue::Key] and [Value][Self] (Self)
-    type Map;
+    type Map: VariantMap<Key = Self::Key, Value = Self>;
 
     /// Match each enum variant to a [Key][MapValue::Key]
+    ///
+    /// # Panics
+    ///
+    /// If the variant is skipped with `#[key_name(skip)]`, see [try_to_key][MapValue::try_to_key]
     fn to_key(&self) -> Self::Key;
 
     /// Initialize an empty [Map][MapValue::Map]
     fn make_map() -> Self::Map;
+
+    /// [Key][MapValue::Key] of the variant, unless it is skipped with `#[key_name(skip)]` and has no key
+    fn try_to_key(&self) -> Result<Self::Key, Skipped> {
+        Ok(self.to_key())
+    }
+
+    /// Whether the maps drop the skipped variants they deserialize instead of failing
+    ///
+    /// Set with `#[VariantStore(skipped = "ignore")]`, fails by default
+    const IGNORE_SKIPPED: bool = false;
+}
+
+/// Error of [MapValue::try_to_key], a variant skipped with `#[key_name(skip)]` has no key and cannot be stored in a map
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Skipped {
+    /// Name of the skipped variant
+    pub variant: &'static str,
+}
+
+impl Display for Skipped {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(f, "variant `{}` is skipped, it cannot be stored in a map", self.variant)
+    }
+}
+
+impl Error for Skipped {}
+
+/// Key of a value read by a deserializer, none for a skipped variant [ignored][MapValue::IGNORE_SKIPPED] by the maps
+#[cfg(feature = "serde")]
+pub fn deserialize_key<Value, E>(value: &Value) -> Result<Option<Value::Key>, E>
+where
+    Value: MapValue,
+    E: serde::de::Error,
+{
+    match value.try_to_key() {
+        Ok(key) => Ok(Some(key)),
+        Err(_) if Value::IGNORE_SKIPPED => Ok(None),
+        Err(skipped) => Err(E::custom(skipped)),
+    }
+}
+
+/// Metadata about the [Keys][MapValue::Key] of an enum
+///
+/// Implemented by the derive on every generated Key enum, along with inherent `ALL` (an array) and `COUNT` constants
+pub trait VariantKey: Sized + 'static {
+
+    /// Number of keys
+    const COUNT: usize;
+
+    /// Every key, ordered as the variants of the enum
+    const ALL: &'static [Self];
+
+    /// Name of the key, as de/serialized by [serde]
+    fn name(&self) -> &'static str;
+
+    /// Position of the key in [ALL][VariantKey::ALL]
+    fn index(&self) -> usize;
+
+    /// Key at position `index` in [ALL][VariantKey::ALL], if any
+    fn from_index(index: usize) -> Option<Self>;
+
+    /// Keys that must be present once a [Strict][crate::strict::Strict] map is deserialized
+    ///
+    /// Set with `#[VariantStore(strict(require(...)))]`, none by default
+    const REQUIRED: &'static [Self] = &[];
+}
+
+/// Default value of each variant of an enum, used by [Entry::or_default][crate::entry::Entry::or_default]
+///
+/// Implemented by the derive for the variants whose fields all implement [Default]
+pub trait VariantDefault: MapValue {
+
+    /// Variant of `key` with every field set to its default value
+    fn variant_default(key: &Self::Key) -> Self;
+}
+
+/// Common interface of every data structure storing enum variants
+///
+/// Implemented by [hashmap::Map][crate::hashmap::Map], [btreemap::Map][crate::btreemap::Map],
+/// [arraymap::Map][crate::arraymap::Map] and the derived `StructMap`, so generic code can be written
+/// once and the storage selected with the `datastruct` attribute of the derive
+pub trait VariantMap {
+
+    /// Type of the [Keys][MapValue::Key] of the map
+    type Key;
+
+    /// Type of the values stored in the map (the enum)
+    type Value;
+
+    /// Reference to a value of the map, `&Value` for the maps storing the enum
+    ///
+    /// A `StructMap` storing the payloads rebuilds the value, see [Rebuilt]
+    type Ref<'a>: Deref<Target = Self::Value>
+    where
+        Self: 'a;
+
+    /// Mutable reference to a value of the map, a [ValueMut] for the maps storing the enum
+    ///
+    /// A `StructMap` storing the payloads rebuilds the value and stores it back, see [Reinsert]
+    type Mut<'a>: DerefMut<Target = Self::Value>
+    where
+        Self: 'a;
+
+    /// Iterator over the values stored in the map
+    type Iter<'a>: Iterator<Item = Self::Ref<'a>>
+    where
+        Self: 'a;
+
+    /// Iterator over the values stored in the map along the key each one is stored under
+    type KeyedIter<'a>: Iterator<Item = (&'a Self::Key, Self::Ref<'a>)>
+    where
+        Self: 'a;
+
+    /// Get a reference to the value stored under `key` if any
+    fn get(&self, key: &Self::Key) -> Option<Self::Ref<'_>>;
+
+    /// Get a mutable reference to the value stored under `key` if any
+    fn get_mut(&mut self, key: &Self::Key) -> Option<Self::Mut<'_>>;
+
+    /// Insert `value` under its own key, returning the value previously stored under it
+    ///
+    /// A variant skipped with `#[key_name(skip)]` has no key, it is given back instead of being stored,
+    /// see [try_insert][VariantMap::try_insert] to tell it apart from a replaced value
+    fn insert(&mut self, value: Self::Value) -> Option<Self::Value>;
+
+    /// [Insert][VariantMap::insert] `value` under its own key, giving it back if it is a skipped variant without key
+    fn try_insert(&mut self, value: Self::Value) -> Result<Option<Self::Value>, Self::Value>
+    where
+        Self::Value: MapValue<Key = Self::Key>,
+    {
+        match value.try_to_key() {
+            Ok(_) => Ok(self.insert(value)),
+            Err(_) => Err(value),
+        }
+    }
+
+    /// Remove and return the value stored under `key` if any
+    fn remove(&mut self, key: &Self::Key) -> Option<Self::Value>;
+
+    /// Whether a value is stored under `key`
+    fn contains_key(&self, key: &Self::Key) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Number of values stored in the map
+    fn len(&self) -> usize;
+
+    /// Whether the map holds no value
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Iterate over the values stored in the map
+    fn iter(&self) -> Self::Iter<'_>;
+
+    /// Iterate over the values stored in the map along the key each one is stored under
+    fn iter_keyed(&self) -> Self::KeyedIter<'_>;
+
+    /// Remove all values from the map
+    fn clear(&mut self);
+
+    /// Move every value of `other` into the map
+    ///
+    /// The values of keys held by both maps are resolved by the [strategy][MergeStrategy]:
+    /// [KeepLeft][crate::merge::KeepLeft], [KeepRight][crate::merge::KeepRight] or a closure, see [merge_with][VariantMap::merge_with].
+    /// See [overlay][crate::merge::overlay] to merge without modifying the maps
+    ///
+    /// # Errors
+    ///
+    /// If the strategy resolves to a skipped variant, its key is left without value and the merge goes on with
+    /// the other keys, the first skipped variant is reported once all keys are merged
+    ///
+    /// # Panics
+    ///
+    /// If the strategy resolves to a variant of another key
+    fn merge<S>(&mut self, mut other: Self, strategy: S) -> Result<(), Skipped>
+    where
+        Self: Sized,
+        Self::Key: VariantKey,
+        Self::Value: MapValue<Key = Self::Key>,
+        S: MergeStrategy<Self::Key, Self::Value>,
+    {
+        let mut result = Ok(());
+        for key in Self::Key::ALL {
+            if let Some(right) = other.remove(key) {
+                let value = match self.remove(key) {
+                    Some(left) => merge::resolve(&strategy, key, left, right, MapValue::try_to_key),
+                    None => Ok(right),
+                };
+                match value {
+                    Ok(value) => {
+                        self.insert(value);
+                    }
+                    Err(skipped) => result = result.and(Err(skipped)),
+                }
+            }
+        }
+
+        result
+    }
+
+    /// [Merge][VariantMap::merge] `other` into the map, resolving the values of keys held by both maps with `f(key, left, right)`
+    ///
+    /// # Errors
+    ///
+    /// If `f` returns a skipped variant, see [merge][VariantMap::merge]
+    ///
+    /// # Panics
+    ///
+    /// If `f` returns a variant of another key
+    fn merge_with<F>(&mut self, other: Self, f: F) -> Result<(), Skipped>
+    where
+        Self: Sized,
+        Self::Key: VariantKey,
+        Self::Value: MapValue<Key = Self::Key>,
+        F: Fn(&Self::Key, Self::Value, Self::Value) -> Self::Value,
+    {
+        self.merge(other, f)
+    }
+
+    /// [Set][KeySet] of the keys holding a value
+    fn keys_set(&self) -> KeySet<Self::Key>
+    where
+        Self::Key: VariantKey,
+        Self::Value: MapValue<Key = Self::Key>,
+    {
+        self.iter_keyed().map(|(key, _)| key).collect()
+    }
+
+    /// Check that every value is stored under its own [key][MapValue::to_key]
+    ///
+    /// Inserting through the maps always keeps this invariant and a value modified in place through a [ValueMut]
+    /// is checked once dropped, but the map is left inconsistent when the panic of that check is caught
+    fn validate(&self) -> Result<(), KeyMismatch<'_, Self::Key>>
+    where
+        Self::Key: PartialEq,
+        Self::Value: MapValue<Key = Self::Key>,
+    {
+        for (key, value) in self.iter_keyed() {
+            let found = value.to_key();
+            if found != *key {
+                return Err(KeyMismatch { key, found });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Get the value stored under the key of `value`, inserting `value` first if the key holds no value
+    ///
+    /// Fails with `value` given back if it is a skipped variant without key
+    fn get_or_insert(&mut self, value: Self::Value) -> Result<Self::Mut<'_>, Self::Value>
+    where
+        Self::Key: VariantKey,
+        Self::Value: MapValue<Key = Self::Key>,
+    {
+        match value.try_to_key() {
+            Ok(key) => self.entry(key).or_insert(value),
+            Err(_) => Err(value),
+        }
+    }
+
+    /// Get the value stored under `key`, inserting the result of `f` first if the key holds no value
+    ///
+    /// Fails with the result of `f` given back if it is a skipped variant
+    ///
+    /// # Panics
+    ///
+    /// If `f` returns a variant of another key
+    fn get_or_insert_with<F>(&mut self, key: Self::Key, f: F) -> Result<Self::Mut<'_>, Self::Value>
+    where
+        Self::Key: VariantKey,
+        Self::Value: MapValue<Key = Self::Key>,
+        F: FnOnce() -> Self::Value,
+    {
+        self.entry(key).or_insert_with(f)
+    }
+
+    /// [Entry] of `key` for in-place manipulation, only the variant of `key` can be stored through it
+    fn entry(&mut self, key: Self::Key) -> Entry<'_, Self>
+    where
+        Self::Key: VariantKey,
+        Self::Value: MapValue<Key = Self::Key>,
+    {
+        Entry::new(self, key)
+    }
+
+    /// [Entry] of the key of `value`, fails if `value` is a skipped variant without key
+    fn entry_for(&mut self, value: &Self::Value) -> Result<Entry<'_, Self>, Skipped>
+    where
+        Self::Key: VariantKey,
+        Self::Value: MapValue<Key = Self::Key>,
+    {
+        Ok(Entry::new(self, value.try_to_key()?))
+    }
+
+    /// Insert a value read by a deserializer
+    ///
+    /// Same as [insert][VariantMap::insert] by default, [Strict][crate::strict::Strict] maps reject duplicated keys
+    #[cfg(feature = "serde")]
+    fn deserialize_insert<E>(&mut self, value: Self::Value) -> Result<(), E>
+    where
+        E: serde::de::Error,
+    {
+        self.insert(value);
+        Ok(())
+    }
+
+    /// Check the map once a deserializer inserted all of its values
+    ///
+    /// Accepts any map by default, [Strict][crate::strict::Strict] maps check their [required keys][VariantKey::REQUIRED]
+    #[cfg(feature = "serde")]
+    fn deserialize_finish<E>(&self) -> Result<(), E>
+    where
+        E: serde::de::Error,
+    {
+        Ok(())
+    }
+}
+
+/// Error of [VariantMap::validate], a value stored under the key of another variant
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyMismatch<'a, Key> {
+
+    /// Key the value is stored under
+    pub key: &'a Key,
+
+    /// [Key][MapValue::to_key] of the value
+    pub found: Key,
+}
+
+impl<Key> Display for KeyMismatch<'_, Key>
+where
+    Key: VariantKey,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(f, "value of key `{}` stored under key `{}`", self.found.name(), self.key.name())
+    }
+}
+
+impl<Key> Error for KeyMismatch<'_, Key> where Key: VariantKey + Debug {}
+
+/// Mutable reference to a value stored in a map, checking once dropped that the value still has the key it is stored under
+///
+/// The [Mut][VariantMap::Mut] of the maps storing the enum, also handed out by their `iter_mut`.
+/// The payload of the value can be freely modified, replacing the value with another variant is a bug
+///
+/// # Panics
+///
+/// On drop, if the value was replaced by a variant of another key or a skipped variant, unless the thread is already panicking.
+/// Without the `std` feature, the guard cannot tell whether the thread is already panicking:
+/// dropping a guard holding a replaced value while unwinding panics a second time and aborts
+pub struct ValueMut<'a, Value>
+where
+    Value: MapValue,
+{
+    value: &'a mut Value,
+    key: Value::Key,
+    same_key: fn(&Value::Key, &Value::Key) -> bool,
+}
+
+/// Iterator guarding with a [ValueMut] each mutable reference yielded by `I`, returned by the `iter_mut` of the maps
+pub type IterMut<'a, I, Value> = core::iter::Map<I, fn(&'a mut Value) -> ValueMut<'a, Value>>;
+
+impl<'a, Value> ValueMut<'a, Value>
+where
+    Value: MapValue,
+{
+    /// Guards `value`, stored under its own key, the keys are compared with [PartialEq]
+    pub fn new(value: &'a mut Value) -> Self
+    where
+        Value::Key: PartialEq,
+    {
+        let key = value.to_key();
+        ValueMut { value, key, same_key: PartialEq::eq }
+    }
+
+    /// Guards `value`, stored under its own key, the keys are compared by [index][VariantKey::index]
+    pub fn indexed(value: &'a mut Value) -> Self
+    where
+        Value::Key: VariantKey,
+    {
+        let key = value.to_key();
+        ValueMut { value, key, same_key: |left, right| left.index() == right.index() }
+    }
+}
+
+impl<Value> Deref for ValueMut<'_, Value>
+where
+    Value: MapValue,
+{
+    type Target = Value;
+
+    fn deref(&self) -> &Self::Target {
+        self.value
+    }
+}
+
+impl<Value> DerefMut for ValueMut<'_, Value>
+where
+    Value: MapValue,
+{
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.value
+    }
+}
+
+impl<Value> Drop for ValueMut<'_, Value>
+where
+    Value: MapValue,
+{
+    fn drop(&mut self) {
+        // a second panic while unwinding would abort
+        #[cfg(feature = "std")]
+        if std::thread::panicking() {
+            return;
+        }
+
+        assert!(
+            self.value.try_to_key().is_ok_and(|key| (self.same_key)(&key, &self.key)),
+            "a value of the map cannot be replaced by a variant of another key"
+        );
+    }
+}
+
+impl<Value> Debug for ValueMut<'_, Value>
+where
+    Value: MapValue + Debug,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        Debug::fmt(&**self, f)
+    }
+}
+
+/// Function rebuilding the value stored under a key of a map which does not store the values, if any
+pub type RebuildFn<M> = fn(&M, &<M as VariantMap>::Key) -> Option<<M as VariantMap>::Value>;
+
+/// Value rebuilt by a map which does not store it, returned in place of a reference
+///
+/// The [Ref][VariantMap::Ref] of a `StructMap` with `storage = "payload"`.
+/// Getting it does not clone anything, the value is rebuilt from a clone of the payload
+/// the first time it is dereferenced, then kept until it is dropped
+pub struct Rebuilt<'a, M>
+where
+    M: VariantMap,
+{
+    map: &'a M,
+    key: &'a M::Key,
+    rebuild: RebuildFn<M>,
+    value: OnceCell<M::Value>,
+}
+
+impl<'a, M> Rebuilt<'a, M>
+where
+    M: VariantMap,
+{
+    /// Value stored under `key` in `map`, rebuilt with `rebuild` when needed
+    ///
+    /// `map` must hold a value under `key`
+    pub fn new(map: &'a M, key: &'a M::Key, rebuild: RebuildFn<M>) -> Self {
+        Rebuilt { map, key, rebuild, value: OnceCell::new() }
+    }
+
+    /// Key of the value
+    pub fn key(&self) -> &'a M::Key {
+        self.key
+    }
+
+    /// Unwraps the rebuilt value, rebuilding it if it was never dereferenced
+    pub fn into_inner(self) -> M::Value {
+        let Rebuilt { map, key, rebuild, value } = self;
+        value.into_inner().unwrap_or_else(|| Self::rebuild(map, key, rebuild))
+    }
+
+    fn rebuild(map: &M, key: &M::Key, rebuild: RebuildFn<M>) -> M::Value {
+        rebuild(map, key).expect("a rebuilt value is only created for a key holding a value")
+    }
+}
+
+impl<M> Deref for Rebuilt<'_, M>
+where
+    M: VariantMap,
+{
+    type Target = M::Value;
+
+    fn deref(&self) -> &Self::Target {
+        self.value.get_or_init(|| Self::rebuild(self.map, self.key, self.rebuild))
+    }
+}
+
+impl<M> Debug for Rebuilt<'_, M>
+where
+    M: VariantMap,
+    M::Value: Debug,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        Debug::fmt(&**self, f)
+    }
+}
+
+impl<M> PartialEq for Rebuilt<'_, M>
+where
+    M: VariantMap,
+    M::Value: PartialEq,
+{
+    fn eq(&self, other: &Self) -> bool {
+        **self == **other
+    }
+}
+
+/// [Iterator] over the values of a map which does not store them, see [Rebuilt]
+pub struct RebuiltIter<'a, M>
+where
+    M: VariantMap,
+{
+    map: &'a M,
+    keys: core::slice::Iter<'a, M::Key>,
+    rebuild: RebuildFn<M>,
+}
+
+impl<'a, M> RebuiltIter<'a, M>
+where
+    M: VariantMap,
+    M::Key: VariantKey,
+{
+    /// Iterate over the values of `map` in the order of [VariantKey::ALL]
+    pub fn new(map: &'a M, rebuild: RebuildFn<M>) -> Self {
+        RebuiltIter { map, keys: M::Key::ALL.iter(), rebuild }
+    }
+}
+
+impl<'a, M> Iterator for RebuiltIter<'a, M>
+where
+    M: VariantMap,
+{
+    type Item = Rebuilt<'a, M>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let map = self.map;
+        self.keys
+            .by_ref()
+            .find(|key| map.contains_key(key))
+            .map(|key| Rebuilt::new(map, key, self.rebuild))
+    }
+}
+
+/// Value taken out of a map which does not store it, [inserted][VariantMap::insert] back once dropped
+///
+/// The [Mut][VariantMap::Mut] of a `StructMap` with `storage = "payload"`,
+/// the payload is only written back to the map when the guard is dropped.
+/// A value changed into another variant is stored under the key of that variant, replacing the value stored there
+pub struct Reinsert<'a, M>
+where
+    M: VariantMap,
+{
+    map: &'a mut M,
+    value: Option<M::Value>,
+}
+
+impl<'a, M> Reinsert<'a, M>
+where
+    M: VariantMap,
+{
+    /// Guards `value`, removed from `map`
+    pub fn new(map: &'a mut M, value: M::Value) -> Self {
+        Reinsert { map, value: Some(value) }
+    }
+}
+
+impl<M> Deref for Reinsert<'_, M>
+where
+    M: VariantMap,
+{
+    type Target = M::Value;
+
+    fn deref(&self) -> &Self::Target {
+        self.value.as_ref().expect("value is only taken on drop")
+    }
+}
+
+impl<M> DerefMut for Reinsert<'_, M>
+where
+    M: VariantMap,
+{
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.value.as_mut().expect("value is only taken on drop")
+    }
+}
+
+impl<M> Drop for Reinsert<'_, M>
+where
+    M: VariantMap,
+{
+    fn drop(&mut self) {
+        if let Some(value) = self.value.take() {
+            self.map.insert(value);
+        }
+    }
+}
+
+impl<M> Debug for Reinsert<'_, M>
+where
+    M: VariantMap,
+    M::Value: Debug,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        Debug::fmt(&**self, f)
+    }
 }
@@ -0,0 +1,181 @@
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use alloc::vec::Vec;
+use core::fmt::{Debug, Formatter};
+
+use crate::common::{MapValue, VariantKey, VariantMap};
+use crate::keyset::KeySet;
+
+/// Differences between two maps of the same enum `E`, computed by [diff] and applied by [apply]
+///
+/// Serializable with the `serde` feature to send deltas of a map instead of the full map,
+/// the variants are de/serialized as in the maps and the removed keys as a [KeySet]
+///
+/// # Example
+///
+/// ```
+/// use serde::{Deserialize, Serialize};
+/// use variant_map::common::{MapValue, VariantMap};
+/// use variant_map::diff::{apply, diff, MapDiff};
+/// use variant_map_derive::VariantStore;
+///
+/// #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, VariantStore)]
+/// enum State {
+///     Power(bool),
+///     Volume(u8),
+///     Muted,
+/// }
+///
+/// fn main() {
+///     let mut device = State::make_map();
+///     device.insert(State::Power(true));
+///     device.insert(State::Muted);
+///
+///     let mut replica = State::make_map();
+///     replica.insert(State::Power(true));
+///     replica.insert(State::Muted);
+///
+///     device.insert(State::Volume(10));
+///     device.remove(&StateKey::Muted);
+///
+///     let delta = serde_json::to_string(&diff(&replica, &device)).unwrap();
+///     assert_eq!(delta, r#"{"added":[{"Volume":10}],"removed":["Muted"],"changed":[]}"#);
+///
+///     apply(&mut replica, &serde_json::from_str::<MapDiff<State>>(&delta).unwrap());
+///     assert_eq!(replica.keys_set(), device.keys_set());
+/// }
+/// ```
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(bound(
+    serialize = "E: Serialize, E::Key: VariantKey",
+    deserialize = "E: Deserialize<'de>, E::Key: VariantKey"
+)))]
+pub struct MapDiff<E>
+where
+    E: MapValue,
+{
+    added: Vec<E>,
+    removed: KeySet<E::Key>,
+    changed: Vec<E>,
+}
+
+impl<E> MapDiff<E>
+where
+    E: MapValue,
+    E::Key: VariantKey,
+{
+    /// Variants of keys holding no value in the old map
+    pub fn added(&self) -> &[E] {
+        &self.added
+    }
+
+    /// Keys holding no value in the new map
+    pub fn removed(&self) -> &KeySet<E::Key> {
+        &self.removed
+    }
+
+    /// Variants of keys holding a different value in the old map
+    pub fn changed(&self) -> &[E] {
+        &self.changed
+    }
+
+    /// Whether both maps held the same values
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+impl<E> Default for MapDiff<E>
+where
+    E: MapValue,
+    E::Key: VariantKey,
+{
+    fn default() -> Self {
+        MapDiff {
+            added: Vec::new(),
+            removed: KeySet::new(),
+            changed: Vec::new(),
+        }
+    }
+}
+
+// the traits are implemented by hand, the derives would not require `VariantKey` on the keys of the removed set
+
+impl<E> Debug for MapDiff<E>
+where
+    E: MapValue + Debug,
+    E::Key: VariantKey + Debug,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("MapDiff")
+            .field("added", &self.added)
+            .field("removed", &self.removed)
+            .field("changed", &self.changed)
+            .finish()
+    }
+}
+
+impl<E> Clone for MapDiff<E>
+where
+    E: MapValue + Clone,
+{
+    fn clone(&self) -> Self {
+        MapDiff {
+            added: self.added.clone(),
+            removed: self.removed,
+            changed: self.changed.clone(),
+        }
+    }
+}
+
+impl<E> PartialEq for MapDiff<E>
+where
+    E: MapValue + PartialEq,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.added == other.added && self.removed == other.removed && self.changed == other.changed
+    }
+}
+
+/// Differences turning the map `old` into the map `new`
+///
+/// Values held under the same key by both maps are compared with [PartialEq]
+pub fn diff<M>(old: &M, new: &M) -> MapDiff<M::Value>
+where
+    M: VariantMap,
+    M::Key: VariantKey,
+    M::Value: MapValue<Key = M::Key> + PartialEq + Clone,
+{
+    let mut diff = MapDiff::default();
+
+    for key in M::Key::ALL {
+        match (old.get(key), new.get(key)) {
+            (None, Some(value)) => diff.added.push((*value).clone()),
+            (Some(_), None) => diff.removed.extend([key]),
+            (Some(old_value), Some(new_value)) if *old_value != *new_value => diff.changed.push((*new_value).clone()),
+            _ => {}
+        }
+    }
+
+    diff
+}
+
+/// Apply the differences `diff` to `map`
+///
+/// Applied to the old map given to [diff], the map then holds the same values as the new one
+///
+/// Skipped variants without key, which only a deserialized diff may hold, are silently left out of the map
+pub fn apply<M>(map: &mut M, diff: &MapDiff<M::Value>)
+where
+    M: VariantMap,
+    M::Key: VariantKey,
+    M::Value: MapValue<Key = M::Key> + Clone,
+{
+    for key in diff.removed.iter() {
+        map.remove(&key);
+    }
+
+    for value in diff.added.iter().chain(diff.changed.iter()) {
+        map.insert(value.clone());
+    }
+}
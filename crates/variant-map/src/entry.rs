@@ -0,0 +1,274 @@
+use crate::common::{MapValue, Skipped, VariantDefault, VariantKey, VariantMap};
+
+/// View into the slot of a single [Key][VariantMap::Key] of a [VariantMap], either occupied or vacant
+///
+/// Obtained with [VariantMap::entry] or [VariantMap::entry_for].
+/// Unlike the entries of the std maps, a value is only stored if its [key][MapValue::to_key] is the key of the entry,
+/// so the map never holds a variant under the key of another one.
+/// Stored values are handed out as the [Mut][VariantMap::Mut] references of the map
+///
+/// The methods inserting a value give it back with `Err` if it is a variant skipped with `#[key_name(skip)]`,
+/// which has no key and cannot be stored
+///
+/// # Panics
+///
+/// Every method inserting a value panics if the value is a variant of another key than the key of the entry
+///
+/// # Example
+///
+/// ```
+/// use variant_map::common::{MapValue, VariantMap};
+/// use variant_map_derive::VariantStore;
+///
+/// #[derive(Debug, PartialEq, VariantStore)]
+/// enum Counter {
+///     Hits(u32),
+///     Misses(u32),
+/// }
+///
+/// fn main() {
+///     let mut map = Counter::make_map();
+///     for _ in 0..3 {
+///         map.entry(CounterKey::Hits)
+///             .and_modify(|hits| if let Counter::Hits(n) = hits { *n += 1 })
+///             .or_insert(Counter::Hits(1))
+///             .unwrap();
+///     }
+///     map.entry(CounterKey::Misses).or_default();
+///
+///     assert_eq!(map.get(&CounterKey::Hits), Some(&Counter::Hits(3)));
+///     assert_eq!(map.get(&CounterKey::Misses), Some(&Counter::Misses(0)));
+/// }
+/// ```
+pub enum Entry<'a, M>
+where
+    M: VariantMap + ?Sized,
+{
+    /// The key holds a value
+    Occupied(OccupiedEntry<'a, M>),
+
+    /// The key holds no value
+    Vacant(VacantEntry<'a, M>),
+}
+
+/// Entry of a key holding a value, see [Entry]
+pub struct OccupiedEntry<'a, M>
+where
+    M: VariantMap + ?Sized,
+{
+    map: &'a mut M,
+    key: M::Key,
+}
+
+/// Entry of a key holding no value, see [Entry]
+pub struct VacantEntry<'a, M>
+where
+    M: VariantMap + ?Sized,
+{
+    map: &'a mut M,
+    key: M::Key,
+}
+
+/// Fails if `value` is a skipped variant without key
+///
+/// # Panics
+///
+/// If `value` is a variant of another key than `key`
+fn check_key<Key, Value>(key: &Key, value: &Value) -> Result<(), Skipped>
+where
+    Key: VariantKey,
+    Value: MapValue<Key = Key>,
+{
+    let value_key = value.try_to_key()?;
+    assert!(
+        value_key.index() == key.index(),
+        "entry of key `{}` cannot hold a value of key `{}`",
+        key.name(),
+        value_key.name()
+    );
+    Ok(())
+}
+
+impl<'a, M> Entry<'a, M>
+where
+    M: VariantMap + ?Sized,
+    M::Key: VariantKey,
+    M::Value: MapValue<Key = M::Key>,
+{
+    /// Entry of `key` in `map`
+    pub fn new(map: &'a mut M, key: M::Key) -> Self {
+        if map.contains_key(&key) {
+            Entry::Occupied(OccupiedEntry { map, key })
+        } else {
+            Entry::Vacant(VacantEntry { map, key })
+        }
+    }
+
+    /// Key of the entry
+    pub fn key(&self) -> &M::Key {
+        match self {
+            Entry::Occupied(entry) => entry.key(),
+            Entry::Vacant(entry) => entry.key(),
+        }
+    }
+
+    /// Insert `default` if the key holds no value, returning the stored value
+    ///
+    /// Fails with `default` given back if it is a skipped variant and the key holds no value
+    pub fn or_insert(self, default: M::Value) -> Result<M::Mut<'a>, M::Value> {
+        match self {
+            Entry::Occupied(entry) => Ok(entry.into_mut()),
+            Entry::Vacant(entry) => entry.insert(default),
+        }
+    }
+
+    /// Insert the result of `default` if the key holds no value, returning the stored value
+    ///
+    /// Fails with the result of `default` given back if it is a skipped variant
+    pub fn or_insert_with<F>(self, default: F) -> Result<M::Mut<'a>, M::Value>
+    where
+        F: FnOnce() -> M::Value,
+    {
+        match self {
+            Entry::Occupied(entry) => Ok(entry.into_mut()),
+            Entry::Vacant(entry) => entry.insert(default()),
+        }
+    }
+
+    /// Insert the result of `default` called with the key if the key holds no value, returning the stored value
+    ///
+    /// Fails with the result of `default` given back if it is a skipped variant
+    pub fn or_insert_with_key<F>(self, default: F) -> Result<M::Mut<'a>, M::Value>
+    where
+        F: FnOnce(&M::Key) -> M::Value,
+    {
+        match self {
+            Entry::Occupied(entry) => Ok(entry.into_mut()),
+            Entry::Vacant(entry) => {
+                let value = default(entry.key());
+                entry.insert(value)
+            }
+        }
+    }
+
+    /// Insert the [default value of the variant][VariantDefault] of the key if it holds no value, returning the stored value
+    pub fn or_default(self) -> M::Mut<'a>
+    where
+        M::Value: VariantDefault,
+    {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                let value = M::Value::variant_default(entry.key());
+                // the default value of a key is a variant of that key, never a skipped one
+                entry.insert(value).unwrap_or_else(|_| unreachable!("the default value of a key has that key"))
+            }
+        }
+    }
+
+    /// Modify the value held by the key, if any
+    ///
+    /// `f` is free to replace the value, but not with another variant
+    ///
+    /// # Panics
+    ///
+    /// If `f` leaves a variant of another key or a skipped variant
+    pub fn and_modify<F>(self, f: F) -> Self
+    where
+        F: FnOnce(&mut M::Value),
+    {
+        match self {
+            Entry::Occupied(entry) => {
+                // checked while the value is borrowed, before the [Mut][VariantMap::Mut] checks it on drop
+                let mut value = entry.map.get_mut(&entry.key).expect("occupied entry holds a value");
+                f(&mut value);
+                if let Err(skipped) = check_key(&entry.key, &*value) {
+                    panic!("entry of key `{}` cannot hold the skipped variant `{}`", entry.key.name(), skipped.variant);
+                }
+                drop(value);
+                Entry::Occupied(entry)
+            }
+            Entry::Vacant(entry) => Entry::Vacant(entry),
+        }
+    }
+}
+
+impl<'a, M> OccupiedEntry<'a, M>
+where
+    M: VariantMap + ?Sized,
+    M::Key: VariantKey,
+    M::Value: MapValue<Key = M::Key>,
+{
+    /// Key of the entry
+    pub fn key(&self) -> &M::Key {
+        &self.key
+    }
+
+    /// Value held by the key
+    pub fn get(&self) -> M::Ref<'_> {
+        self.map.get(&self.key).expect("occupied entry holds a value")
+    }
+
+    /// Mutable value held by the key
+    ///
+    /// Replacing it with another variant is a logic error, use [insert][OccupiedEntry::insert] instead
+    pub fn get_mut(&mut self) -> M::Mut<'_> {
+        self.map.get_mut(&self.key).expect("occupied entry holds a value")
+    }
+
+    /// Converts the entry into the mutable value held by the key, bound to the lifetime of the map
+    pub fn into_mut(self) -> M::Mut<'a> {
+        self.map.get_mut(&self.key).expect("occupied entry holds a value")
+    }
+
+    /// Replace the value held by the key with `value`, returning the previous one
+    ///
+    /// Fails with `value` given back if it is a skipped variant
+    ///
+    /// # Panics
+    ///
+    /// If `value` is a variant of another key
+    pub fn insert(&mut self, value: M::Value) -> Result<M::Value, M::Value> {
+        match check_key(&self.key, &value) {
+            Ok(()) => Ok(self.map.insert(value).expect("occupied entry holds a value")),
+            Err(_) => Err(value),
+        }
+    }
+
+    /// Remove the value held by the key
+    pub fn remove(self) -> M::Value {
+        self.map.remove(&self.key).expect("occupied entry holds a value")
+    }
+}
+
+impl<'a, M> VacantEntry<'a, M>
+where
+    M: VariantMap + ?Sized,
+    M::Key: VariantKey,
+    M::Value: MapValue<Key = M::Key>,
+{
+    /// Key of the entry
+    pub fn key(&self) -> &M::Key {
+        &self.key
+    }
+
+    /// Unwraps the key of the entry
+    pub fn into_key(self) -> M::Key {
+        self.key
+    }
+
+    /// Store `value` under the key, returning the stored value
+    ///
+    /// Fails with `value` given back if it is a skipped variant
+    ///
+    /// # Panics
+    ///
+    /// If `value` is a variant of another key
+    pub fn insert(self, value: M::Value) -> Result<M::Mut<'a>, M::Value> {
+        if check_key(&self.key, &value).is_err() {
+            return Err(value);
+        }
+        self.map.insert(value);
+        Ok(self.map.get_mut(&self.key).expect("inserted entry holds a value"))
+    }
+}
@@ -1,17 +1,34 @@
+#[cfg(feature = "serde")]
 use serde::de::{DeserializeOwned, SeqAccess, Visitor};
+#[cfg(feature = "serde")]
 use serde::ser::SerializeSeq;
+#[cfg(feature = "serde")]
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
-use std::collections::HashMap;
-use std::fmt::Formatter;
-use std::hash::Hash;
-use std::marker::PhantomData;
-use std::ops::{Deref, DerefMut, Index, IndexMut};
+#[cfg(feature = "hashbrown")]
+use hashbrown::{hash_map, HashMap};
+#[cfg(not(feature = "hashbrown"))]
+use std::collections::{hash_map, HashMap};
+use core::fmt::Debug;
+#[cfg(feature = "serde")]
+use core::fmt::Formatter;
+use core::hash::Hash;
+#[cfg(feature = "serde")]
+use core::marker::PhantomData;
+use core::ops::{Deref, Index};
 
-use crate::common::MapValue;
+use crate::__private::UncheckedMut;
+use crate::common::{IterMut, KeyMismatch, MapValue, Skipped, ValueMut, VariantKey, VariantMap};
+#[cfg(feature = "serde")]
+use crate::common::deserialize_key;
+use crate::entry::Entry;
 
 
 /// [Map] wrapping a [HashMap] used as associated [Map][crate::common::MapValue::Map]
 /// Keys must implement [HashKey]
+///
+/// The inner [HashMap] is only exposed through [Deref], its mutations could store a value under the key of another variant.
+/// The map instead provides the mutations keeping each value under its own key, the mutable references are [ValueMut] guards
+/// checking the key of the modified value, see also [VariantMap::validate]
 #[derive(Debug, Clone)]
 pub struct Map<Key, Value>
     where
@@ -28,19 +45,123 @@ impl<K, V> Map<K, V>
     where
         K: HashKey,
 {
+    /// Insert `value` under its own key, returning the value previously stored under it
+    ///
+    /// A variant skipped with `#[key_name(skip)]` has no key, it is given back instead of being stored,
+    /// see [try_insert][Map::try_insert] to tell it apart from a replaced value
     pub fn insert(&mut self, value: V) -> Option<V>
         where
             K: HashKey,
             V: MapValue<Key=K>,
     {
-        let key: K = value.to_key();
-        self.inner.insert(key, value)
+        self.try_insert(value).unwrap_or_else(Some)
+    }
+
+    /// [Insert][Map::insert] `value` under its own key, giving it back with `Err` if it is a skipped variant without key
+    pub fn try_insert(&mut self, value: V) -> Result<Option<V>, V>
+        where
+            K: HashKey,
+            V: MapValue<Key=K>,
+    {
+        match value.try_to_key() {
+            Ok(key) => Ok(self.inner.insert(key, value)),
+            Err(_) => Err(value),
+        }
+    }
+
+    /// [Entry] of `key`, shadowing the entry of the inner map which accepts values of any variant
+    pub fn entry(&mut self, key: K) -> Entry<'_, Self>
+        where
+            K: VariantKey,
+            V: MapValue<Key=K>,
+    {
+        Entry::new(self, key)
+    }
+
+    /// [Entry] of the key of `value`, fails if `value` is a skipped variant without key
+    pub fn entry_for(&mut self, value: &V) -> Result<Entry<'_, Self>, Skipped>
+        where
+            K: VariantKey,
+            V: MapValue<Key=K>,
+    {
+        Ok(Entry::new(self, value.try_to_key()?))
     }
 }
 
+impl<Key, Value> VariantMap for Map<Key, Value>
+    where
+        Key: HashKey,
+        Value: MapValue<Key=Key>,
+{
+    type Key = Key;
+    type Value = Value;
+    type Ref<'a> = &'a Value
+        where
+            Self: 'a;
+    type Mut<'a> = ValueMut<'a, Value>
+        where
+            Self: 'a;
+    type Iter<'a> = hash_map::Values<'a, Key, Value>
+        where
+            Self: 'a;
+    type KeyedIter<'a> = hash_map::Iter<'a, Key, Value>
+        where
+            Self: 'a;
+
+    fn get(&self, key: &Key) -> Option<&Value> {
+        self.inner.get(key)
+    }
+
+    fn get_mut(&mut self, key: &Key) -> Option<ValueMut<'_, Value>> {
+        Map::get_mut(self, key)
+    }
+
+    fn insert(&mut self, value: Value) -> Option<Value> {
+        Map::insert(self, value)
+    }
+
+    fn remove(&mut self, key: &Key) -> Option<Value> {
+        self.inner.remove(key)
+    }
+
+    fn contains_key(&self, key: &Key) -> bool {
+        self.inner.contains_key(key)
+    }
+
+    fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    fn iter(&self) -> Self::Iter<'_> {
+        self.inner.values()
+    }
+
+    fn iter_keyed(&self) -> Self::KeyedIter<'_> {
+        self.inner.iter()
+    }
+
+    fn clear(&mut self) {
+        self.inner.clear()
+    }
+}
+
+impl<Key, Value> UncheckedMut for Map<Key, Value>
+    where
+        Key: HashKey,
+        Value: MapValue<Key=Key>,
+{
+    fn get_mut_unchecked(&mut self, key: &Key) -> Option<&mut Value> {
+        self.inner.get_mut(key)
+    }
+}
+
+/// # Panics
+///
+/// If a value is not stored under its own key, see [Map::new]
 impl<Key, Value> From<HashMap<Key, Value>> for Map<Key, Value>
     where
         Key: HashKey,
+        Value: MapValue<Key=Key>,
 {
     fn from(value: HashMap<Key, Value>) -> Self {
         Map::new(value)
@@ -50,9 +171,93 @@ impl<Key, Value> From<HashMap<Key, Value>> for Map<Key, Value>
 impl<Key, Value> Map<Key, Value>
     where
         Key: HashKey,
+        Value: MapValue<Key=Key>,
 {
+    /// Wraps a [HashMap] in which every value must be stored under its own [key][MapValue::to_key]
+    ///
+    /// # Panics
+    ///
+    /// If a value is stored under the key of another variant or is a skipped variant, see [try_new][Map::try_new]
     pub fn new(map: HashMap<Key, Value>) -> Self {
-        Map { inner: map }
+        Map::try_new(map).unwrap_or_else(|_| panic!("every value of a Map must be stored under its own key"))
+    }
+
+    /// Wraps a [HashMap] in which every value must be stored under its own [key][MapValue::to_key],
+    /// giving the map back if a value is stored under the key of another variant or is a skipped variant without key
+    pub fn try_new(map: HashMap<Key, Value>) -> Result<Self, HashMap<Key, Value>> {
+        if map.iter().all(|(key, value)| value.try_to_key().is_ok_and(|value_key| value_key == *key)) {
+            Ok(Map { inner: map })
+        } else {
+            Err(map)
+        }
+    }
+
+    /// Mutable reference to the value stored under `key`, checked to keep its key once dropped
+    pub fn get_mut(&mut self, key: &Key) -> Option<ValueMut<'_, Value>> {
+        self.inner.get_mut(key).map(ValueMut::new)
+    }
+
+    /// Iterate mutably over the stored values, each checked to keep its key once dropped
+    pub fn iter_mut<'a>(&'a mut self) -> IterMut<'a, hash_map::ValuesMut<'a, Key, Value>, Value> {
+        self.inner.values_mut().map(ValueMut::new)
+    }
+
+    /// Check that every value is stored under its own key, see [VariantMap::validate]
+    pub fn validate(&self) -> Result<(), KeyMismatch<'_, Key>> {
+        VariantMap::validate(self)
+    }
+}
+
+impl<Key, Value> Map<Key, Value>
+    where
+        Key: HashKey,
+{
+    pub fn remove(&mut self, key: &Key) -> Option<Value> {
+        self.inner.remove(key)
+    }
+
+    pub fn clear(&mut self) {
+        self.inner.clear()
+    }
+
+    /// Keep only the values for which `f` returns `true`
+    pub fn retain<F>(&mut self, mut f: F)
+        where
+            F: FnMut(&Value) -> bool,
+    {
+        self.inner.retain(|_, value| f(value))
+    }
+}
+
+/// Skipped variants without key are silently left out of the map, see [Map::try_insert]
+impl<Key, Value> Extend<Value> for Map<Key, Value>
+    where
+        Key: HashKey,
+        Value: MapValue<Key=Key>,
+{
+    fn extend<I>(&mut self, iter: I)
+        where
+            I: IntoIterator<Item = Value>,
+    {
+        for value in iter {
+            self.insert(value);
+        }
+    }
+}
+
+/// Skipped variants without key are silently left out of the map, see [Map::try_insert]
+impl<Key, Value> FromIterator<Value> for Map<Key, Value>
+    where
+        Key: HashKey,
+        Value: MapValue<Key=Key>,
+{
+    fn from_iter<I>(iter: I) -> Self
+        where
+            I: IntoIterator<Item = Value>,
+    {
+        let mut map = Map::default();
+        map.extend(iter);
+        map
     }
 }
 
@@ -67,6 +272,7 @@ impl<Key, Value> Default for Map<Key, Value>
     }
 }
 
+#[cfg(feature = "serde")]
 impl<Key, Value> Serialize for Map<Key, Value>
     where
         Key: HashKey,
@@ -86,6 +292,7 @@ impl<Key, Value> Serialize for Map<Key, Value>
     }
 }
 
+#[cfg(feature = "serde")]
 struct MapVisitor<Key, Value>
     where
         Key: HashKey,
@@ -93,6 +300,7 @@ struct MapVisitor<Key, Value>
     marker: PhantomData<fn() -> Map<Key, Value>>,
 }
 
+#[cfg(feature = "serde")]
 impl<'de, Key, Value> Visitor<'de> for MapVisitor<Key, Value>
     where
         Key: HashKey,
@@ -100,7 +308,7 @@ impl<'de, Key, Value> Visitor<'de> for MapVisitor<Key, Value>
 {
     type Value = Map<Key, Value>;
 
-    fn expecting(&self, formatter: &mut Formatter) -> std::fmt::Result {
+    fn expecting(&self, formatter: &mut Formatter) -> core::fmt::Result {
         write!(formatter, "MapVisitor expects to receive a map of <EnumKey, Enum> with untagged Enum variants and EnumKey serializing to Enum variants' names ")
     }
 
@@ -113,13 +321,16 @@ impl<'de, Key, Value> Visitor<'de> for MapVisitor<Key, Value>
 
         while let Some(value) = seq.next_element()? {
             let variant: Value = value;
-            map.insert(variant.to_key(), variant);
+            if let Some(key) = deserialize_key(&variant)? {
+                map.insert(key, variant);
+            }
         }
 
-        Ok(Map::from(map))
+        Ok(Map { inner: map })
     }
 }
 
+#[cfg(feature = "serde")]
 impl<'de, Key, Value> Deserialize<'de> for Map<Key, Value>
     where
         Key: HashKey,
@@ -147,32 +358,31 @@ impl<Key, Value> Deref for Map<Key, Value>
     }
 }
 
-impl<Key, Value> DerefMut for Map<Key, Value>
-    where
-        Key: HashKey,
-{
-    fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut self.inner
-    }
-}
-
+/// # Panics
+///
+/// If no value is stored under the key
 impl<Key, Value> Index<Key> for Map<Key, Value>
     where
-        Key: HashKey,
+        Key: HashKey + Debug,
 {
     type Output = Value;
 
     fn index(&self, index: Key) -> &Self::Output {
-        self.inner.get(&index).unwrap()
+        &self[&index]
     }
 }
 
-impl<Key, Value> IndexMut<Key> for Map<Key, Value>
+/// # Panics
+///
+/// If no value is stored under the key
+impl<'a, Key, Value> Index<&'a Key> for Map<Key, Value>
     where
-        Key: HashKey,
+        Key: HashKey + Debug,
 {
-    fn index_mut(&mut self, index: Key) -> &mut Self::Output {
-        self.inner.get_mut(&index).unwrap()
+    type Output = Value;
+
+    fn index(&self, index: &'a Key) -> &Self::Output {
+        self.inner.get(index).unwrap_or_else(|| panic!("no value stored under key `{:?}`", index))
     }
 }
 
@@ -183,17 +393,18 @@ impl<'a, Key, Value> IntoIterator for &'a Map<Key, Value>
     type IntoIter = <&'a HashMap<Key, Value> as IntoIterator>::IntoIter;
 
     fn into_iter(self) -> Self::IntoIter {
-        (&self.inner).into_iter()
+        self.inner.iter()
     }
 }
 
 impl<'a, Key, Value> IntoIterator for &'a mut Map<Key, Value>
     where
-        Key: HashKey {
-    type Item = <&'a mut HashMap<Key, Value> as IntoIterator>::Item;
-    type IntoIter = <&'a mut HashMap<Key, Value> as IntoIterator>::IntoIter;
+        Key: HashKey,
+        Value: MapValue<Key=Key> {
+    type Item = (&'a Key, ValueMut<'a, Value>);
+    type IntoIter = core::iter::Map<hash_map::IterMut<'a, Key, Value>, fn((&'a Key, &'a mut Value)) -> Self::Item>;
 
     fn into_iter(self) -> Self::IntoIter {
-        (&mut self.inner).into_iter()
+        self.inner.iter_mut().map(|(key, value)| (key, ValueMut::new(value)))
     }
 }
\ No newline at end of file
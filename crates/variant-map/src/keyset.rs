@@ -0,0 +1,375 @@
+#[cfg(feature = "serde")]
+use serde::de::{SeqAccess, Visitor};
+#[cfg(feature = "serde")]
+use serde::ser::SerializeSeq;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use core::fmt::{Debug, Formatter};
+use core::hash::{Hash, Hasher};
+use core::marker::PhantomData;
+
+use crate::common::VariantKey;
+#[cfg(feature = "serde")]
+use crate::object::KeySeed;
+
+/// Set of [Keys][VariantKey] stored as a bitset, a bit per key
+///
+/// Keys are ordered as the variants of the enum, a set is [Copy] and never allocates.
+/// Obtained from any map with [keys_set][crate::common::VariantMap::keys_set]
+///
+/// Supports enums with up to 128 variants, using it with a larger enum fails to compile
+///
+/// De/serialized as a sequence of [key names][VariantKey::name]
+///
+/// # Example
+///
+/// ```
+/// use variant_map::common::{MapValue, VariantMap};
+/// use variant_map::keyset::KeySet;
+/// use variant_map_derive::VariantStore;
+///
+/// #[derive(VariantStore)]
+/// enum Region {
+///     Europe(u32),
+///     Asia(u32),
+///     America(u32),
+/// }
+///
+/// fn main() {
+///     let mut map = Region::make_map();
+///     map.insert(Region::Asia(2));
+///
+///     let missing = map.keys_set().complement();
+///     assert_eq!(missing, KeySet::from_iter([RegionKey::Europe, RegionKey::America]));
+///     assert_eq!(serde_json::to_string(&missing).unwrap(), r#"["Europe","America"]"#);
+/// }
+/// ```
+pub struct KeySet<Key> {
+    bits: u128,
+    marker: PhantomData<fn() -> Key>,
+}
+
+impl<Key> KeySet<Key>
+where
+    Key: VariantKey,
+{
+    /// Fails to compile when used with more keys than bits
+    const FITS: () = assert!(Key::COUNT <= u128::BITS as usize, "a KeySet holds at most 128 keys");
+
+    /// Empty set
+    pub fn new() -> Self {
+        let () = Self::FITS;
+        KeySet {
+            bits: 0,
+            marker: PhantomData,
+        }
+    }
+
+    /// Set of every key
+    pub fn all() -> Self {
+        Self::new().complement()
+    }
+
+    fn with_bits(bits: u128) -> Self {
+        KeySet {
+            bits,
+            marker: PhantomData,
+        }
+    }
+
+    fn bit(key: &Key) -> u128 {
+        1 << key.index()
+    }
+
+    /// Number of keys in the set
+    pub fn len(&self) -> usize {
+        self.bits.count_ones() as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bits == 0
+    }
+
+    pub fn contains(&self, key: &Key) -> bool {
+        self.bits & Self::bit(key) != 0
+    }
+
+    /// Add `key` to the set, returning whether it was absent
+    pub fn insert(&mut self, key: Key) -> bool {
+        let absent = !self.contains(&key);
+        self.bits |= Self::bit(&key);
+        absent
+    }
+
+    /// Remove `key` from the set, returning whether it was present
+    pub fn remove(&mut self, key: &Key) -> bool {
+        let present = self.contains(key);
+        self.bits &= !Self::bit(key);
+        present
+    }
+
+    pub fn clear(&mut self) {
+        self.bits = 0;
+    }
+
+    /// Keys in either set
+    pub fn union(&self, other: &Self) -> Self {
+        Self::with_bits(self.bits | other.bits)
+    }
+
+    /// Keys in both sets
+    pub fn intersection(&self, other: &Self) -> Self {
+        Self::with_bits(self.bits & other.bits)
+    }
+
+    /// Keys in `self` but not in `other`
+    pub fn difference(&self, other: &Self) -> Self {
+        Self::with_bits(self.bits & !other.bits)
+    }
+
+    /// Keys in exactly one of the sets
+    pub fn symmetric_difference(&self, other: &Self) -> Self {
+        Self::with_bits(self.bits ^ other.bits)
+    }
+
+    /// Keys not in the set
+    pub fn complement(&self) -> Self {
+        let all = match Key::COUNT {
+            128 => u128::MAX,
+            count => (1 << count) - 1,
+        };
+        Self::with_bits(!self.bits & all)
+    }
+
+    pub fn is_subset(&self, other: &Self) -> bool {
+        self.bits & !other.bits == 0
+    }
+
+    pub fn is_superset(&self, other: &Self) -> bool {
+        other.is_subset(self)
+    }
+
+    /// Iterate over the keys of the set, ordered as the variants of the enum
+    pub fn iter(&self) -> Iter<Key> {
+        Iter {
+            bits: self.bits,
+            marker: PhantomData,
+        }
+    }
+}
+
+/// Iterator over the keys of a [KeySet]
+pub struct Iter<Key> {
+    bits: u128,
+    marker: PhantomData<fn() -> Key>,
+}
+
+impl<Key> Iterator for Iter<Key>
+where
+    Key: VariantKey,
+{
+    type Item = Key;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.bits == 0 {
+            return None;
+        }
+
+        let index = self.bits.trailing_zeros() as usize;
+        self.bits &= self.bits - 1;
+        Key::from_index(index)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.bits.count_ones() as usize;
+        (len, Some(len))
+    }
+}
+
+impl<Key> ExactSizeIterator for Iter<Key> where Key: VariantKey {}
+
+impl<Key> IntoIterator for KeySet<Key>
+where
+    Key: VariantKey,
+{
+    type Item = Key;
+    type IntoIter = Iter<Key>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<Key> IntoIterator for &KeySet<Key>
+where
+    Key: VariantKey,
+{
+    type Item = Key;
+    type IntoIter = Iter<Key>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<Key> Extend<Key> for KeySet<Key>
+where
+    Key: VariantKey,
+{
+    fn extend<I>(&mut self, iter: I)
+    where
+        I: IntoIterator<Item = Key>,
+    {
+        for key in iter {
+            self.insert(key);
+        }
+    }
+}
+
+impl<'a, Key> Extend<&'a Key> for KeySet<Key>
+where
+    Key: VariantKey,
+{
+    fn extend<I>(&mut self, iter: I)
+    where
+        I: IntoIterator<Item = &'a Key>,
+    {
+        for key in iter {
+            self.bits |= Self::bit(key);
+        }
+    }
+}
+
+impl<Key> FromIterator<Key> for KeySet<Key>
+where
+    Key: VariantKey,
+{
+    fn from_iter<I>(iter: I) -> Self
+    where
+        I: IntoIterator<Item = Key>,
+    {
+        let mut set = KeySet::new();
+        set.extend(iter);
+        set
+    }
+}
+
+impl<'a, Key> FromIterator<&'a Key> for KeySet<Key>
+where
+    Key: VariantKey,
+{
+    fn from_iter<I>(iter: I) -> Self
+    where
+        I: IntoIterator<Item = &'a Key>,
+    {
+        let mut set = KeySet::new();
+        set.extend(iter);
+        set
+    }
+}
+
+// the traits are implemented by hand, the derives would require them on `Key`
+
+impl<Key> Clone for KeySet<Key> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<Key> Copy for KeySet<Key> {}
+
+impl<Key> PartialEq for KeySet<Key> {
+    fn eq(&self, other: &Self) -> bool {
+        self.bits == other.bits
+    }
+}
+
+impl<Key> Eq for KeySet<Key> {}
+
+impl<Key> Hash for KeySet<Key> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.bits.hash(state)
+    }
+}
+
+impl<Key> Default for KeySet<Key>
+where
+    Key: VariantKey,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<Key> Debug for KeySet<Key>
+where
+    Key: VariantKey + Debug,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<Key> Serialize for KeySet<Key>
+where
+    Key: VariantKey,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut seq = serializer.serialize_seq(Some(self.len()))?;
+
+        for key in self.iter() {
+            seq.serialize_element(key.name())?;
+        }
+
+        seq.end()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, Key> Deserialize<'de> for KeySet<Key>
+where
+    Key: VariantKey,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_seq(KeySetVisitor {
+            marker: PhantomData,
+        })
+    }
+}
+
+#[cfg(feature = "serde")]
+struct KeySetVisitor<Key> {
+    marker: PhantomData<fn() -> Key>,
+}
+
+#[cfg(feature = "serde")]
+impl<'de, Key> Visitor<'de> for KeySetVisitor<Key>
+where
+    Key: VariantKey,
+{
+    type Value = KeySet<Key>;
+
+    fn expecting(&self, formatter: &mut Formatter) -> core::fmt::Result {
+        write!(formatter, "KeySetVisitor expects to receive a sequence of key names")
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let mut set = KeySet::new();
+
+        while let Some(key) = seq.next_element_seed(KeySeed::<Key>::new())? {
+            set.insert(key);
+        }
+
+        Ok(set)
+    }
+}
@@ -2,7 +2,8 @@
 //!
 //! Provides different kinds of map-equivalent types to store enum variants into.
 //! As those data structures are maps, they store one value of each variant.
-//! All Maps are [serde::Serialize]-able and [serde::Deserialize]-able
+//! With the `serde` feature (enabled by default), all Maps are [serde::Serialize]-able and [serde::Deserialize]-able
+//! and share a common interface through the [VariantMap][common::VariantMap] trait
 //!
 //!
 //! Those maps can be generated easily using the derive macros from [variant_map_derive].
@@ -27,34 +28,110 @@
 //!     fn main() {
 //!         use variant_map::{as_key, as_map};
 //!         let mut map = <as_map!(MyEnum)>::default();
+//!         map.insert(MyEnum::A);
+//!         map.insert(MyEnum::B(0));
 //!         let _: &MyEnum = map.get(&<as_key!(MyEnum)>::A).unwrap();
 //!         let _: &MyEnum = map.get(&MyEnumKey::A).unwrap();
-//!         map[&MyEnumKey::B] = MyEnum::B(69);
+//!         let _: &MyEnum = &map[&MyEnumKey::A];
+//!         *map.get_mut(&MyEnumKey::B).unwrap() = MyEnum::B(69);
 //!     }
 //! ```
 //!
 //! For more customizability of the [Map][common::MapValue::Map] check out the [variant_map_derive] crate documentation
 //!
+//! # Features
+//!
+//! The crate is `no_std`, the [arraymap], the [keyset] and the derived `StructMap` need neither `std` nor `alloc`
+//!
+//! `std` (default): [hashmap] based on the [std::collections::HashMap], implies `alloc`
+//!
+//! `alloc`: [btreemap], [multimap] and [diff]
+//!
+//! `hashbrown`: [hashmap] based on the `hashbrown::HashMap`, available without `std`
+//!
+//! `serde` (default): [serde::Serialize] and [serde::Deserialize] of the maps
+//!
 //! For more detailed examples check out the [example project](https://github.com/mxyns/variant-map/tree/master/example) on this crates' [repo](https://github.com/mxyns/variant-map/)
 
-/// Code in common between [hashmap] and [btreemap]
+#![no_std]
+
+#[cfg(feature = "std")]
+extern crate std;
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+/// Code in common between [hashmap], [btreemap] and [arraymap]
 pub mod common;
 
 /// Used by the [variant_map_derive] to provide [serde::Serialize] and [serde::Deserialize] implementations
+#[cfg(feature = "serde")]
 pub use serde;
 
-/// A [hashmap::Map] storing Enum variants based on a [std::collections::HashMap]
+/// A [hashmap::Map] storing Enum variants based on a [std::collections::HashMap], or a `hashbrown::HashMap` with the `hashbrown` feature
+#[cfg(any(feature = "std", feature = "hashbrown"))]
 pub mod hashmap {
     mod lib;
     pub use lib::*;
 }
 
-/// A [btreemap::Map] storing Enum variants based on a [std::collections::BTreeMap]
+/// A [btreemap::Map] storing Enum variants based on an [alloc::collections::BTreeMap]
+#[cfg(feature = "alloc")]
 pub mod btreemap {
     mod lib;
     pub use lib::*;
 }
 
+/// An [arraymap::Map] storing Enum variants in a fixed-size array indexed by their key
+pub mod arraymap {
+    mod lib;
+    pub use lib::*;
+}
+
+/// A [multimap::Map] storing any number of values of each Enum variant, grouped by key
+#[cfg(feature = "alloc")]
+pub mod multimap {
+    mod lib;
+    pub use lib::*;
+}
+
+/// [diff::MapDiff] between two maps of the same enum, to send or apply deltas
+#[cfg(feature = "alloc")]
+pub mod diff;
+
+/// [entry::Entry] API of every [VariantMap][common::VariantMap], only storing values matching the key of the entry
+pub mod entry;
+
+/// [keyset::KeySet] of the keys of an enum, stored as a bitset
+pub mod keyset;
+
+/// [merge::MergeStrategy] of maps merged together and their [merge::overlay]
+pub mod merge;
+
+/// [object::Object] de/serializing maps as an object keyed by variant name instead of a sequence of variants
+#[cfg(feature = "serde")]
+pub mod object;
+
+/// [strict::Strict] deserialization rejecting duplicated variants and requiring a set of variants
+#[cfg(feature = "serde")]
+pub mod strict;
+
+/// [total::MissingKeys] error of the total maps holding every variant, generated by the derive
+pub mod total;
+
+/// Items used by the code generated by [variant_map_derive], not part of the public API
+#[doc(hidden)]
+pub mod __private {
+    use crate::common::VariantMap;
+
+    /// Mutable access to a stored value bypassing the [ValueMut][crate::common::ValueMut] guard checking its key
+    ///
+    /// Only used by the derived payload accessors, which cannot replace the value with another variant
+    pub trait UncheckedMut: VariantMap {
+        fn get_mut_unchecked(&mut self, key: &Self::Key) -> Option<&mut Self::Value>;
+    }
+}
+
 /// Derive macro which derives an enum of keys and implements [common::MapValue] on your enum
 /// Available when using the *derive* or *struct-map* feature
 #[cfg(feature = "derive")]
@@ -21,10 +21,10 @@
 #[macro_export]
 macro_rules! as_key {
     ($T:ty) => {
-        <$T as MapValue>::Key
+        <$T as $crate::common::MapValue>::Key
     };
     ($T:ty, $V:ident) => {
-        <$T as MapValue>::Key::$V
+        <$T as $crate::common::MapValue>::Key::$V
     };
 }
 pub use as_key;
@@ -52,7 +52,7 @@ pub use as_key;
 #[macro_export]
 macro_rules! as_map {
     ($T:ty) => {
-        <$T as MapValue>::Map
+        <$T as $crate::common::MapValue>::Map
     };
 }
 pub use as_map;
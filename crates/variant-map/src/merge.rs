@@ -0,0 +1,111 @@
+use crate::common::{Skipped, VariantKey, VariantMap};
+
+/// Resolution of a key holding a value in both maps given to [merge][VariantMap::merge]
+///
+/// Implemented by [KeepLeft], [KeepRight] and any closure `Fn(&Key, Value, Value) -> Value`
+/// receiving the value of the merged map (left) then the value of the other one (right).
+/// The signature of a closure is only inferred by [merge_with][VariantMap::merge_with]
+pub trait MergeStrategy<Key, Value> {
+
+    /// Value kept under `key`, it must be a variant of `key`
+    fn resolve(&self, key: &Key, left: Value, right: Value) -> Value;
+}
+
+/// [MergeStrategy] keeping the values of the merged map
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct KeepLeft;
+
+/// [MergeStrategy] keeping the values of the other map, as if it was inserted value by value
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct KeepRight;
+
+impl<Key, Value> MergeStrategy<Key, Value> for KeepLeft {
+    fn resolve(&self, _key: &Key, left: Value, _right: Value) -> Value {
+        left
+    }
+}
+
+impl<Key, Value> MergeStrategy<Key, Value> for KeepRight {
+    fn resolve(&self, _key: &Key, _left: Value, right: Value) -> Value {
+        right
+    }
+}
+
+impl<Key, Value, F> MergeStrategy<Key, Value> for F
+where
+    F: Fn(&Key, Value, Value) -> Value,
+{
+    fn resolve(&self, key: &Key, left: Value, right: Value) -> Value {
+        self(key, left, right)
+    }
+}
+
+/// Resolve the values both held under `key` with `strategy`, `to_key` gives the key of the resolved value
+///
+/// Used by [merge][VariantMap::merge], available to the maps implementing [merge][VariantMap::merge] themselves.
+/// Fails if the strategy resolves to a skipped variant without key, which cannot be stored
+///
+/// # Panics
+///
+/// If the strategy resolves to a variant of another key
+pub fn resolve<Key, Value, S, F>(strategy: &S, key: &Key, left: Value, right: Value, to_key: F) -> Result<Value, Skipped>
+where
+    Key: VariantKey,
+    S: MergeStrategy<Key, Value>,
+    F: FnOnce(&Value) -> Result<Key, Skipped>,
+{
+    let value = strategy.resolve(key, left, right);
+    let value_key = to_key(&value)?;
+    assert!(
+        value_key.index() == key.index(),
+        "merge of key `{}` cannot resolve to a value of key `{}`",
+        key.name(),
+        value_key.name()
+    );
+    Ok(value)
+}
+
+/// New map holding the values of `base` replaced by the values of `overrides`, neither map is modified
+///
+/// Overlaying the layers of a configuration, from the defaults to the most specific one, gives the effective configuration
+///
+/// # Example
+///
+/// ```
+/// use variant_map::common::{MapValue, VariantMap};
+/// use variant_map::merge::overlay;
+/// use variant_map_derive::VariantStore;
+///
+/// #[derive(Debug, Clone, PartialEq, VariantStore)]
+/// enum Setting {
+///     Timeout(u32),
+///     Retries(u8),
+/// }
+///
+/// fn main() {
+///     let mut defaults = Setting::make_map();
+///     defaults.insert(Setting::Timeout(30));
+///     defaults.insert(Setting::Retries(3));
+///
+///     let mut cli = Setting::make_map();
+///     cli.insert(Setting::Retries(5));
+///
+///     let settings = overlay(&defaults, &cli);
+///     assert_eq!(settings.get(&SettingKey::Timeout), Some(&Setting::Timeout(30)));
+///     assert_eq!(settings.get(&SettingKey::Retries), Some(&Setting::Retries(5)));
+/// }
+/// ```
+pub fn overlay<M>(base: &M, overrides: &M) -> M
+where
+    M: VariantMap + Default,
+    M::Value: Clone,
+{
+    let mut map = M::default();
+
+    // later insertions replace the values of the base, as with `KeepRight`
+    for value in base.iter().chain(overrides.iter()) {
+        map.insert((*value).clone());
+    }
+
+    map
+}
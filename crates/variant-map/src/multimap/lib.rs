@@ -0,0 +1,306 @@
+#[cfg(feature = "serde")]
+use serde::de::{DeserializeOwned, SeqAccess, Visitor};
+#[cfg(feature = "serde")]
+use serde::ser::SerializeSeq;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+#[cfg(feature = "serde")]
+use core::fmt::Formatter;
+use alloc::vec::Vec;
+use core::marker::PhantomData;
+
+use crate::common::{IterMut, MapValue, ValueMut, VariantKey};
+#[cfg(feature = "serde")]
+use crate::common::deserialize_key;
+
+/// Trait to implement on your Enum [Keys][crate::common::MapValue::Key]
+/// Required to be a key of a [Map]
+///
+/// The [index][VariantKey::index] of the key is the position of its values in the [Map]
+pub trait MultiKey: VariantKey {}
+
+/// Binds an enum to its [Map], implemented by the derive with `datastruct = "MultiMap"`
+///
+/// The [Map][MapValue::Map] of the enum keeps storing one value per key
+pub trait MultiMapValue: MapValue
+where
+    Self::Key: MultiKey,
+{
+    /// Initialize an empty [Map]
+    fn make_multimap() -> Map<Self::Key, Self> {
+        Map::default()
+    }
+}
+
+/// [Map] storing any number of values of each variant, grouped by key in the order they were pushed
+/// Keys must implement [MultiKey]
+///
+/// Unlike the other maps it is not a [VariantMap][crate::common::VariantMap], a key holds a sequence of values.
+/// De/serialized as a flat sequence of the variants, ordered by key
+///
+/// As in the other maps, the mutable references are [ValueMut] guards checking the key of the modified value
+///
+/// # Example
+///
+/// ```
+/// use serde::{Deserialize, Serialize};
+/// use variant_map::multimap::MultiMapValue;
+/// use variant_map_derive::VariantStore;
+///
+/// #[derive(Debug, PartialEq, Serialize, Deserialize, VariantStore)]
+/// #[VariantStore(datastruct = "MultiMap")]
+/// enum Event {
+///     Click(u32),
+///     Scroll(i32),
+/// }
+///
+/// fn main() {
+///     let mut log = Event::make_multimap();
+///     log.push(Event::Scroll(-3));
+///     log.push(Event::Click(1));
+///     log.push(Event::Click(2));
+///
+///     assert_eq!(log.count(&EventKey::Click), 2);
+///     assert_eq!(log.iter_variant(&EventKey::Click).collect::<Vec<_>>(), vec![&Event::Click(1), &Event::Click(2)]);
+///     assert_eq!(serde_json::to_string(&log).unwrap(), r#"[{"Click":1},{"Click":2},{"Scroll":-3}]"#);
+///
+///     assert_eq!(log.drain(&EventKey::Scroll).collect::<Vec<_>>(), vec![Event::Scroll(-3)]);
+///     assert_eq!(log.len(), 2);
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Map<Key, Value>
+where
+    Key: MultiKey,
+{
+    inner: Vec<Vec<Value>>,
+    marker: PhantomData<fn() -> Key>,
+}
+
+impl<Key, Value> Map<Key, Value>
+where
+    Key: MultiKey,
+{
+    /// Append `value` to the values of its key, giving it back if it is a skipped variant without key
+    pub fn push(&mut self, value: Value) -> Option<Value>
+    where
+        Value: MapValue<Key = Key>,
+    {
+        match value.try_to_key() {
+            Ok(key) => {
+                self.inner[key.index()].push(value);
+                None
+            }
+            Err(_) => Some(value),
+        }
+    }
+
+    /// Values of `key`, in the order they were pushed
+    pub fn get(&self, key: &Key) -> &[Value] {
+        &self.inner[key.index()]
+    }
+
+    /// Iterate over the values of `key`, in the order they were pushed
+    pub fn iter_variant(&self, key: &Key) -> core::slice::Iter<'_, Value> {
+        self.inner[key.index()].iter()
+    }
+
+    /// Iterate mutably over the values of `key`, in the order they were pushed, each checked to keep its key once dropped
+    pub fn iter_variant_mut<'a>(&'a mut self, key: &Key) -> IterMut<'a, core::slice::IterMut<'a, Value>, Value>
+    where
+        Value: MapValue<Key = Key>,
+    {
+        self.inner[key.index()].iter_mut().map(ValueMut::indexed)
+    }
+
+    /// Number of values of `key`
+    pub fn count(&self, key: &Key) -> usize {
+        self.inner[key.index()].len()
+    }
+
+    pub fn contains_key(&self, key: &Key) -> bool {
+        !self.inner[key.index()].is_empty()
+    }
+
+    /// Remove the values of `key`, returning them in the order they were pushed
+    pub fn drain(&mut self, key: &Key) -> alloc::vec::Drain<'_, Value> {
+        self.inner[key.index()].drain(..)
+    }
+
+    /// Number of values of every key
+    pub fn len(&self) -> usize {
+        self.inner.iter().map(Vec::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.inner.iter().all(Vec::is_empty)
+    }
+
+    pub fn clear(&mut self) {
+        self.inner.iter_mut().for_each(Vec::clear);
+    }
+
+    /// Keep only the values for which `f` returns `true`
+    pub fn retain<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&Value) -> bool,
+    {
+        self.inner.iter_mut().for_each(|values| values.retain(&mut f));
+    }
+
+    /// Iterate over the values of every key, in [index][VariantKey::index] order
+    pub fn iter(&self) -> core::iter::Flatten<core::slice::Iter<'_, Vec<Value>>> {
+        self.inner.iter().flatten()
+    }
+
+    /// Iterate mutably over the values of every key, in [index][VariantKey::index] order, each checked to keep its key once dropped
+    pub fn iter_mut<'a>(&'a mut self) -> IterMut<'a, core::iter::Flatten<core::slice::IterMut<'a, Vec<Value>>>, Value>
+    where
+        Value: MapValue<Key = Key>,
+    {
+        self.inner.iter_mut().flatten().map(ValueMut::indexed)
+    }
+}
+
+impl<Key, Value> Default for Map<Key, Value>
+where
+    Key: MultiKey,
+{
+    fn default() -> Self {
+        Map {
+            inner: core::iter::repeat_with(Vec::new).take(Key::COUNT).collect(),
+            marker: PhantomData,
+        }
+    }
+}
+
+/// Skipped variants without key are silently left out of the map, see [Map::push]
+impl<Key, Value> Extend<Value> for Map<Key, Value>
+where
+    Key: MultiKey,
+    Value: MapValue<Key = Key>,
+{
+    fn extend<I>(&mut self, iter: I)
+    where
+        I: IntoIterator<Item = Value>,
+    {
+        for value in iter {
+            self.push(value);
+        }
+    }
+}
+
+/// Skipped variants without key are silently left out of the map, see [Map::push]
+impl<Key, Value> FromIterator<Value> for Map<Key, Value>
+where
+    Key: MultiKey,
+    Value: MapValue<Key = Key>,
+{
+    fn from_iter<I>(iter: I) -> Self
+    where
+        I: IntoIterator<Item = Value>,
+    {
+        let mut map = Map::default();
+        map.extend(iter);
+        map
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<Key, Value> Serialize for Map<Key, Value>
+where
+    Key: MultiKey,
+    Value: Serialize,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut map = serializer.serialize_seq(Some(self.len()))?;
+
+        for v in self.iter() {
+            map.serialize_element(v)?
+        }
+
+        map.end()
+    }
+}
+
+#[cfg(feature = "serde")]
+struct MapVisitor<Key, Value>
+where
+    Key: MultiKey,
+{
+    marker: PhantomData<fn() -> Map<Key, Value>>,
+}
+
+#[cfg(feature = "serde")]
+impl<'de, Key, Value> Visitor<'de> for MapVisitor<Key, Value>
+where
+    Key: MultiKey,
+    Value: MapValue<Key = Key> + DeserializeOwned,
+{
+    type Value = Map<Key, Value>;
+
+    fn expecting(&self, formatter: &mut Formatter) -> core::fmt::Result {
+        write!(formatter, "MapVisitor expects to receive a sequence of untagged Enum variants")
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let mut map = Map::<Key, Value>::default();
+
+        while let Some(value) = seq.next_element()? {
+            let variant: Value = value;
+            if let Some(key) = deserialize_key(&variant)? {
+                map.inner[key.index()].push(variant);
+            }
+        }
+
+        Ok(map)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, Key, Value> Deserialize<'de> for Map<Key, Value>
+where
+    Key: MultiKey,
+    Value: MapValue<Key = Key> + DeserializeOwned,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let visitor = MapVisitor::<Key, Value> {
+            marker: PhantomData,
+        };
+        deserializer.deserialize_seq(visitor)
+    }
+}
+
+impl<'a, Key, Value> IntoIterator for &'a Map<Key, Value>
+where
+    Key: MultiKey,
+{
+    type Item = &'a Value;
+    type IntoIter = core::iter::Flatten<core::slice::Iter<'a, Vec<Value>>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, Key, Value> IntoIterator for &'a mut Map<Key, Value>
+where
+    Key: MultiKey,
+    Value: MapValue<Key = Key>,
+{
+    type Item = ValueMut<'a, Value>;
+    type IntoIter = IterMut<'a, core::iter::Flatten<core::slice::IterMut<'a, Vec<Value>>>, Value>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
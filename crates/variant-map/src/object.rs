@@ -0,0 +1,654 @@
+use serde::de::value::U64Deserializer;
+use serde::de::{DeserializeSeed, EnumAccess, IntoDeserializer, MapAccess, VariantAccess, Visitor};
+use serde::ser::{Impossible, SerializeMap, SerializeStruct, SerializeStructVariant, SerializeTuple, SerializeTupleVariant};
+use serde::{de, ser, Deserialize, Deserializer, Serialize, Serializer};
+use core::fmt::{Display, Formatter};
+use core::marker::PhantomData;
+use core::ops::{Deref, DerefMut};
+
+use crate::__private::UncheckedMut;
+use crate::common::{MapValue, VariantKey, VariantMap};
+
+/// Wrapper de/serializing a [VariantMap] as an object mapping each [Key name][VariantKey::name] to the payload of its variant
+///
+/// The maps are de/serialized as a sequence of variants by default,
+/// this representation is friendlier to human-edited formats: `{ "timeout": 30, "retries": 3 }`
+///
+/// Unit variants have a `()` payload (`null` in JSON). Later entries replace earlier ones sharing the same key,
+/// unless the inner map is [Strict][crate::strict::Strict].
+///
+/// The payload of an entry is read as the variant at the position given by the [index][VariantKey::index] of its key.
+/// The index of the key of every variant must therefore be the position of that variant in the enum.
+/// The keys generated or implemented by the derive follow the order of the variants, and the derive rejects
+/// `serde_format = "object"` with a user type of keys it does not implement.
+/// A hand-written [VariantKey] used with this adapter must follow the same order:
+/// a payload read as a variant of another key is rejected with an error, the map cannot be deserialized
+///
+/// Used as the [Map][MapValue::Map] of an enum derived with `#[VariantStore(serde_format = "object")]`,
+/// the same representation is available on any field through `#[serde(with = "variant_map::object")]`
+///
+/// # Example
+///
+/// ```
+/// use serde::{Deserialize, Serialize};
+/// use variant_map::common::MapValue;
+/// use variant_map::object::Object;
+/// use variant_map_derive::VariantStore;
+///
+/// #[derive(Serialize, Deserialize, VariantStore)]
+/// #[VariantStore(datastruct = "BTreeMap")]
+/// enum Setting {
+///     #[key_name(serde = "timeout")]
+///     Timeout(u32),
+///     #[key_name(serde = "retries")]
+///     Retries(u8),
+/// }
+///
+/// fn main() {
+///     let mut settings = Object(Setting::make_map());
+///     settings.insert(Setting::Timeout(30));
+///     settings.insert(Setting::Retries(3));
+///     assert_eq!(serde_json::to_string(&settings).unwrap(), r#"{"timeout":30,"retries":3}"#);
+/// }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Object<M>(pub M);
+
+impl<M> Object<M> {
+    /// Unwraps the inner map
+    pub fn into_inner(self) -> M {
+        self.0
+    }
+}
+
+impl<M> From<M> for Object<M> {
+    fn from(map: M) -> Self {
+        Object(map)
+    }
+}
+
+impl<M> Deref for Object<M> {
+    type Target = M;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<M> DerefMut for Object<M> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl<'a, M> IntoIterator for &'a Object<M>
+where
+    &'a M: IntoIterator,
+{
+    type Item = <&'a M as IntoIterator>::Item;
+    type IntoIter = <&'a M as IntoIterator>::IntoIter;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl<M> VariantMap for Object<M>
+where
+    M: VariantMap,
+{
+    type Key = M::Key;
+    type Value = M::Value;
+    type Ref<'a> = M::Ref<'a>
+    where
+        Self: 'a;
+    type Mut<'a> = M::Mut<'a>
+    where
+        Self: 'a;
+    type Iter<'a> = M::Iter<'a>
+    where
+        Self: 'a;
+    type KeyedIter<'a> = M::KeyedIter<'a>
+    where
+        Self: 'a;
+
+    fn get(&self, key: &Self::Key) -> Option<Self::Ref<'_>> {
+        self.0.get(key)
+    }
+
+    fn get_mut(&mut self, key: &Self::Key) -> Option<Self::Mut<'_>> {
+        self.0.get_mut(key)
+    }
+
+    fn insert(&mut self, value: Self::Value) -> Option<Self::Value> {
+        self.0.insert(value)
+    }
+
+    fn remove(&mut self, key: &Self::Key) -> Option<Self::Value> {
+        self.0.remove(key)
+    }
+
+    fn contains_key(&self, key: &Self::Key) -> bool {
+        self.0.contains_key(key)
+    }
+
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    fn iter(&self) -> Self::Iter<'_> {
+        self.0.iter()
+    }
+
+    fn iter_keyed(&self) -> Self::KeyedIter<'_> {
+        self.0.iter_keyed()
+    }
+
+    fn clear(&mut self) {
+        self.0.clear()
+    }
+
+    fn deserialize_insert<E>(&mut self, value: Self::Value) -> Result<(), E>
+    where
+        E: de::Error,
+    {
+        self.0.deserialize_insert(value)
+    }
+
+    fn deserialize_finish<E>(&self) -> Result<(), E>
+    where
+        E: de::Error,
+    {
+        self.0.deserialize_finish()
+    }
+}
+
+impl<M> UncheckedMut for Object<M>
+where
+    M: UncheckedMut,
+{
+    fn get_mut_unchecked(&mut self, key: &Self::Key) -> Option<&mut Self::Value> {
+        self.0.get_mut_unchecked(key)
+    }
+}
+
+impl<M> Serialize for Object<M>
+where
+    M: VariantMap,
+    M::Key: VariantKey,
+    M::Value: MapValue<Key = M::Key> + Serialize,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serialize(&self.0, serializer)
+    }
+}
+
+impl<'de, M> Deserialize<'de> for Object<M>
+where
+    M: VariantMap + Default,
+    M::Key: VariantKey,
+    M::Value: Deserialize<'de> + MapValue<Key = M::Key>,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserialize(deserializer).map(Object)
+    }
+}
+
+/// Serializes `map` as an object, to be used with `#[serde(with = "variant_map::object")]`
+///
+/// see [Object]
+pub fn serialize<M, S>(map: &M, serializer: S) -> Result<S::Ok, S::Error>
+where
+    M: VariantMap,
+    M::Key: VariantKey,
+    M::Value: MapValue<Key = M::Key> + Serialize,
+    S: Serializer,
+{
+    let mut object = serializer.serialize_map(Some(map.len()))?;
+
+    for value in map.iter() {
+        object.serialize_entry(value.to_key().name(), &Payload(&*value))?;
+    }
+
+    object.end()
+}
+
+/// Deserializes a map from an object, to be used with `#[serde(with = "variant_map::object")]`
+///
+/// see [Object]
+pub fn deserialize<'de, M, D>(deserializer: D) -> Result<M, D::Error>
+where
+    M: VariantMap + Default,
+    M::Key: VariantKey,
+    M::Value: Deserialize<'de> + MapValue<Key = M::Key>,
+    D: Deserializer<'de>,
+{
+    deserializer.deserialize_map(ObjectVisitor {
+        marker: PhantomData,
+    })
+}
+
+struct ObjectVisitor<M> {
+    marker: PhantomData<fn() -> M>,
+}
+
+impl<'de, M> Visitor<'de> for ObjectVisitor<M>
+where
+    M: VariantMap + Default,
+    M::Key: VariantKey,
+    M::Value: Deserialize<'de> + MapValue<Key = M::Key>,
+{
+    type Value = M;
+
+    fn expecting(&self, formatter: &mut Formatter) -> core::fmt::Result {
+        write!(formatter, "ObjectVisitor expects to receive an object of variant payloads keyed by their key name")
+    }
+
+    fn visit_map<A>(self, mut object: A) -> Result<Self::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let mut map = M::default();
+
+        while let Some(key) = object.next_key_seed(KeySeed::<M::Key>::new())? {
+            map.deserialize_insert(object.next_value_seed(PayloadSeed::<M::Value>::new(key.index()))?)?;
+        }
+
+        map.deserialize_finish()?;
+        Ok(map)
+    }
+}
+
+/// Deserializes a [Key][VariantKey] from its [name][VariantKey::name], without allocating
+///
+/// Used by the `StructMap` derived with `serde_format = "object"`
+pub struct KeySeed<Key> {
+    marker: PhantomData<fn() -> Key>,
+}
+
+impl<Key> KeySeed<Key> {
+    pub fn new() -> Self {
+        KeySeed {
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<Key> Default for KeySeed<Key> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'de, Key> DeserializeSeed<'de> for KeySeed<Key>
+where
+    Key: VariantKey,
+{
+    type Value = Key;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_str(self)
+    }
+}
+
+impl<'de, Key> Visitor<'de> for KeySeed<Key>
+where
+    Key: VariantKey,
+{
+    type Value = Key;
+
+    fn expecting(&self, formatter: &mut Formatter) -> core::fmt::Result {
+        write!(formatter, "a key name")
+    }
+
+    fn visit_str<E>(self, name: &str) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Key::ALL
+            .iter()
+            .position(|key| key.name() == name)
+            .and_then(Key::from_index)
+            .ok_or_else(|| E::custom(format_args!("unknown key `{name}`")))
+    }
+}
+
+/// Serializes only the payload of a variant, see [Object]
+///
+/// Relies on the [Serialize] implementation of the enum: the variant tag is dropped and its content serialized alone
+///
+/// Used by the `StructMap` derived with `serde_format = "object"`
+pub struct Payload<'a, Value>(pub &'a Value);
+
+impl<Value> Serialize for Payload<'_, Value>
+where
+    Value: Serialize,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        self.0.serialize(PayloadSerializer { inner: serializer })
+    }
+}
+
+/// Deserializes a variant from its payload alone, knowing its [index][VariantKey::index], see [Object]
+///
+/// Relies on the [Deserialize] implementation of the enum, which is given the key index as variant index.
+/// Fails if the deserialized variant is not one of that key
+///
+/// Used by the `StructMap` derived with `serde_format = "object"`
+pub struct PayloadSeed<Value> {
+    index: usize,
+    marker: PhantomData<fn() -> Value>,
+}
+
+impl<Value> PayloadSeed<Value> {
+    pub fn new(index: usize) -> Self {
+        PayloadSeed {
+            index,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<'de, Value> DeserializeSeed<'de> for PayloadSeed<Value>
+where
+    Value: Deserialize<'de> + MapValue,
+    Value::Key: VariantKey,
+{
+    type Value = Value;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let value = Value::deserialize(PayloadDeserializer {
+            index: self.index,
+            inner: deserializer,
+        })?;
+
+        // a key index out of the order of the variants reads the payload as another variant
+        let key = value.to_key();
+        if key.index() != self.index {
+            return Err(de::Error::custom(format_args!(
+                "the payload of key index {} was read as a variant of `{}` (index {}), the key indices must follow the order of the enum variants",
+                self.index,
+                key.name(),
+                key.index(),
+            )));
+        }
+
+        Ok(value)
+    }
+}
+
+/// Error message of the [PayloadSerializer] and [PayloadDeserializer] when the value is not an enum
+const NOT_AN_ENUM: &str = "only enum variants can be stored in an object map";
+
+/// [Serializer] forwarding the content of an enum variant to the inner [Serializer]
+struct PayloadSerializer<S> {
+    inner: S,
+}
+
+fn not_an_enum<T, E: ser::Error>() -> Result<T, E> {
+    Err(E::custom(NOT_AN_ENUM))
+}
+
+macro_rules! not_an_enum {
+    ($($method:ident($($arg:ty),*)),* $(,)?) => {
+        $(
+            fn $method(self, $(_: $arg),*) -> Result<Self::Ok, Self::Error> {
+                not_an_enum()
+            }
+        )*
+    };
+}
+
+impl<S> Serializer for PayloadSerializer<S>
+where
+    S: Serializer,
+{
+    type Ok = S::Ok;
+    type Error = S::Error;
+    type SerializeSeq = Impossible<S::Ok, S::Error>;
+    type SerializeTuple = Impossible<S::Ok, S::Error>;
+    type SerializeTupleStruct = Impossible<S::Ok, S::Error>;
+    type SerializeTupleVariant = PayloadTuple<S::SerializeTuple>;
+    type SerializeMap = Impossible<S::Ok, S::Error>;
+    type SerializeStruct = Impossible<S::Ok, S::Error>;
+    type SerializeStructVariant = PayloadStruct<S::SerializeStruct>;
+
+    not_an_enum! {
+        serialize_bool(bool),
+        serialize_i8(i8),
+        serialize_i16(i16),
+        serialize_i32(i32),
+        serialize_i64(i64),
+        serialize_u8(u8),
+        serialize_u16(u16),
+        serialize_u32(u32),
+        serialize_u64(u64),
+        serialize_f32(f32),
+        serialize_f64(f64),
+        serialize_char(char),
+        serialize_str(&str),
+        serialize_bytes(&[u8]),
+        serialize_none(),
+        serialize_unit(),
+        serialize_unit_struct(&'static str),
+    }
+
+    fn serialize_some<T>(self, _: &T) -> Result<Self::Ok, Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        not_an_enum()
+    }
+
+    fn serialize_unit_variant(self, _name: &'static str, _index: u32, _variant: &'static str) -> Result<Self::Ok, Self::Error> {
+        self.inner.serialize_unit()
+    }
+
+    fn serialize_newtype_struct<T>(self, _: &'static str, _: &T) -> Result<Self::Ok, Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        not_an_enum()
+    }
+
+    fn serialize_newtype_variant<T>(self, _name: &'static str, _index: u32, _variant: &'static str, value: &T) -> Result<Self::Ok, Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(self.inner)
+    }
+
+    fn serialize_seq(self, _: Option<usize>) -> Result<Self::SerializeSeq, Self::Error> {
+        not_an_enum()
+    }
+
+    fn serialize_tuple(self, _: usize) -> Result<Self::SerializeTuple, Self::Error> {
+        not_an_enum()
+    }
+
+    fn serialize_tuple_struct(self, _: &'static str, _: usize) -> Result<Self::SerializeTupleStruct, Self::Error> {
+        not_an_enum()
+    }
+
+    fn serialize_tuple_variant(self, _name: &'static str, _index: u32, _variant: &'static str, len: usize) -> Result<Self::SerializeTupleVariant, Self::Error> {
+        self.inner.serialize_tuple(len).map(PayloadTuple)
+    }
+
+    fn serialize_map(self, _: Option<usize>) -> Result<Self::SerializeMap, Self::Error> {
+        not_an_enum()
+    }
+
+    fn serialize_struct(self, _: &'static str, _: usize) -> Result<Self::SerializeStruct, Self::Error> {
+        not_an_enum()
+    }
+
+    fn serialize_struct_variant(self, _name: &'static str, _index: u32, variant: &'static str, len: usize) -> Result<Self::SerializeStructVariant, Self::Error> {
+        self.inner.serialize_struct(variant, len).map(PayloadStruct)
+    }
+
+    fn collect_str<T>(self, _: &T) -> Result<Self::Ok, Self::Error>
+    where
+        T: ?Sized + Display,
+    {
+        not_an_enum()
+    }
+}
+
+/// Fields of a tuple variant serialized as a tuple
+struct PayloadTuple<S>(S);
+
+impl<S> SerializeTupleVariant for PayloadTuple<S>
+where
+    S: SerializeTuple,
+{
+    type Ok = S::Ok;
+    type Error = S::Error;
+
+    fn serialize_field<T>(&mut self, value: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.0.serialize_element(value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.0.end()
+    }
+}
+
+/// Fields of a struct variant serialized as a struct
+struct PayloadStruct<S>(S);
+
+impl<S> SerializeStructVariant for PayloadStruct<S>
+where
+    S: SerializeStruct,
+{
+    type Ok = S::Ok;
+    type Error = S::Error;
+
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.0.serialize_field(key, value)
+    }
+
+    fn skip_field(&mut self, key: &'static str) -> Result<(), Self::Error> {
+        self.0.skip_field(key)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.0.end()
+    }
+}
+
+/// [Deserializer] presenting a payload as the content of the variant at `index`
+struct PayloadDeserializer<D> {
+    index: usize,
+    inner: D,
+}
+
+impl<'de, D> Deserializer<'de> for PayloadDeserializer<D>
+where
+    D: Deserializer<'de>,
+{
+    type Error = D::Error;
+
+    fn deserialize_any<V>(self, _visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        Err(de::Error::custom(NOT_AN_ENUM))
+    }
+
+    fn deserialize_enum<V>(self, name: &'static str, _variants: &'static [&'static str], visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_enum(PayloadEnum {
+            name,
+            index: self.index,
+            inner: self.inner,
+        })
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct seq tuple
+        tuple_struct map struct identifier ignored_any
+    }
+}
+
+/// Access to a variant given by index, its content read from the inner [Deserializer]
+struct PayloadEnum<D> {
+    name: &'static str,
+    index: usize,
+    inner: D,
+}
+
+impl<'de, D> EnumAccess<'de> for PayloadEnum<D>
+where
+    D: Deserializer<'de>,
+{
+    type Error = D::Error;
+    type Variant = Self;
+
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self::Variant), Self::Error>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        let tag: U64Deserializer<D::Error> = (self.index as u64).into_deserializer();
+        seed.deserialize(tag).map(|variant| (variant, self))
+    }
+}
+
+impl<'de, D> VariantAccess<'de> for PayloadEnum<D>
+where
+    D: Deserializer<'de>,
+{
+    type Error = D::Error;
+
+    fn unit_variant(self) -> Result<(), Self::Error> {
+        <()>::deserialize(self.inner)
+    }
+
+    fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value, Self::Error>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        seed.deserialize(self.inner)
+    }
+
+    fn tuple_variant<V>(self, len: usize, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.inner.deserialize_tuple(len, visitor)
+    }
+
+    fn struct_variant<V>(self, fields: &'static [&'static str], visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.inner.deserialize_struct(self.name, fields, visitor)
+    }
+}
@@ -0,0 +1,277 @@
+use serde::de::{self, SeqAccess, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use core::fmt::Formatter;
+use core::marker::PhantomData;
+use core::ops::{Deref, DerefMut};
+
+use crate::__private::UncheckedMut;
+use crate::common::{deserialize_key, MapValue, VariantKey, VariantMap};
+
+/// Wrapper making the deserialization of a [VariantMap] strict
+///
+/// A variant present twice in the input is an error naming its key instead of silently replacing the first one,
+/// every [required key][VariantKey::REQUIRED] must be present once the map is deserialized
+///
+/// The map is de/serialized as a sequence of variants, wrap it in an [Object][crate::object::Object] to use the object representation.
+///
+/// Used as the [Map][MapValue::Map] of an enum derived with `#[VariantStore(strict)]`,
+/// the same checks are available on any field through `#[serde(with = "variant_map::strict")]`
+///
+/// # Example
+///
+/// ```
+/// use serde::{Deserialize, Serialize};
+/// use variant_map::common::MapValue;
+/// use variant_map::strict::Strict;
+/// use variant_map_derive::VariantStore;
+///
+/// #[derive(Debug, Serialize, Deserialize, VariantStore)]
+/// enum Setting {
+///     Timeout(u32),
+///     Retries(u8),
+/// }
+///
+/// fn main() {
+///     let error = serde_json::from_str::<Strict<<Setting as MapValue>::Map>>(r#"[{"Timeout":30},{"Timeout":10}]"#)
+///         .unwrap_err();
+///     assert!(error.to_string().starts_with("duplicate key `Timeout`"));
+/// }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Strict<M>(pub M);
+
+impl<M> Strict<M> {
+    /// Unwraps the inner map
+    pub fn into_inner(self) -> M {
+        self.0
+    }
+}
+
+impl<M> From<M> for Strict<M> {
+    fn from(map: M) -> Self {
+        Strict(map)
+    }
+}
+
+impl<M> Deref for Strict<M> {
+    type Target = M;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<M> DerefMut for Strict<M> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl<'a, M> IntoIterator for &'a Strict<M>
+where
+    &'a M: IntoIterator,
+{
+    type Item = <&'a M as IntoIterator>::Item;
+    type IntoIter = <&'a M as IntoIterator>::IntoIter;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl<M> UncheckedMut for Strict<M>
+where
+    M: UncheckedMut,
+    M::Key: VariantKey,
+    M::Value: MapValue<Key = M::Key>,
+{
+    fn get_mut_unchecked(&mut self, key: &Self::Key) -> Option<&mut Self::Value> {
+        self.0.get_mut_unchecked(key)
+    }
+}
+
+/// Error of a deserializer reading `key` a second time
+pub fn duplicate_key<E, Key>(key: &Key) -> E
+where
+    E: de::Error,
+    Key: VariantKey,
+{
+    E::custom(format_args!("duplicate key `{}`", key.name()))
+}
+
+/// Error of a deserializer which did not read the required `key`
+pub fn missing_key<E, Key>(key: &Key) -> E
+where
+    E: de::Error,
+    Key: VariantKey,
+{
+    E::custom(format_args!("missing required key `{}`", key.name()))
+}
+
+impl<M> VariantMap for Strict<M>
+where
+    M: VariantMap,
+    M::Key: VariantKey,
+    M::Value: MapValue<Key = M::Key>,
+{
+    type Key = M::Key;
+    type Value = M::Value;
+    type Ref<'a> = M::Ref<'a>
+    where
+        Self: 'a;
+    type Mut<'a> = M::Mut<'a>
+    where
+        Self: 'a;
+    type Iter<'a> = M::Iter<'a>
+    where
+        Self: 'a;
+    type KeyedIter<'a> = M::KeyedIter<'a>
+    where
+        Self: 'a;
+
+    fn get(&self, key: &Self::Key) -> Option<Self::Ref<'_>> {
+        self.0.get(key)
+    }
+
+    fn get_mut(&mut self, key: &Self::Key) -> Option<Self::Mut<'_>> {
+        self.0.get_mut(key)
+    }
+
+    fn insert(&mut self, value: Self::Value) -> Option<Self::Value> {
+        self.0.insert(value)
+    }
+
+    fn remove(&mut self, key: &Self::Key) -> Option<Self::Value> {
+        self.0.remove(key)
+    }
+
+    fn contains_key(&self, key: &Self::Key) -> bool {
+        self.0.contains_key(key)
+    }
+
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    fn iter(&self) -> Self::Iter<'_> {
+        self.0.iter()
+    }
+
+    fn iter_keyed(&self) -> Self::KeyedIter<'_> {
+        self.0.iter_keyed()
+    }
+
+    fn clear(&mut self) {
+        self.0.clear()
+    }
+
+    fn deserialize_insert<E>(&mut self, value: Self::Value) -> Result<(), E>
+    where
+        E: de::Error,
+    {
+        let Some(key) = deserialize_key(&value)? else {
+            return Ok(());
+        };
+        if self.0.contains_key(&key) {
+            return Err(duplicate_key(&key));
+        }
+
+        self.0.deserialize_insert(value)
+    }
+
+    fn deserialize_finish<E>(&self) -> Result<(), E>
+    where
+        E: de::Error,
+    {
+        match M::Key::REQUIRED.iter().find(|key| !self.0.contains_key(key)) {
+            Some(key) => Err(missing_key(key)),
+            None => self.0.deserialize_finish(),
+        }
+    }
+}
+
+impl<M> Serialize for Strict<M>
+where
+    M: Serialize,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        self.0.serialize(serializer)
+    }
+}
+
+impl<'de, M> Deserialize<'de> for Strict<M>
+where
+    M: VariantMap + Default,
+    M::Key: VariantKey,
+    M::Value: MapValue<Key = M::Key> + Deserialize<'de>,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_seq(StrictVisitor {
+            marker: PhantomData,
+        })
+    }
+}
+
+/// Serializes `map` as usual, to be used with `#[serde(with = "variant_map::strict")]`
+///
+/// see [Strict]
+pub fn serialize<M, S>(map: &M, serializer: S) -> Result<S::Ok, S::Error>
+where
+    M: Serialize,
+    S: Serializer,
+{
+    map.serialize(serializer)
+}
+
+/// Deserializes a map from a sequence of variants with the checks of [Strict],
+/// to be used with `#[serde(with = "variant_map::strict")]`
+pub fn deserialize<'de, M, D>(deserializer: D) -> Result<M, D::Error>
+where
+    M: VariantMap + Default,
+    M::Key: VariantKey,
+    M::Value: MapValue<Key = M::Key> + Deserialize<'de>,
+    D: Deserializer<'de>,
+{
+    Strict::<M>::deserialize(deserializer).map(Strict::into_inner)
+}
+
+struct StrictVisitor<M> {
+    marker: PhantomData<fn() -> M>,
+}
+
+impl<'de, M> Visitor<'de> for StrictVisitor<M>
+where
+    M: VariantMap + Default,
+    M::Key: VariantKey,
+    M::Value: MapValue<Key = M::Key> + Deserialize<'de>,
+{
+    type Value = Strict<M>;
+
+    fn expecting(&self, formatter: &mut Formatter) -> core::fmt::Result {
+        write!(formatter, "StrictVisitor expects to receive a sequence of untagged Enum variants with distinct keys")
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let mut map = Strict(M::default());
+
+        while let Some(value) = seq.next_element()? {
+            map.deserialize_insert(value)?;
+        }
+
+        map.deserialize_finish()?;
+        Ok(map)
+    }
+}
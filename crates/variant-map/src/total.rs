@@ -0,0 +1,77 @@
+use core::error::Error;
+use core::fmt::{Debug, Display, Formatter};
+
+use crate::common::VariantKey;
+use crate::keyset::KeySet;
+
+/// Error of the total maps generated with `#[VariantStore(total)]`, listing the keys without a value
+///
+/// Returned when building a total map or converting it from a partial map
+pub struct MissingKeys<Key> {
+    keys: KeySet<Key>,
+}
+
+impl<Key> MissingKeys<Key>
+where
+    Key: VariantKey,
+{
+    /// Error listing `keys`
+    pub fn new(keys: KeySet<Key>) -> Self {
+        MissingKeys { keys }
+    }
+
+    /// Keys without a value, ordered as the variants of the enum
+    pub fn keys(&self) -> &KeySet<Key> {
+        &self.keys
+    }
+
+    /// Unwraps the missing keys
+    pub fn into_keys(self) -> KeySet<Key> {
+        self.keys
+    }
+}
+
+// the traits are implemented by hand, the derives would require them on `Key`
+
+impl<Key> Debug for MissingKeys<Key>
+where
+    Key: VariantKey + Debug,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("MissingKeys").field("keys", &self.keys).finish()
+    }
+}
+
+impl<Key> Clone for MissingKeys<Key> {
+    fn clone(&self) -> Self {
+        MissingKeys { keys: self.keys }
+    }
+}
+
+impl<Key> PartialEq for MissingKeys<Key> {
+    fn eq(&self, other: &Self) -> bool {
+        self.keys == other.keys
+    }
+}
+
+impl<Key> Eq for MissingKeys<Key> {}
+
+impl<Key> Display for MissingKeys<Key>
+where
+    Key: VariantKey,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(f, "missing keys ")?;
+
+        for (i, key) in self.keys.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "`{}`", key.name())?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<Key> Error for MissingKeys<Key> where Key: VariantKey + Debug {}
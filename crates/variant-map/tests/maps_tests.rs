@@ -147,3 +147,1369 @@ pub fn serialize() {
         println!("expect~ {:#?}", m2_str);
     }
 }
+
+#[test]
+pub fn struct_variants() {
+    use serde::{Deserialize, Serialize};
+    use variant_map_derive::VariantStore;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize, VariantStore)]
+    #[VariantStore(datastruct = "HashMap")]
+    enum HashEnum {
+        A,
+        B(i32),
+        Config { port: u16, host: String },
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize, VariantStore)]
+    #[VariantStore(datastruct = "BTreeMap")]
+    enum BTreeEnum {
+        A,
+        B(i32),
+        Config { port: u16, host: String },
+    }
+
+    let config = HashEnum::Config { port: 8080, host: "localhost".to_string() };
+    assert_eq!(config.to_key(), HashEnumKey::Config);
+
+    let mut m = HashEnum::make_map();
+    m.insert(HashEnum::A);
+    m.insert(HashEnum::B(1));
+    assert_eq!(m.insert(config), None);
+    assert_eq!(
+        m.insert(HashEnum::Config { port: 443, host: "example.com".to_string() }),
+        Some(HashEnum::Config { port: 8080, host: "localhost".to_string() })
+    );
+
+    let m_str = serde_json::to_string(&m).unwrap();
+    let m2: <HashEnum as MapValue>::Map = serde_json::from_str(&m_str).unwrap();
+    assert_eq!(
+        m2.get(&HashEnumKey::Config),
+        Some(&HashEnum::Config { port: 443, host: "example.com".to_string() })
+    );
+
+    let config = BTreeEnum::Config { port: 8080, host: "localhost".to_string() };
+    assert_eq!(config.to_key(), BTreeEnumKey::Config);
+
+    let mut m = BTreeEnum::make_map();
+    m.insert(BTreeEnum::A);
+    m.insert(BTreeEnum::B(1));
+    m.insert(config);
+
+    let m_str = serde_json::to_string(&m).unwrap();
+    assert_eq!(m_str, "[\"A\",{\"B\":1},{\"Config\":{\"port\":8080,\"host\":\"localhost\"}}]");
+    let m2: <BTreeEnum as MapValue>::Map = serde_json::from_str(&m_str).unwrap();
+    assert_eq!(
+        m2.get(&BTreeEnumKey::Config),
+        Some(&BTreeEnum::Config { port: 8080, host: "localhost".to_string() })
+    );
+}
+
+#[test]
+pub fn array_map() {
+    use serde::{Deserialize, Serialize};
+    use variant_map_derive::VariantStore;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize, VariantStore)]
+    #[VariantStore(datastruct = "ArrayMap")]
+    enum ArrayEnum {
+        A,
+        B(i32),
+        #[key_name(code = "Conf")]
+        Config { port: u16, host: String },
+    }
+
+    let mut m = ArrayEnum::make_map();
+    assert!(m.is_empty());
+
+    m.insert(ArrayEnum::Config { port: 8080, host: "localhost".to_string() });
+    m.insert(ArrayEnum::A);
+    assert_eq!(m.insert(ArrayEnum::B(1)), None);
+    assert_eq!(m.insert(ArrayEnum::B(2)), Some(ArrayEnum::B(1)));
+    assert_eq!(m.len(), 3);
+    assert!(m.contains_key(&ArrayEnumKey::Conf));
+
+    assert_eq!(m[ArrayEnumKey::B], ArrayEnum::B(2));
+    *m.get_mut(&ArrayEnumKey::B).unwrap() = ArrayEnum::B(3);
+    if let Some(ArrayEnum::B(b)) = m.get_mut(&ArrayEnumKey::B).as_deref_mut() {
+        *b += 1;
+    }
+    assert_eq!(m.get(&ArrayEnumKey::B), Some(&ArrayEnum::B(4)));
+
+    let keys: Vec<ArrayEnumKey> = m.iter().map(|value| value.to_key()).collect();
+    assert_eq!(keys, vec![ArrayEnumKey::A, ArrayEnumKey::B, ArrayEnumKey::Conf]);
+
+    let m_str = serde_json::to_string(&m).unwrap();
+    assert_eq!(m_str, "[\"A\",{\"B\":4},{\"Config\":{\"port\":8080,\"host\":\"localhost\"}}]");
+    let mut m2: <ArrayEnum as MapValue>::Map = serde_json::from_str(&m_str).unwrap();
+    assert_eq!(m2.remove(&ArrayEnumKey::A), Some(ArrayEnum::A));
+    assert_eq!(m2.len(), 2);
+
+    m2.clear();
+    assert!(m2.is_empty());
+    assert_eq!(m2.get(&ArrayEnumKey::Conf), None);
+}
+
+/// Fills a map through the [VariantMap] interface only, whatever the storage
+fn fill_generic<E>(values: Vec<E>) -> E::Map
+where
+    E: MapValue,
+{
+    use variant_map::common::VariantMap;
+
+    let mut map = E::make_map();
+    for value in values {
+        map.insert(value);
+    }
+    map
+}
+
+#[test]
+pub fn variant_map_trait() {
+    use serde::{Deserialize, Serialize};
+    use variant_map::common::VariantMap;
+    use variant_map_derive::VariantStore;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize, VariantStore)]
+    #[VariantStore(datastruct = "HashMap")]
+    enum HashEnum {
+        A,
+        B(i32),
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize, VariantStore)]
+    #[VariantStore(datastruct = "BTreeMap")]
+    enum BTreeEnum {
+        A,
+        B(i32),
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize, VariantStore)]
+    #[VariantStore(datastruct = "ArrayMap")]
+    enum ArrayEnum {
+        A,
+        B(i32),
+        C,
+    }
+
+    let hash_map = fill_generic(vec![HashEnum::A, HashEnum::B(1), HashEnum::B(2)]);
+    assert_eq!(VariantMap::len(&hash_map), 2);
+    assert!(VariantMap::contains_key(&hash_map, &HashEnumKey::A));
+    assert_eq!(VariantMap::get(&hash_map, &HashEnumKey::B), Some(&HashEnum::B(2)));
+
+    let mut btree_map = fill_generic(vec![BTreeEnum::B(1), BTreeEnum::A]);
+    assert_eq!(VariantMap::iter(&btree_map).collect::<Vec<_>>(), vec![&BTreeEnum::A, &BTreeEnum::B(1)]);
+    if let Some(BTreeEnum::B(b)) = VariantMap::get_mut(&mut btree_map, &BTreeEnumKey::B).as_deref_mut() {
+        *b = 10;
+    }
+    assert_eq!(VariantMap::remove(&mut btree_map, &BTreeEnumKey::B), Some(BTreeEnum::B(10)));
+    VariantMap::clear(&mut btree_map);
+    assert!(VariantMap::is_empty(&btree_map));
+
+    let array_map = fill_generic(vec![ArrayEnum::B(1), ArrayEnum::A]);
+    assert_eq!(VariantMap::len(&array_map), 2);
+    assert!(!VariantMap::contains_key(&array_map, &ArrayEnumKey::C));
+    assert_eq!(VariantMap::iter(&array_map).collect::<Vec<_>>(), vec![&ArrayEnum::A, &ArrayEnum::B(1)]);
+}
+
+#[test]
+pub fn key_metadata() {
+    use serde::{Deserialize, Serialize};
+    use variant_map::common::{VariantKey, VariantMap};
+    use variant_map_derive::VariantStore;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize, VariantStore)]
+    #[VariantStore(datastruct = "HashMap")]
+    enum HashEnum {
+        A,
+        B(i32),
+        Config { port: u16, host: String },
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize, VariantStore)]
+    #[VariantStore(datastruct = "ArrayMap", keys(derive(Serialize)))]
+    enum ArrayEnum {
+        A,
+        B(i32),
+        #[key_name(code = "Conf", serde = "config")]
+        Config { port: u16, host: String },
+    }
+
+    assert_eq!(HashEnumKey::COUNT, 3);
+    assert_eq!(HashEnumKey::ALL, [HashEnumKey::A, HashEnumKey::B, HashEnumKey::Config]);
+    assert_eq!(<HashEnumKey as VariantKey>::ALL, &HashEnumKey::ALL);
+    assert_eq!(HashEnumKey::Config.name(), "Config");
+    assert_eq!(HashEnumKey::B.index(), 1);
+    assert_eq!(HashEnumKey::from_index(2), Some(HashEnumKey::Config));
+    assert_eq!(HashEnumKey::from_index(3), None);
+
+    assert_eq!(<ArrayEnumKey as VariantKey>::COUNT, 3);
+    assert_eq!(ArrayEnumKey::Conf.name(), "config");
+    assert_eq!(ArrayEnum::B(0).to_key().index(), 1);
+    assert_eq!(serde_json::to_string(&ArrayEnumKey::Conf).unwrap(), "\"config\"");
+    for (index, key) in ArrayEnumKey::ALL.iter().enumerate() {
+        assert_eq!(key.index(), index);
+        assert_eq!(ArrayEnumKey::from_index(index).as_ref(), Some(key));
+    }
+
+    let mut m = HashEnum::make_map();
+    m.insert(HashEnum::B(1));
+    let missing: Vec<&HashEnumKey> = HashEnumKey::ALL
+        .iter()
+        .filter(|key| !VariantMap::contains_key(&m, key))
+        .collect();
+    assert_eq!(missing, vec![&HashEnumKey::A, &HashEnumKey::Config]);
+}
+
+#[test]
+pub fn typed_accessors() {
+    use serde::{Deserialize, Serialize};
+    use variant_map::common::VariantMap;
+    use variant_map_derive::VariantStore;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize, VariantStore)]
+    #[VariantStore(datastruct = "HashMap")]
+    enum HashEnum {
+        A,
+        B(i32),
+        MyVariant(u8),
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize, VariantStore)]
+    #[VariantStore(datastruct = "BTreeMap")]
+    enum BTreeEnum {
+        Config { port: u16, host: String },
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize, VariantStore)]
+    #[VariantStore(datastruct = "ArrayMap")]
+    enum ArrayEnum {
+        B(i32),
+        Config { port: u16, host: String },
+    }
+
+    let mut m = HashEnum::make_map();
+    assert_eq!(m.get_b(), None);
+    assert_eq!(m.set_b(1), None);
+    assert_eq!(m.set_b(2), Some(1));
+    if let Some(b) = m.get_b_mut() {
+        *b += 1;
+    }
+    assert_eq!(m.get_b(), Some(&3));
+    assert_eq!(m.take_b(), Some(3));
+    assert_eq!(m.take_b(), None);
+
+    assert_eq!(m.set_a(), None);
+    assert_eq!(m.get_a(), Some(()));
+
+    m.set_my_variant(7);
+    assert_eq!(m.get_my_variant(), Some(&7));
+
+    let mut m = BTreeEnum::make_map();
+    m.set_config(8080, "localhost".to_string());
+    assert_eq!(m.get_config(), Some((&8080, &"localhost".to_string())));
+    if let Some((port, _host)) = m.get_config_mut() {
+        *port = 443;
+    }
+    assert_eq!(m.get(&BTreeEnumKey::Config), Some(&BTreeEnum::Config { port: 443, host: "localhost".to_string() }));
+    assert_eq!(m.take_config(), Some((443, "localhost".to_string())));
+
+    let mut m = ArrayEnum::make_map();
+    VariantMap::insert(&mut m, ArrayEnum::B(5));
+    assert_eq!(m.get_b(), Some(&5));
+    assert_eq!(m.get_config(), None);
+}
+
+#[test]
+pub fn object_format() {
+    use serde::{Deserialize, Serialize};
+    use variant_map::common::VariantMap;
+    use variant_map_derive::VariantStore;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize, VariantStore)]
+    #[VariantStore(datastruct = "BTreeMap", serde_format = "object")]
+    enum ObjectEnum {
+        A,
+        #[key_name(serde = "timeout")]
+        Timeout(u32),
+        Pair(i32, String),
+        #[key_name(serde = "config")]
+        Config { port: u16, host: String },
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize, VariantStore)]
+    enum HashEnum {
+        A,
+        B(i32),
+    }
+
+    #[derive(Debug, Default, Serialize, Deserialize)]
+    struct Settings {
+        #[serde(with = "variant_map::object")]
+        hash: <HashEnum as MapValue>::Map,
+    }
+
+    let mut m = ObjectEnum::make_map();
+    m.insert(ObjectEnum::A);
+    m.insert(ObjectEnum::Timeout(30));
+    m.insert(ObjectEnum::Pair(1, "one".to_string()));
+    m.insert(ObjectEnum::Config { port: 8080, host: "localhost".to_string() });
+
+    let m_str = serde_json::to_string(&m).unwrap();
+    assert_eq!(
+        m_str,
+        "{\"A\":null,\"timeout\":30,\"Pair\":[1,\"one\"],\"config\":{\"port\":8080,\"host\":\"localhost\"}}"
+    );
+
+    let m2: <ObjectEnum as MapValue>::Map = serde_json::from_str(&m_str).unwrap();
+    assert_eq!(m2.len(), 4);
+    assert_eq!(m2.get(&ObjectEnumKey::A), Some(&ObjectEnum::A));
+    assert_eq!(m2[ObjectEnumKey::Timeout], ObjectEnum::Timeout(30));
+    assert_eq!(m2.get(&ObjectEnumKey::Pair), Some(&ObjectEnum::Pair(1, "one".to_string())));
+    assert_eq!(
+        m2.get(&ObjectEnumKey::Config),
+        Some(&ObjectEnum::Config { port: 8080, host: "localhost".to_string() })
+    );
+
+    assert!(serde_json::from_str::<<ObjectEnum as MapValue>::Map>("{\"unknown\":1}").is_err());
+    assert!(serde_json::from_str::<<ObjectEnum as MapValue>::Map>("{\"timeout\":\"30\"}").is_err());
+
+    let mut settings = Settings::default();
+    settings.hash.insert(HashEnum::B(3));
+    let settings_str = serde_json::to_string(&settings).unwrap();
+    assert_eq!(settings_str, "{\"hash\":{\"B\":3}}");
+    let settings: Settings = serde_json::from_str(&settings_str).unwrap();
+    assert_eq!(VariantMap::get(&settings.hash, &HashEnumKey::B), Some(&HashEnum::B(3)));
+    assert!(!settings.hash.contains_key(&HashEnumKey::A));
+}
+
+#[test]
+pub fn object_key_order() {
+    use serde::{Deserialize, Serialize};
+    use variant_map::common::VariantKey;
+    use variant_map::hashmap::{HashKey, Map};
+    use variant_map::object::Object;
+
+    // the keys are not ordered as the variants
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    enum Shape {
+        Size(u32),
+        Count(u32),
+    }
+
+    #[derive(Debug, PartialEq, Eq, Hash)]
+    enum Kind {
+        Count,
+        Size,
+    }
+
+    impl HashKey for Kind {}
+
+    impl VariantKey for Kind {
+        const COUNT: usize = 2;
+        const ALL: &'static [Self] = &[Kind::Count, Kind::Size];
+
+        fn name(&self) -> &'static str {
+            match self {
+                Kind::Count => "count",
+                Kind::Size => "size",
+            }
+        }
+
+        fn index(&self) -> usize {
+            match self {
+                Kind::Count => 0,
+                Kind::Size => 1,
+            }
+        }
+
+        fn from_index(index: usize) -> Option<Self> {
+            match index {
+                0 => Some(Kind::Count),
+                1 => Some(Kind::Size),
+                _ => None,
+            }
+        }
+    }
+
+    impl MapValue for Shape {
+        type Key = Kind;
+        type Map = Map<Kind, Shape>;
+
+        fn to_key(&self) -> Self::Key {
+            match self {
+                Shape::Size(_) => Kind::Size,
+                Shape::Count(_) => Kind::Count,
+            }
+        }
+
+        fn make_map() -> Self::Map {
+            Map::default()
+        }
+    }
+
+    let mut m = Object(Shape::make_map());
+    m.insert(Shape::Count(5));
+    let m_str = serde_json::to_string(&m).unwrap();
+    assert_eq!(m_str, "{\"count\":5}");
+
+    // read back as the variant at index 0 of the enum, `Size`, which is rejected
+    let error = serde_json::from_str::<Object<<Shape as MapValue>::Map>>(&m_str).unwrap_err();
+    assert!(error.to_string().contains("the key indices must follow the order of the enum variants"));
+}
+
+#[test]
+pub fn strict_deserialization() {
+    use serde::{Deserialize, Serialize};
+    use variant_map::common::VariantMap;
+    use variant_map_derive::VariantStore;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize, VariantStore)]
+    #[VariantStore(datastruct = "HashMap", strict(require(A, Timeout)))]
+    enum StrictEnum {
+        A,
+        #[key_name(serde = "timeout")]
+        Timeout(u32),
+        Retries(u8),
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize, VariantStore)]
+    #[VariantStore(datastruct = "ArrayMap", serde_format = "object", strict)]
+    enum StrictObjectEnum {
+        A,
+        Timeout(u32),
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize, VariantStore)]
+    #[VariantStore(datastruct = "BTreeMap")]
+    enum BTreeEnum {
+        A,
+        B(i32),
+    }
+
+    #[derive(Debug, Default, Serialize, Deserialize)]
+    struct StrictSettings {
+        #[serde(with = "variant_map::strict")]
+        btree: <BTreeEnum as MapValue>::Map,
+    }
+
+    type StrictMap = <StrictEnum as MapValue>::Map;
+
+    let m: StrictMap = serde_json::from_str("[\"A\",{\"Timeout\":30}]").unwrap();
+    assert_eq!(m.get(&StrictEnumKey::Timeout), Some(&StrictEnum::Timeout(30)));
+    assert_eq!(serde_json::to_string(&m).unwrap().len(), "[\"A\",{\"Timeout\":30}]".len());
+
+    let error = serde_json::from_str::<StrictMap>("[\"A\",{\"Timeout\":30},{\"Timeout\":10}]").unwrap_err();
+    assert!(error.to_string().starts_with("duplicate key `timeout`"), "{error}");
+
+    let error = serde_json::from_str::<StrictMap>("[\"A\",{\"Retries\":3}]").unwrap_err();
+    assert!(error.to_string().starts_with("missing required key `timeout`"), "{error}");
+
+    type StrictObjectMap = <StrictObjectEnum as MapValue>::Map;
+    let m: StrictObjectMap = serde_json::from_str("{\"Timeout\":30}").unwrap();
+    assert_eq!(m.len(), 1);
+    assert!(!m.contains_key(&StrictObjectEnumKey::A));
+    let error = serde_json::from_str::<StrictObjectMap>("{\"A\":null,\"A\":null}").unwrap_err();
+    assert!(error.to_string().starts_with("duplicate key `A`"), "{error}");
+
+    let settings: StrictSettings = serde_json::from_str("{\"btree\":[{\"B\":1}]}").unwrap();
+    assert_eq!(VariantMap::get(&settings.btree, &BTreeEnumKey::B), Some(&BTreeEnum::B(1)));
+    assert!(!settings.btree.contains_key(&BTreeEnumKey::A));
+    let error = serde_json::from_str::<StrictSettings>("{\"btree\":[{\"B\":1},{\"B\":2}]}").unwrap_err();
+    assert!(error.to_string().starts_with("duplicate key `B`"), "{error}");
+
+    // lenient maps keep the last duplicated variant
+    let m: <BTreeEnum as MapValue>::Map = serde_json::from_str("[{\"B\":1},{\"B\":2}]").unwrap();
+    assert_eq!(m.get(&BTreeEnumKey::B), Some(&BTreeEnum::B(2)));
+}
+
+#[test]
+pub fn total_map() {
+    use serde::{Deserialize, Serialize};
+    use variant_map::common::VariantMap;
+    use variant_map::keyset::KeySet;
+    use variant_map_derive::VariantStore;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize, VariantStore)]
+    #[VariantStore(datastruct = "HashMap", total(derive(Debug, PartialEq)))]
+    enum TotalEnum {
+        A,
+        B(i32),
+        Config { port: u16, host: String },
+    }
+
+    let mut total = TotalEnumTotalMap::builder()
+        .with(TotalEnum::A)
+        .with(TotalEnum::B(1))
+        .with(TotalEnum::Config { port: 8080, host: "localhost".to_string() })
+        .build()
+        .unwrap();
+
+    assert_eq!(total.get(&TotalEnumKey::B), &TotalEnum::B(1));
+    assert_eq!(total[TotalEnumKey::A], TotalEnum::A);
+    assert_eq!(total.get_config(), (&8080, &"localhost".to_string()));
+    assert_eq!(total.iter().count(), 3);
+
+    *total.get_b_mut() += 1;
+    assert_eq!(total.set_b(5), 2);
+    assert_eq!(total.replace(TotalEnum::B(7)), TotalEnum::B(5));
+
+    let error = TotalEnumTotalMap::builder().with(TotalEnum::B(1)).build().unwrap_err();
+    assert_eq!(error.keys(), &KeySet::from_iter([TotalEnumKey::A, TotalEnumKey::Config]));
+    assert_eq!(error.to_string(), "missing keys `A`, `Config`");
+
+    // deserialized partial maps are checked with `try_from`
+    let partial: <TotalEnum as MapValue>::Map = serde_json::from_str("[\"A\",{\"B\":3}]").unwrap();
+    let error = TotalEnumTotalMap::try_from(partial).unwrap_err();
+    assert_eq!(error.into_keys(), KeySet::from_iter([TotalEnumKey::Config]));
+
+    let partial: <TotalEnum as MapValue>::Map = total.into();
+    assert_eq!(VariantMap::len(&partial), 3);
+    let total = TotalEnumTotalMap::try_from(partial).unwrap();
+    assert_eq!(total.get_b(), &7);
+}
+
+#[test]
+pub fn entry_api() {
+    use serde::{Deserialize, Serialize};
+    use variant_map::common::VariantMap;
+    use variant_map::entry::Entry;
+    use variant_map_derive::VariantStore;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize, VariantStore)]
+    #[VariantStore(datastruct = "HashMap")]
+    enum HashEnum {
+        A,
+        B(i32),
+        Config { port: u16, host: String },
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize, VariantStore)]
+    #[VariantStore(datastruct = "BTreeMap")]
+    enum BTreeEnum {
+        B(i32),
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize, VariantStore)]
+    #[VariantStore(datastruct = "ArrayMap")]
+    enum ArrayEnum {
+        A,
+        B(i32),
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize, VariantStore)]
+    #[VariantStore(datastruct = "BTreeMap", serde_format = "object")]
+    enum ObjectEnum {
+        Timeout(u32),
+    }
+
+    let mut m = HashEnum::make_map();
+
+    // the entry of the inner HashMap is shadowed
+    *m.entry(HashEnumKey::B).or_insert(HashEnum::B(1)).unwrap() = HashEnum::B(2);
+    m.entry(HashEnumKey::B)
+        .and_modify(|b| if let HashEnum::B(b) = b { *b += 1 })
+        .or_insert_with(|| HashEnum::B(0))
+        .unwrap();
+    assert_eq!(m.get_b(), Some(&3));
+
+    m.entry_for(&HashEnum::A).unwrap().or_insert(HashEnum::A).unwrap();
+    assert_eq!(&*m.entry(HashEnumKey::Config).or_default(), &HashEnum::Config { port: 0, host: String::new() });
+    assert_eq!(m.len(), 3);
+
+    match m.entry(HashEnumKey::Config) {
+        Entry::Occupied(entry) => assert_eq!(entry.remove(), HashEnum::Config { port: 0, host: String::new() }),
+        Entry::Vacant(_) => unreachable!(),
+    }
+    assert!(matches!(m.entry(HashEnumKey::Config), Entry::Vacant(_)));
+
+    let mut m = BTreeEnum::make_map();
+    assert_eq!(&*m.entry(BTreeEnumKey::B).or_default(), &BTreeEnum::B(0));
+
+    let mut m = ArrayEnum::make_map();
+    assert_eq!(&*m.entry_for(&ArrayEnum::B(5)).unwrap().or_insert_with_key(|key| {
+        assert_eq!(key, &ArrayEnumKey::B);
+        ArrayEnum::B(5)
+    }).unwrap(), &ArrayEnum::B(5));
+    assert!(!m.contains_key(&ArrayEnumKey::A));
+
+    // the trait provides the same entries, even to wrapped maps
+    let mut m = ObjectEnum::make_map();
+    VariantMap::entry(&mut m, ObjectEnumKey::Timeout).or_insert(ObjectEnum::Timeout(30)).unwrap();
+    assert_eq!(m.get(&ObjectEnumKey::Timeout), Some(&ObjectEnum::Timeout(30)));
+
+    let mismatch = std::panic::catch_unwind(|| {
+        let mut m = HashEnum::make_map();
+        let _ = m.entry(HashEnumKey::A).or_insert(HashEnum::B(1));
+    }).unwrap_err();
+    assert_eq!(mismatch.downcast_ref::<String>().unwrap(), "entry of key `A` cannot hold a value of key `B`");
+}
+
+#[test]
+pub fn key_consistency() {
+    use std::collections::HashMap;
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+    use serde::{Deserialize, Serialize};
+    use variant_map::common::VariantMap;
+    use variant_map::hashmap::Map;
+    use variant_map_derive::VariantStore;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize, VariantStore)]
+    #[VariantStore(datastruct = "HashMap")]
+    enum HashEnum {
+        A,
+        B(i32),
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize, VariantStore)]
+    #[VariantStore(datastruct = "ArrayMap")]
+    enum ArrayEnum {
+        A,
+        B(i32),
+    }
+
+    let mut m: Map<HashEnumKey, HashEnum> = vec![HashEnum::A, HashEnum::B(1)].into_iter().collect();
+    m.extend(vec![HashEnum::B(2)]);
+    assert_eq!(m.len(), 2);
+    for mut value in m.iter_mut() {
+        if let HashEnum::B(b) = &mut *value {
+            *b += 1;
+        }
+    }
+    assert_eq!(m.get(&HashEnumKey::B), Some(&HashEnum::B(3)));
+    m.retain(|value| matches!(value, HashEnum::B(_)));
+    assert!(!m.contains_key(&HashEnumKey::A));
+    assert_eq!(m.validate(), Ok(()));
+
+    // the guard panics when dropped, the map is left inconsistent once the panic is caught
+    let replaced = catch_unwind(AssertUnwindSafe(|| {
+        *m.get_mut(&HashEnumKey::B).unwrap() = HashEnum::A;
+    })).unwrap_err();
+    assert_eq!(*replaced.downcast_ref::<&str>().unwrap(), "a value of the map cannot be replaced by a variant of another key");
+    let mismatch = m.validate().unwrap_err();
+    assert_eq!((mismatch.key, &mismatch.found), (&HashEnumKey::B, &HashEnumKey::A));
+    assert_eq!(mismatch.to_string(), "value of key `A` stored under key `B`");
+
+    let inconsistent = Map::try_new(HashMap::from([(HashEnumKey::A, HashEnum::B(1))])).unwrap_err();
+    assert!(catch_unwind(AssertUnwindSafe(|| Map::new(inconsistent))).is_err());
+
+    let mut m = ArrayEnum::make_map();
+    m.insert(ArrayEnum::B(1));
+    if let Some(ArrayEnum::B(b)) = m.get_mut(&ArrayEnumKey::B).as_deref_mut() {
+        *b = 2;
+    }
+    assert_eq!(VariantMap::iter_keyed(&m).collect::<Vec<_>>(), vec![(&ArrayEnumKey::B, &ArrayEnum::B(2))]);
+    assert!(catch_unwind(AssertUnwindSafe(|| {
+        for mut value in &mut m {
+            *value = ArrayEnum::A;
+        }
+    })).is_err());
+    assert!(m.validate().is_err());
+}
+
+#[test]
+pub fn indexing() {
+    use serde::{Deserialize, Serialize};
+    use variant_map::common::VariantMap;
+    use variant_map_derive::VariantStore;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize, VariantStore)]
+    #[VariantStore(datastruct = "HashMap")]
+    enum HashEnum {
+        A,
+        B(i32),
+        Config { port: u16, host: String },
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize, VariantStore)]
+    #[VariantStore(datastruct = "BTreeMap")]
+    enum BTreeEnum {
+        A,
+        B(i32),
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize, VariantStore)]
+    #[VariantStore(datastruct = "ArrayMap")]
+    enum ArrayEnum {
+        A,
+        #[key_name(code = "Conf")]
+        Config { port: u16 },
+    }
+
+    let mut m = HashEnum::make_map();
+    m.insert(HashEnum::B(2));
+    assert_eq!(m[&HashEnumKey::B], HashEnum::B(2));
+    assert_eq!(m[HashEnumKey::B], HashEnum::B(2));
+
+    let missing = std::panic::catch_unwind(|| HashEnum::make_map()[&HashEnumKey::Config] == HashEnum::A).unwrap_err();
+    assert_eq!(missing.downcast_ref::<String>().unwrap(), "no value stored under key `Config`");
+
+    let missing = std::panic::catch_unwind(|| ArrayEnum::make_map()[ArrayEnumKey::Conf] == ArrayEnum::A).unwrap_err();
+    assert_eq!(missing.downcast_ref::<String>().unwrap(), "no value stored under key `Conf`");
+
+    let mut m = BTreeEnum::make_map();
+    assert_eq!(&*m.get_or_insert(BTreeEnum::B(1)).unwrap(), &BTreeEnum::B(1));
+    assert_eq!(&*m.get_or_insert(BTreeEnum::B(2)).unwrap(), &BTreeEnum::B(1));
+    if let BTreeEnum::B(b) = &mut *m.get_or_insert_with(BTreeEnumKey::B, || BTreeEnum::B(0)).unwrap() {
+        *b += 1;
+    }
+    assert_eq!(m[&BTreeEnumKey::B], BTreeEnum::B(2));
+    m.get_or_insert_with(BTreeEnumKey::A, || BTreeEnum::A).unwrap();
+    assert_eq!(m[BTreeEnumKey::A], BTreeEnum::A);
+}
+
+#[test]
+pub fn key_set() {
+    use serde::{Deserialize, Serialize};
+    use variant_map::common::VariantMap;
+    use variant_map::keyset::KeySet;
+    use variant_map_derive::VariantStore;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize, VariantStore)]
+    #[VariantStore(datastruct = "HashMap")]
+    enum HashEnum {
+        A,
+        B(i32),
+        Config { port: u16, host: String },
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize, VariantStore)]
+    #[VariantStore(datastruct = "ArrayMap")]
+    enum ArrayEnum {
+        A,
+        #[key_name(code = "Conf", serde = "config")]
+        Config { port: u16 },
+    }
+
+    let mut m = HashEnum::make_map();
+    m.insert(HashEnum::A);
+    m.insert(HashEnum::Config { port: 80, host: "localhost".to_string() });
+
+    let present = m.keys_set();
+    assert_eq!(present.len(), 2);
+    assert!(present.contains(&HashEnumKey::A));
+    assert!(!present.contains(&HashEnumKey::B));
+    assert_eq!(present.iter().collect::<Vec<_>>(), vec![HashEnumKey::A, HashEnumKey::Config]);
+    assert_eq!(present.complement(), KeySet::from_iter([HashEnumKey::B]));
+    assert_eq!(format!("{:?}", present), "{A, Config}");
+
+    let mut other = KeySet::new();
+    assert!(other.insert(HashEnumKey::B));
+    assert!(!other.insert(HashEnumKey::B));
+    other.insert(HashEnumKey::Config);
+    assert_eq!(present.union(&other), KeySet::all());
+    assert_eq!(present.intersection(&other), KeySet::from_iter([HashEnumKey::Config]));
+    assert_eq!(present.difference(&other), KeySet::from_iter([HashEnumKey::A]));
+    assert_eq!(present.symmetric_difference(&other).len(), 2);
+    assert!(present.intersection(&other).is_subset(&other));
+    assert!(other.remove(&HashEnumKey::B));
+    assert!(present.is_superset(&other));
+
+    assert_eq!(serde_json::to_string(&present).unwrap(), "[\"A\",\"Config\"]");
+    let keys: KeySet<ArrayEnumKey> = serde_json::from_str("[\"config\",\"A\"]").unwrap();
+    assert_eq!(keys, KeySet::from_iter([ArrayEnumKey::A, ArrayEnumKey::Conf]));
+    assert!(serde_json::from_str::<KeySet<ArrayEnumKey>>("[\"Z\"]").is_err());
+}
+
+#[test]
+pub fn merge() {
+    use serde::{Deserialize, Serialize};
+    use variant_map::common::VariantMap;
+    use variant_map::merge::{overlay, KeepLeft, KeepRight};
+    use variant_map_derive::VariantStore;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize, VariantStore)]
+    #[VariantStore(datastruct = "HashMap")]
+    enum HashEnum {
+        A,
+        B(i32),
+        Config { port: u16, host: String },
+    }
+
+    #[derive(Debug, Clone, PartialEq, VariantStore)]
+    #[VariantStore(datastruct = "BTreeMap")]
+    enum LayerEnum {
+        Timeout(u32),
+        Retries(u8),
+        Verbose,
+    }
+
+    let layer = |values: Vec<HashEnum>| values.into_iter().collect::<<HashEnum as MapValue>::Map>();
+
+    let mut m = layer(vec![HashEnum::A, HashEnum::B(1)]);
+    m.merge(layer(vec![HashEnum::B(2)]), KeepLeft).unwrap();
+    assert_eq!(m.get(&HashEnumKey::B), Some(&HashEnum::B(1)));
+
+    m.merge(layer(vec![HashEnum::B(2), HashEnum::Config { port: 80, host: "localhost".to_string() }]), KeepRight).unwrap();
+    assert_eq!(m.get(&HashEnumKey::B), Some(&HashEnum::B(2)));
+    assert_eq!(m.len(), 3);
+
+    m.merge_with(layer(vec![HashEnum::B(3)]), |key, left, right| match (left, right) {
+        (HashEnum::B(l), HashEnum::B(r)) => HashEnum::B(l + r),
+        _ => unreachable!("{key:?} is the only key held by both maps"),
+    }).unwrap();
+    assert_eq!(m.get(&HashEnumKey::B), Some(&HashEnum::B(5)));
+
+    let mut defaults = LayerEnum::make_map();
+    defaults.insert(LayerEnum::Timeout(30));
+    defaults.insert(LayerEnum::Retries(3));
+    let mut file = LayerEnum::make_map();
+    file.insert(LayerEnum::Retries(5));
+    let mut cli = LayerEnum::make_map();
+    cli.insert(LayerEnum::Verbose);
+
+    let settings = overlay(&overlay(&defaults, &file), &cli);
+    assert_eq!(settings.iter().collect::<Vec<_>>(), vec![&LayerEnum::Timeout(30), &LayerEnum::Retries(5), &LayerEnum::Verbose]);
+    assert_eq!(defaults.get(&LayerEnumKey::Retries), Some(&LayerEnum::Retries(3)));
+
+    let mismatch = std::panic::catch_unwind(|| {
+        let mut m = layer(vec![HashEnum::B(1)]);
+        let _ = m.merge_with(layer(vec![HashEnum::B(2)]), |_, _, _| HashEnum::A);
+    }).unwrap_err();
+    assert_eq!(mismatch.downcast_ref::<String>().unwrap(), "merge of key `B` cannot resolve to a value of key `A`");
+}
+
+#[test]
+pub fn map_diff() {
+    use serde::{Deserialize, Serialize};
+    use variant_map::common::VariantMap;
+    use variant_map::diff::{apply, diff, MapDiff};
+    use variant_map::keyset::KeySet;
+    use variant_map_derive::VariantStore;
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, VariantStore)]
+    #[VariantStore(datastruct = "BTreeMap")]
+    enum LayerEnum {
+        Timeout(u32),
+        Retries(u8),
+        Verbose,
+    }
+
+    let mut old = LayerEnum::make_map();
+    old.insert(LayerEnum::Timeout(30));
+    old.insert(LayerEnum::Retries(3));
+    let mut new = LayerEnum::make_map();
+    new.insert(LayerEnum::Retries(5));
+    new.insert(LayerEnum::Verbose);
+
+    let delta = diff(&old, &new);
+    assert_eq!(delta.added(), &[LayerEnum::Verbose]);
+    assert_eq!(delta.removed(), &KeySet::from_iter([LayerEnumKey::Timeout]));
+    assert_eq!(delta.changed(), &[LayerEnum::Retries(5)]);
+    assert!(diff(&new, &new).is_empty());
+
+    let json = serde_json::to_string(&delta).unwrap();
+    assert_eq!(json, r#"{"added":["Verbose"],"removed":["Timeout"],"changed":[{"Retries":5}]}"#);
+    let received: MapDiff<LayerEnum> = serde_json::from_str(&json).unwrap();
+    assert_eq!(received, delta);
+
+    apply(&mut old, &received);
+    assert_eq!(old.iter().collect::<Vec<_>>(), new.iter().collect::<Vec<_>>());
+    assert!(serde_json::from_str::<MapDiff<LayerEnum>>(r#"{"added":[],"removed":["Z"],"changed":[]}"#).is_err());
+}
+
+#[test]
+pub fn multimap() {
+    use serde::{Deserialize, Serialize};
+    use variant_map::multimap::{self, MultiMapValue};
+    use variant_map_derive::VariantStore;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize, VariantStore)]
+    #[VariantStore(datastruct = "MultiMap")]
+    enum EventEnum {
+        Click { x: u16, y: u16 },
+        Press(char),
+        Resize(u16, u16),
+    }
+
+    let mut log = EventEnum::make_multimap();
+    log.push(EventEnum::Press('a'));
+    log.push(EventEnum::Click { x: 1, y: 2 });
+    log.push(EventEnum::Press('b'));
+
+    assert_eq!(log.count(&EventEnumKey::Press), 2);
+    assert_eq!(log.count(&EventEnumKey::Resize), 0);
+    assert!(!log.contains_key(&EventEnumKey::Resize));
+    assert_eq!(log.len(), 3);
+    assert_eq!(log.iter_variant(&EventEnumKey::Press).collect::<Vec<_>>(), vec![&EventEnum::Press('a'), &EventEnum::Press('b')]);
+    assert_eq!(log.get(&EventEnumKey::Click), &[EventEnum::Click { x: 1, y: 2 }]);
+
+    for mut event in log.iter_variant_mut(&EventEnumKey::Click) {
+        if let EventEnum::Click { x, .. } = &mut *event {
+            *x += 1;
+        }
+    }
+    assert_eq!(log.get(&EventEnumKey::Click), &[EventEnum::Click { x: 2, y: 2 }]);
+
+    let json = serde_json::to_string(&log).unwrap();
+    assert_eq!(json, r#"[{"Click":{"x":2,"y":2}},{"Press":"a"},{"Press":"b"}]"#);
+    let deserialized: multimap::Map<EventEnumKey, EventEnum> = serde_json::from_str(r#"[{"Press":"a"},{"Resize":[80,24]},{"Press":"b"}]"#).unwrap();
+    assert_eq!(deserialized.iter_variant(&EventEnumKey::Press).collect::<Vec<_>>(), log.iter_variant(&EventEnumKey::Press).collect::<Vec<_>>());
+    assert_eq!(deserialized.count(&EventEnumKey::Resize), 1);
+
+    assert_eq!(log.drain(&EventEnumKey::Press).collect::<Vec<_>>(), vec![EventEnum::Press('a'), EventEnum::Press('b')]);
+    assert_eq!(log.count(&EventEnumKey::Press), 0);
+    assert_eq!(log.len(), 1);
+
+    // the enum still has a map holding one value per key
+    let mut last = EventEnum::make_map();
+    last.insert(EventEnum::Resize(80, 24));
+    last.insert(EventEnum::Resize(120, 40));
+    assert_eq!(last.len(), 1);
+}
+
+#[test]
+pub fn user_keys() {
+    use serde::{Deserialize, Serialize};
+    use variant_map::common::{MapValue, VariantKey};
+    use variant_map::object::Object;
+    use variant_map_derive::VariantStore;
+
+    // stands for keys defined in another crate, implementing the key traits themselves
+    mod shared {
+        use variant_map::common::VariantKey;
+        use variant_map::hashmap::HashKey;
+
+        #[derive(Debug, PartialEq, Eq, Hash)]
+        pub enum Kind {
+            Text,
+            Number,
+            Flag,
+        }
+
+        impl VariantKey for Kind {
+            const COUNT: usize = 3;
+            const ALL: &'static [Self] = &[Kind::Text, Kind::Number, Kind::Flag];
+
+            fn name(&self) -> &'static str {
+                match self {
+                    Kind::Text => "text",
+                    Kind::Number => "number",
+                    Kind::Flag => "flag",
+                }
+            }
+
+            fn index(&self) -> usize {
+                match self {
+                    Kind::Text => 0,
+                    Kind::Number => 1,
+                    Kind::Flag => 2,
+                }
+            }
+
+            fn from_index(index: usize) -> Option<Self> {
+                match index {
+                    0 => Some(Kind::Text),
+                    1 => Some(Kind::Number),
+                    2 => Some(Kind::Flag),
+                    _ => None,
+                }
+            }
+        }
+
+        impl HashKey for Kind {}
+    }
+    use shared::Kind;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize, VariantStore)]
+    #[VariantStore(keys(use = "shared::Kind"))]
+    enum SharedKeyEnum {
+        #[key(Kind::Text)]
+        Label(String),
+        #[key(Kind::Number)]
+        Count(u32),
+        #[key(Kind::Flag)]
+        Enabled,
+    }
+
+    // the variants are declared in another order than their keys
+    #[derive(Debug, PartialEq, Serialize, Deserialize, VariantStore)]
+    #[VariantStore(keys(use = "shared::Kind"))]
+    enum SwappedEnum {
+        #[key(Kind::Number)]
+        Count(u32),
+        #[key(Kind::Text)]
+        Size(u32),
+        #[key(Kind::Flag)]
+        Enabled,
+    }
+
+    // declared in another order than the variants using them
+    #[derive(Debug, PartialEq, Eq)]
+    enum Level {
+        Low,
+        High,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize, VariantStore)]
+    #[VariantStore(keys(use = "Level", implement), datastruct = "ArrayMap", serde_format = "object")]
+    enum LevelEnum {
+        #[key(Level::High)]
+        #[key_name(serde = "loud")]
+        Loud(u8),
+        #[key(Level::Low)]
+        Quiet(u8),
+    }
+
+    assert_eq!(SharedKeyEnum::Count(1).to_key(), Kind::Number);
+    assert_eq!(SharedKeyEnum::Enabled.to_key(), Kind::Flag);
+
+    let mut map = SharedKeyEnum::make_map();
+    map.insert(SharedKeyEnum::Label("a".to_string()));
+    map.insert(SharedKeyEnum::Count(1));
+    assert_eq!(map.get(&Kind::Number), Some(&SharedKeyEnum::Count(1)));
+    assert_eq!(map.get(&Kind::Flag), None);
+    assert_eq!(map.get_count(), Some(&1));
+
+    let json = serde_json::to_string(&map).unwrap();
+    let deserialized: <SharedKeyEnum as MapValue>::Map = serde_json::from_str(&json).unwrap();
+    assert_eq!(deserialized.get(&Kind::Text), Some(&SharedKeyEnum::Label("a".to_string())));
+
+    // the object adapter reads the payload of `number` as the variant at index 1, which has another key
+    let mut swapped = Object(SwappedEnum::make_map());
+    swapped.insert(SwappedEnum::Count(5));
+    let json = serde_json::to_string(&swapped).unwrap();
+    assert_eq!(json, r#"{"number":5}"#);
+    assert!(serde_json::from_str::<Object<<SwappedEnum as MapValue>::Map>>(&json).is_err());
+
+    // the keys implemented by the derive follow the order of the variants
+    assert_eq!(<Level as VariantKey>::ALL, &[Level::High, Level::Low]);
+    assert_eq!(Level::Low.index(), 1);
+    assert_eq!(Level::High.name(), "loud");
+    assert_eq!(Level::Low.name(), "Low");
+
+    let mut levels = LevelEnum::make_map();
+    levels.insert(LevelEnum::Quiet(1));
+    levels.insert(LevelEnum::Loud(9));
+    assert_eq!(levels.get(&Level::Low), Some(&LevelEnum::Quiet(1)));
+
+    // so the object format reads each payload back as the variant of its key
+    let json = serde_json::to_string(&levels).unwrap();
+    assert_eq!(json, r#"{"loud":9,"Low":1}"#);
+    let deserialized: <LevelEnum as MapValue>::Map = serde_json::from_str(&json).unwrap();
+    assert_eq!(deserialized.get(&Level::Low), Some(&LevelEnum::Quiet(1)));
+    assert_eq!(deserialized.get(&Level::High), Some(&LevelEnum::Loud(9)));
+}
+
+#[test]
+pub fn key_fields() {
+    use serde::{Deserialize, Serialize};
+    use variant_map::common::MapValue;
+    use variant_map_derive::VariantStore;
+
+    #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+    struct SensorId(u8);
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize, VariantStore)]
+    #[VariantStore(datastruct = "HashMap")]
+    enum ReadingEnum {
+        #[key(by = 0)]
+        Sensor(SensorId, f32),
+        Offline,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize, VariantStore)]
+    #[VariantStore(datastruct = "BTreeMap", keys(derive(Serialize)))]
+    enum SessionEnum {
+        #[key(by = user)]
+        #[key_name(code = "User", serde = "user")]
+        Login { since: u64, user: String },
+        Anonymous(u64),
+    }
+
+    assert_eq!(ReadingEnum::Sensor(SensorId(3), 0.5).to_key(), ReadingEnumKey::Sensor(SensorId(3)));
+    assert_eq!(ReadingEnum::Offline.to_key(), ReadingEnumKey::Offline);
+
+    let mut readings = ReadingEnum::make_map();
+    readings.insert(ReadingEnum::Sensor(SensorId(1), 0.5));
+    readings.insert(ReadingEnum::Sensor(SensorId(2), 1.5));
+    assert_eq!(readings.insert(ReadingEnum::Sensor(SensorId(1), 2.5)), Some(ReadingEnum::Sensor(SensorId(1), 0.5)));
+    readings.insert(ReadingEnum::Offline);
+    assert_eq!(readings.len(), 3);
+    assert_eq!(readings.get(&ReadingEnumKey::Sensor(SensorId(2))), Some(&ReadingEnum::Sensor(SensorId(2), 1.5)));
+    assert_eq!(readings.get(&ReadingEnumKey::Sensor(SensorId(9))), None);
+
+    let json = serde_json::to_string(&readings).unwrap();
+    let deserialized: <ReadingEnum as MapValue>::Map = serde_json::from_str(&json).unwrap();
+    assert_eq!(deserialized.get(&ReadingEnumKey::Sensor(SensorId(1))), Some(&ReadingEnum::Sensor(SensorId(1), 2.5)));
+    assert_eq!(deserialized.len(), 3);
+
+    // the field carried by the key cannot be changed through the map
+    let result = std::panic::catch_unwind(move || {
+        let mut readings = readings;
+        let mut reading = readings.get_mut(&ReadingEnumKey::Sensor(SensorId(2))).unwrap();
+        *reading = ReadingEnum::Sensor(SensorId(5), 1.5);
+    });
+    assert!(result.is_err());
+
+    let mut sessions = SessionEnum::make_map();
+    sessions.insert(SessionEnum::Login { since: 2, user: "b".to_string() });
+    sessions.insert(SessionEnum::Login { since: 1, user: "a".to_string() });
+    sessions.insert(SessionEnum::Anonymous(3));
+    assert_eq!(sessions.remove(&SessionEnumKey::User("b".to_string())), Some(SessionEnum::Login { since: 2, user: "b".to_string() }));
+    assert_eq!(sessions.len(), 2);
+    assert_eq!(serde_json::to_string(&SessionEnumKey::User("a".to_string())).unwrap(), r#"{"user":"a"}"#);
+}
+
+#[test]
+pub fn skipped_variants() {
+    use serde::{Deserialize, Serialize};
+    use variant_map::common::{MapValue, Skipped, VariantMap};
+    use variant_map::diff::{apply, MapDiff};
+    use variant_map::multimap::{self, MultiMapValue};
+    use variant_map_derive::VariantStore;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize, VariantStore)]
+    #[VariantStore(datastruct = "ArrayMap")]
+    enum FrameEnum {
+        Ping,
+        Data(Vec<u8>),
+        #[key_name(skip)]
+        Unknown(String),
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize, VariantStore)]
+    #[VariantStore(datastruct = "HashMap", skipped = "ignore")]
+    enum LenientFrameEnum {
+        Ping,
+        #[key_name(skip)]
+        __NonExhaustive,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, VariantStore)]
+    #[VariantStore(datastruct = "BTreeMap", total)]
+    enum StageEnum {
+        Build,
+        Deploy(u8),
+        #[variant_store(skip)]
+        Aborted,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize, VariantStore)]
+    #[VariantStore(datastruct = "MultiMap")]
+    enum JournalEnum {
+        Line(String),
+        #[variant_store(skip)]
+        Eof,
+    }
+
+    assert_eq!(FrameEnumKey::COUNT, 2);
+    assert_eq!(FrameEnum::Ping.try_to_key(), Ok(FrameEnumKey::Ping));
+    assert_eq!(FrameEnum::Unknown("?".to_string()).try_to_key().unwrap_err().variant, "Unknown");
+
+    let mut frames = FrameEnum::make_map();
+    assert_eq!(frames.try_insert(FrameEnum::Data(vec![1])), Ok(None));
+    assert_eq!(frames.try_insert(FrameEnum::Data(vec![2])), Ok(Some(FrameEnum::Data(vec![1]))));
+    assert_eq!(frames.try_insert(FrameEnum::Unknown("?".to_string())), Err(FrameEnum::Unknown("?".to_string())));
+    assert_eq!(frames.insert(FrameEnum::Unknown("?".to_string())), Some(FrameEnum::Unknown("?".to_string())));
+    assert_eq!(VariantMap::try_insert(&mut frames, FrameEnum::Unknown("!".to_string())), Err(FrameEnum::Unknown("!".to_string())));
+    assert_eq!(frames.entry_for(&FrameEnum::Unknown("?".to_string())).err(), Some(Skipped { variant: "Unknown" }));
+    assert_eq!(frames.get_or_insert(FrameEnum::Unknown("?".to_string())).unwrap_err(), FrameEnum::Unknown("?".to_string()));
+    assert_eq!(frames.len(), 1);
+
+    let error = serde_json::from_str::<<FrameEnum as MapValue>::Map>(r#"["Ping",{"Unknown":"?"}]"#).unwrap_err();
+    assert!(error.to_string().contains("variant `Unknown` is skipped"));
+
+    let lenient: <LenientFrameEnum as MapValue>::Map = serde_json::from_str(r#"["Ping","__NonExhaustive"]"#).unwrap();
+    assert_eq!(lenient.len(), 1);
+    assert_eq!(lenient.get(&LenientFrameEnumKey::Ping), Some(&LenientFrameEnum::Ping));
+
+    // collecting and extending leave the skipped variants out
+    assert_eq!(StageEnumKey::ALL, [StageEnumKey::Build, StageEnumKey::Deploy]);
+    let mut stages: <StageEnum as MapValue>::Map = [StageEnum::Build, StageEnum::Aborted].into_iter().collect();
+    stages.extend([StageEnum::Aborted, StageEnum::Deploy(1)]);
+    assert_eq!(stages.len(), 2);
+    assert_eq!(stages.validate(), Ok(()));
+
+    // entries and merges give the skipped variants back instead of panicking
+    assert_eq!(stages.entry(StageEnumKey::Build).or_insert(StageEnum::Aborted).map(|stage| (*stage).clone()), Ok(StageEnum::Build));
+    assert_eq!(StageEnum::make_map().entry(StageEnumKey::Build).or_insert(StageEnum::Aborted).err(), Some(StageEnum::Aborted));
+    assert_eq!(StageEnum::make_map().get_or_insert_with(StageEnumKey::Deploy, || StageEnum::Aborted).err(), Some(StageEnum::Aborted));
+
+    let mut other = StageEnum::make_map();
+    other.insert(StageEnum::Build);
+    other.insert(StageEnum::Deploy(2));
+    let merged = stages.merge_with(other, |key, _left, right| match key {
+        StageEnumKey::Build => StageEnum::Aborted,
+        StageEnumKey::Deploy => right,
+    });
+    assert_eq!(merged, Err(Skipped { variant: "Aborted" }));
+    assert_eq!(stages.iter().collect::<Vec<_>>(), vec![&StageEnum::Deploy(2)]);
+
+    let received: MapDiff<StageEnum> = serde_json::from_str(r#"{"added":["Aborted","Build"],"removed":[],"changed":[{"Deploy":3}]}"#).unwrap();
+    apply(&mut stages, &received);
+    assert_eq!(stages.iter().collect::<Vec<_>>(), vec![&StageEnum::Build, &StageEnum::Deploy(3)]);
+
+    let mismatch = std::panic::catch_unwind(|| {
+        let mut m = StageEnum::make_map();
+        let _ = m.entry(StageEnumKey::Build).or_insert(StageEnum::Deploy(1));
+    }).unwrap_err();
+    assert_eq!(mismatch.downcast_ref::<String>().unwrap(), "entry of key `Build` cannot hold a value of key `Deploy`");
+
+    let mut builder = StageEnumTotalMap::builder();
+    assert_eq!(builder.try_insert(StageEnum::Aborted), Err(StageEnum::Aborted));
+    assert_eq!(builder.insert(StageEnum::Aborted), Some(StageEnum::Aborted));
+    assert_eq!(builder.try_insert(StageEnum::Build), Ok(None));
+    let mut total = builder.with(StageEnum::Aborted).with(StageEnum::Deploy(3)).build().unwrap();
+    assert_eq!(total.try_replace(StageEnum::Aborted), Err(StageEnum::Aborted));
+    assert_eq!(total.replace(StageEnum::Aborted), StageEnum::Aborted);
+    assert_eq!(total.try_replace(StageEnum::Deploy(4)), Ok(StageEnum::Deploy(3)));
+
+    let mut journal: multimap::Map<JournalEnumKey, JournalEnum> = [JournalEnum::Line("a".to_string()), JournalEnum::Eof].into_iter().collect();
+    assert_eq!(journal.push(JournalEnum::Eof), Some(JournalEnum::Eof));
+    assert_eq!(journal.push(JournalEnum::Line("b".to_string())), None);
+    assert_eq!(journal.len(), 2);
+    assert_eq!(JournalEnum::make_multimap().len(), 0);
+}
+
+#[test]
+pub fn shared_keys() {
+    use serde::{Deserialize, Serialize};
+    use variant_map::common::{MapValue, VariantKey};
+    use variant_map_derive::VariantStore;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize, VariantStore)]
+    #[VariantStore(datastruct = "ArrayMap", total)]
+    enum ConnectionEnum {
+        #[key_name(code = "Network")]
+        Wifi { ssid: String },
+        #[key_name(code = "Network")]
+        Ethernet,
+        Proxy(u16),
+    }
+
+    #[derive(Debug, PartialEq, Eq)]
+    enum Slot {
+        Main,
+        Spare,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize, VariantStore)]
+    #[VariantStore(keys(use = "Slot", implement), datastruct = "ArrayMap")]
+    enum DiskEnum {
+        #[key(Slot::Main)]
+        Ssd(u32),
+        #[key(Slot::Main)]
+        Hdd(u32),
+        #[key(Slot::Spare)]
+        Spare(u32),
+    }
+
+    assert_eq!(ConnectionEnumKey::ALL, [ConnectionEnumKey::Network, ConnectionEnumKey::Proxy]);
+    assert_eq!(ConnectionEnum::Ethernet.to_key(), ConnectionEnumKey::Network);
+
+    let mut m = ConnectionEnum::make_map();
+    m.insert(ConnectionEnum::Wifi { ssid: "home".to_string() });
+    assert_eq!(m.insert(ConnectionEnum::Ethernet), Some(ConnectionEnum::Wifi { ssid: "home".to_string() }));
+    m.insert(ConnectionEnum::Proxy(8080));
+    assert_eq!(m.len(), 2);
+    assert_eq!(m.get(&ConnectionEnumKey::Network), Some(&ConnectionEnum::Ethernet));
+
+    // the accessors of a variant leave the other variants of its key in place, `set` gives them back
+    assert_eq!(m.get_wifi(), None);
+    assert_eq!(m.take_wifi(), None);
+    assert_eq!(m.get(&ConnectionEnumKey::Network), Some(&ConnectionEnum::Ethernet));
+    assert_eq!(m.set_wifi("office".to_string()), Some(ConnectionEnum::Ethernet));
+    assert_eq!(m.set_wifi("cafe".to_string()), Some(ConnectionEnum::Wifi { ssid: "office".to_string() }));
+    assert_eq!(m.take_wifi(), Some("cafe".to_string()));
+    assert_eq!(m.set_proxy(3128), Some(8080));
+
+    m.insert(ConnectionEnum::Ethernet);
+    let json = serde_json::to_string(&m).unwrap();
+    let deserialized: <ConnectionEnum as MapValue>::Map = serde_json::from_str(&json).unwrap();
+    assert_eq!(deserialized.get(&ConnectionEnumKey::Network), Some(&ConnectionEnum::Ethernet));
+
+    let mut total = ConnectionEnumTotalMap::builder()
+        .with(ConnectionEnum::Ethernet)
+        .with(ConnectionEnum::Proxy(3128))
+        .build()
+        .unwrap();
+    assert_eq!(total.replace(ConnectionEnum::Wifi { ssid: "cafe".to_string() }), ConnectionEnum::Ethernet);
+    assert_eq!(total.get_proxy(), &3128);
+    assert_eq!(total.iter().count(), 2);
+
+    // variants may share a key of an existing type too
+    assert_eq!(<Slot as VariantKey>::ALL, &[Slot::Main, Slot::Spare]);
+    let mut disks = DiskEnum::make_map();
+    disks.insert(DiskEnum::Ssd(512));
+    disks.insert(DiskEnum::Spare(1024));
+    assert_eq!(disks.insert(DiskEnum::Hdd(2048)), Some(DiskEnum::Ssd(512)));
+    assert_eq!(disks.get(&Slot::Main), Some(&DiskEnum::Hdd(2048)));
+    assert_eq!(disks.len(), 2);
+}
+
+#[test]
+pub fn renamed_keys() {
+    use serde::{Deserialize, Serialize};
+    use serde_json::{json, Value};
+    use variant_map::common::{MapValue, VariantKey};
+    use variant_map_derive::VariantStore;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize, VariantStore)]
+    #[VariantStore(datastruct = "HashMap", serde_format = "object", keys(rename_all = "snake_case", derive(Serialize)))]
+    enum RenamedEnum {
+        RetryCount(u8),
+        HTTPProxy(String),
+        #[key_name(serde = "tls")]
+        UseTls,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize, VariantStore)]
+    #[serde(rename_all = "snake_case")]
+    #[VariantStore(datastruct = "BTreeMap", serde_format = "object", keys(rename_all = "snake_case"))]
+    enum AcronymEnum {
+        HTTPServer(u16),
+        Tls,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize, VariantStore)]
+    #[serde(rename_all = "camelCase")]
+    #[VariantStore(datastruct = "BTreeMap", keys(rename_all = "camelCase"))]
+    enum CamelAcronymEnum {
+        HTTPServer(u16),
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize, VariantStore)]
+    #[VariantStore(datastruct = "ArrayMap", keys(rename_all(serde = "kebab-case", code = "SCREAMING_SNAKE_CASE")))]
+    enum SettingEnum {
+        DarkMode(bool),
+        #[key_name(code = "Size")]
+        FontSize(u8),
+    }
+
+    assert_eq!(RenamedEnumKey::HTTPProxy.name(), "h_t_t_p_proxy");
+    assert_eq!(serde_json::to_string(&RenamedEnumKey::RetryCount).unwrap(), r#""retry_count""#);
+
+    let mut m = RenamedEnum::make_map();
+    m.insert(RenamedEnum::RetryCount(3));
+    m.insert(RenamedEnum::UseTls);
+    let json: Value = serde_json::to_value(&m).unwrap();
+    assert_eq!(json["retry_count"], 3);
+    assert_eq!(json["tls"], Value::Null);
+
+    let deserialized: <RenamedEnum as MapValue>::Map = serde_json::from_str(r#"{"h_t_t_p_proxy":"localhost"}"#).unwrap();
+    assert_eq!(deserialized.get(&RenamedEnumKey::HTTPProxy), Some(&RenamedEnum::HTTPProxy("localhost".to_string())));
+
+    // serde starts a word at each uppercase letter of an acronym, as the names of the accessors do
+    assert_eq!(serde_json::to_value(AcronymEnum::HTTPServer(80)).unwrap(), json!({"h_t_t_p_server": 80}));
+    assert_eq!(AcronymEnumKey::HTTPServer.name(), "h_t_t_p_server");
+    assert_eq!(AcronymEnumKey::Tls.name(), "tls");
+    assert_eq!(serde_json::to_value(CamelAcronymEnum::HTTPServer(80)).unwrap(), json!({"hTTPServer": 80}));
+    assert_eq!(CamelAcronymEnumKey::HTTPServer.name(), "hTTPServer");
+
+    // the object format finds the variants by the serde names of the keys
+    let mut m = AcronymEnum::make_map();
+    m.insert(AcronymEnum::HTTPServer(80));
+    m.insert(AcronymEnum::Tls);
+    assert_eq!(m.get_h_t_t_p_server(), Some(&80));
+    let json = serde_json::to_value(&m).unwrap();
+    assert_eq!(json, json!({"h_t_t_p_server": 80, "tls": null}));
+    let deserialized: <AcronymEnum as MapValue>::Map = serde_json::from_value(json).unwrap();
+    assert_eq!(deserialized.get(&AcronymEnumKey::HTTPServer), Some(&AcronymEnum::HTTPServer(80)));
+    assert_eq!(deserialized.get(&AcronymEnumKey::Tls), Some(&AcronymEnum::Tls));
+
+    // the code of a key_name takes precedence over the case, which then converts its serde name
+    assert_eq!(SettingEnumKey::DARK_MODE.name(), "dark-mode");
+    assert_eq!(SettingEnumKey::Size.name(), "size");
+    assert_eq!(SettingEnum::FontSize(12).to_key(), SettingEnumKey::Size);
+}
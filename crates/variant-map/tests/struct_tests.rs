@@ -72,7 +72,7 @@ mod user {
         }
     }
 
-    struct MyStructVisitor<T>(PhantomData<(T)>);
+    struct MyStructVisitor<T>(PhantomData<T>);
 
     impl<'de, T> Visitor<'de> for MyStructVisitor<T>
     where T: Deserialize<'de> {
@@ -106,7 +106,7 @@ mod user {
         where
             D: Deserializer<'de>,
         {
-            deserializer.deserialize_seq(MyStructVisitor(PhantomData::default()))
+            deserializer.deserialize_seq(MyStructVisitor(PhantomData))
         }
     }
 
@@ -122,10 +122,10 @@ mod user {
 
         pub fn insert(&mut self, value: MyEnum<T>) -> Option<MyEnum<T>> {
             match value {
-                MyEnum::A => mem::replace(&mut self.A, Some(value)),
-                MyEnum::B(_) => mem::replace(&mut self.B, Some(value)),
-                MyEnum::C => mem::replace(&mut self.C, Some(value)),
-                MyEnum::D(_) => mem::replace(&mut self.D, Some(value)),
+                MyEnum::A => self.A.replace(value),
+                MyEnum::B(_) => self.B.replace(value),
+                MyEnum::C => self.C.replace(value),
+                MyEnum::D(_) => self.D.replace(value),
             }
         }
 
@@ -191,6 +191,65 @@ mod user {
     }
 }
 
+/// [VariantMap] for the hand-written [user::MyStruct], built on its public methods only
+mod user_variant_map {
+    use super::user::{MyEnum, MyEnumKey, MyStruct};
+    use std::array;
+    use std::iter::Flatten;
+    use variant_map::common::VariantMap;
+
+    impl<T> VariantMap for MyStruct<T> {
+        type Key = MyEnumKey;
+        type Value = MyEnum<T>;
+        type Ref<'a> = &'a MyEnum<T> where T: 'a;
+        type Mut<'a> = &'a mut MyEnum<T> where T: 'a;
+        type Iter<'a> = Flatten<array::IntoIter<Option<&'a MyEnum<T>>, 4>> where T: 'a;
+        type KeyedIter<'a> = Flatten<array::IntoIter<Option<(&'a MyEnumKey, &'a MyEnum<T>)>, 4>> where T: 'a;
+
+        fn get(&self, key: &MyEnumKey) -> Option<&MyEnum<T>> {
+            MyStruct::get(self, key).as_ref()
+        }
+
+        fn get_mut(&mut self, key: &MyEnumKey) -> Option<&mut MyEnum<T>> {
+            MyStruct::get_mut(self, key).as_mut()
+        }
+
+        fn insert(&mut self, value: MyEnum<T>) -> Option<MyEnum<T>> {
+            MyStruct::insert(self, value)
+        }
+
+        fn remove(&mut self, key: &MyEnumKey) -> Option<MyEnum<T>> {
+            MyStruct::remove(self, key)
+        }
+
+        fn len(&self) -> usize {
+            self.iter().count()
+        }
+
+        fn iter(&self) -> Self::Iter<'_> {
+            [
+                self[MyEnumKey::A].as_ref(),
+                self[MyEnumKey::B].as_ref(),
+                self[MyEnumKey::C].as_ref(),
+                self[MyEnumKey::D].as_ref(),
+            ].into_iter().flatten()
+        }
+
+        fn iter_keyed(&self) -> Self::KeyedIter<'_> {
+            [
+                self[MyEnumKey::A].as_ref().map(|value| (&MyEnumKey::A, value)),
+                self[MyEnumKey::B].as_ref().map(|value| (&MyEnumKey::B, value)),
+                self[MyEnumKey::C].as_ref().map(|value| (&MyEnumKey::C, value)),
+                self[MyEnumKey::D].as_ref().map(|value| (&MyEnumKey::D, value)),
+            ].into_iter().flatten()
+        }
+
+        fn clear(&mut self) {
+            *self = MyStruct::default();
+        }
+    }
+}
+
 #[test]
 pub fn ensure_correct_key() {
     let value: MyEnum<i64> = MyEnum::A;
@@ -287,3 +346,683 @@ pub fn serialize() {
         println!("expect~ {:#?}", m2_str);
     }
 }
+
+#[test]
+pub fn struct_variants() {
+    use serde::{Deserialize, Serialize};
+    use variant_map_derive::VariantStore;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize, VariantStore)]
+    #[VariantStore(datastruct = "StructMap", keys(derive(PartialEq)))]
+    #[VariantStruct(features(index, serialize, deserialize))]
+    enum StructEnum {
+        A,
+        B(i32),
+        Config { port: u16, host: String },
+    }
+
+    let config = StructEnum::Config { port: 8080, host: "localhost".to_string() };
+    assert_eq!(config.to_key(), StructEnumKey::Config);
+
+    let m_str = "[\"A\",{\"B\":1},{\"Config\":{\"port\":8080,\"host\":\"localhost\"}}]";
+    let m: <StructEnum as MapValue>::Map = serde_json::from_str(m_str).unwrap();
+    assert_eq!(m[StructEnumKey::B], Some(StructEnum::B(1)));
+    assert_eq!(
+        m[StructEnumKey::Config],
+        Some(StructEnum::Config { port: 8080, host: "localhost".to_string() })
+    );
+    assert_eq!(serde_json::to_string(&m).unwrap(), m_str);
+}
+
+#[test]
+pub fn variant_map_trait() {
+    use serde::{Deserialize, Serialize};
+    use variant_map::common::VariantMap;
+    use variant_map_derive::VariantStore;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize, VariantStore)]
+    #[VariantStore(datastruct = "StructMap", keys(derive(PartialEq)))]
+    enum StructEnum {
+        A,
+        B(i32),
+        Config { port: u16, host: String },
+    }
+
+    let mut m = StructEnum::make_map();
+    assert!(m.is_empty());
+
+    m.insert(StructEnum::B(1));
+    m.insert(StructEnum::A);
+    assert_eq!(m.len(), 2);
+    assert!(m.contains_key(&StructEnumKey::B));
+    assert!(!m.contains_key(&StructEnumKey::Config));
+    assert_eq!(m.iter().collect::<Vec<_>>(), vec![&StructEnum::A, &StructEnum::B(1)]);
+
+    if let Some(StructEnum::B(b)) = m.get_mut(&StructEnumKey::B).as_deref_mut() {
+        *b = 2;
+    }
+    assert_eq!(m.get(&StructEnumKey::B), Some(&StructEnum::B(2)));
+
+    m.clear();
+    assert_eq!(m.len(), 0);
+    assert_eq!(m.get(&StructEnumKey::A), None);
+
+    let mut hand_written: user::MyStruct<i64> = MyEnum::make_map();
+    hand_written.insert(MyEnum::B(3));
+    assert_eq!(VariantMap::len(&hand_written), 1);
+    assert_eq!(VariantMap::get(&hand_written, &MyEnumKey::B), Some(&MyEnum::B(3)));
+}
+
+#[test]
+pub fn typed_accessors() {
+    use serde::{Deserialize, Serialize};
+    use variant_map::common::VariantMap;
+    use variant_map_derive::VariantStore;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize, VariantStore)]
+    #[VariantStore(datastruct = "StructMap")]
+    enum StructEnum {
+        A,
+        B(i32),
+        Config { port: u16, host: String },
+        Pair(i32, String),
+    }
+
+    let mut m = StructEnum::make_map();
+    assert_eq!(m.set_b(1), None);
+    assert_eq!(m.get_b(), Some(&1));
+    *m.get_b_mut().unwrap() = 2;
+    assert_eq!(m.take_b(), Some(2));
+    assert!(!VariantMap::contains_key(&m, &StructEnumKey::B));
+
+    m.set_pair(1, "one".to_string());
+    assert_eq!(m.get_pair(), Some((&1, &"one".to_string())));
+    assert_eq!(m.set_pair(2, "two".to_string()), Some((1, "one".to_string())));
+
+    m.set_config(8080, "localhost".to_string());
+    assert_eq!(m.get_config(), Some((&8080, &"localhost".to_string())));
+    assert_eq!(m.take_a(), None);
+}
+
+#[test]
+pub fn payload_storage() {
+    use serde::{Deserialize, Serialize};
+    use variant_map::common::VariantMap;
+    use variant_map_derive::VariantStore;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize, VariantStore)]
+    #[VariantStore(datastruct = "StructMap", keys(derive(PartialEq)))]
+    #[VariantStruct(storage = "payload", features(serialize, deserialize))]
+    enum PayloadEnum {
+        A,
+        B(i32),
+        Config { port: u16, host: String },
+    }
+
+    let mut m = PayloadEnum::make_map();
+    assert!(m.is_empty());
+
+    assert_eq!(m.insert(PayloadEnum::A), None);
+    assert_eq!(m.insert(PayloadEnum::B(1)), None);
+    assert_eq!(m.insert(PayloadEnum::B(2)), Some(PayloadEnum::B(1)));
+    assert_eq!(m.B, Some(2));
+    assert_eq!(m.len(), 2);
+    assert!(m.contains_key(&PayloadEnumKey::A));
+    assert_eq!(m.get(&PayloadEnumKey::B).as_deref(), Some(&PayloadEnum::B(2)));
+    assert_eq!(m.get(&PayloadEnumKey::Config).as_deref(), None);
+
+    m.set_config(8080, "localhost".to_string());
+    assert_eq!(m.get_config(), Some((&8080, &"localhost".to_string())));
+    *m.get_b_mut().unwrap() = 3;
+    if let Some(mut value) = m.get_mut(&PayloadEnumKey::B) {
+        *value = PayloadEnum::B(4);
+    }
+    assert_eq!(m.take_b(), Some(4));
+
+    let rebuilt = m.get(&PayloadEnumKey::Config).unwrap();
+    assert_eq!(rebuilt.key(), &PayloadEnumKey::Config);
+    assert_eq!(rebuilt.into_inner(), PayloadEnum::Config { port: 8080, host: "localhost".to_string() });
+    let keys: Vec<PayloadEnumKey> = m.iter().map(|value| value.to_key()).collect();
+    assert_eq!(keys, vec![PayloadEnumKey::A, PayloadEnumKey::Config]);
+
+    let m_str = serde_json::to_string(&m).unwrap();
+    assert_eq!(m_str, "[\"A\",{\"Config\":{\"port\":8080,\"host\":\"localhost\"}}]");
+    let mut m2: <PayloadEnum as MapValue>::Map = serde_json::from_str(&m_str).unwrap();
+    assert_eq!(
+        m2.remove(&PayloadEnumKey::Config),
+        Some(PayloadEnum::Config { port: 8080, host: "localhost".to_string() })
+    );
+
+    m2.clear();
+    assert_eq!(m2.len(), 0);
+}
+
+#[test]
+pub fn object_format() {
+    use serde::{Deserialize, Serialize};
+    use variant_map::common::VariantMap;
+    use variant_map_derive::VariantStore;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize, VariantStore)]
+    #[VariantStore(datastruct = "StructMap", serde_format = "object")]
+    #[VariantStruct(features(serialize, deserialize))]
+    enum ObjectEnum {
+        A,
+        #[key_name(serde = "timeout")]
+        Timeout(u32),
+        Config { port: u16, host: String },
+    }
+
+    let mut m = ObjectEnum::make_map();
+    VariantMap::insert(&mut m, ObjectEnum::Timeout(30));
+    VariantMap::insert(&mut m, ObjectEnum::Config { port: 8080, host: "localhost".to_string() });
+
+    let m_str = serde_json::to_string(&m).unwrap();
+    assert_eq!(m_str, "{\"timeout\":30,\"Config\":{\"port\":8080,\"host\":\"localhost\"}}");
+
+    let m2: ObjectEnumStructMap = serde_json::from_str("{\"A\":null,\"timeout\":10,\"timeout\":30}").unwrap();
+    assert_eq!(VariantMap::len(&m2), 2);
+    assert_eq!(VariantMap::get(&m2, &ObjectEnumKey::A), Some(&ObjectEnum::A));
+    assert_eq!(VariantMap::get(&m2, &ObjectEnumKey::Timeout), Some(&ObjectEnum::Timeout(30)));
+}
+
+#[test]
+pub fn strict_deserialization() {
+    use serde::{Deserialize, Serialize};
+    use variant_map::common::VariantMap;
+    use variant_map_derive::VariantStore;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize, VariantStore)]
+    #[VariantStore(datastruct = "StructMap", strict(require(Timeout)))]
+    #[VariantStruct(features(serialize, deserialize))]
+    enum StrictEnum {
+        A,
+        Timeout(u32),
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize, VariantStore)]
+    #[VariantStore(datastruct = "StructMap", serde_format = "object", strict)]
+    #[VariantStruct(storage = "payload", features(serialize, deserialize))]
+    enum StrictPayloadEnum {
+        A,
+        Timeout(u32),
+    }
+
+    let m: StrictEnumStructMap = serde_json::from_str("[\"A\",{\"Timeout\":30}]").unwrap();
+    assert_eq!(VariantMap::get(&m, &StrictEnumKey::Timeout), Some(&StrictEnum::Timeout(30)));
+
+    let error = serde_json::from_str::<StrictEnumStructMap>("[{\"Timeout\":30},{\"Timeout\":10}]").unwrap_err();
+    assert!(error.to_string().starts_with("duplicate key `Timeout`"), "{error}");
+
+    let error = serde_json::from_str::<StrictEnumStructMap>("[\"A\"]").unwrap_err();
+    assert!(error.to_string().starts_with("missing required key `Timeout`"), "{error}");
+
+    let m: StrictPayloadEnumStructMap = serde_json::from_str("{\"A\":null,\"Timeout\":30}").unwrap();
+    assert_eq!(VariantMap::len(&m), 2);
+    assert_eq!(VariantMap::get(&m, &StrictPayloadEnumKey::Timeout).as_deref(), Some(&StrictPayloadEnum::Timeout(30)));
+    let error = serde_json::from_str::<StrictPayloadEnumStructMap>("{\"Timeout\":30,\"Timeout\":10}").unwrap_err();
+    assert!(error.to_string().starts_with("duplicate key `Timeout`"), "{error}");
+}
+
+#[test]
+pub fn total_map() {
+    use variant_map::common::VariantMap;
+    use variant_map::keyset::KeySet;
+    use variant_map_derive::VariantStore;
+
+    #[derive(Debug, Clone, PartialEq, VariantStore)]
+    #[VariantStore(datastruct = "StructMap", keys(derive(PartialEq)), total(name = "TotalPayloadMap", derive(Debug)))]
+    #[VariantStruct(storage = "payload")]
+    enum TotalPayloadEnum {
+        A,
+        Timeout(u32),
+    }
+
+    let mut builder = TotalPayloadMap::builder();
+    assert_eq!(builder.insert(TotalPayloadEnum::Timeout(10)), None);
+    assert_eq!(builder.insert(TotalPayloadEnum::Timeout(30)), Some(TotalPayloadEnum::Timeout(10)));
+    assert_eq!(builder.missing_keys(), KeySet::from_iter([TotalPayloadEnumKey::A]));
+
+    let total = builder.with(TotalPayloadEnum::A).build().unwrap();
+    assert_eq!(total.get_timeout(), &30);
+
+    let mut partial: TotalPayloadEnumStructMap = total.into();
+    assert_eq!(VariantMap::get(&partial, &TotalPayloadEnumKey::Timeout).as_deref(), Some(&TotalPayloadEnum::Timeout(30)));
+
+    VariantMap::remove(&mut partial, &TotalPayloadEnumKey::A);
+    let error = TotalPayloadMap::try_from(partial).unwrap_err();
+    assert_eq!(error.keys(), &KeySet::from_iter([TotalPayloadEnumKey::A]));
+}
+
+#[test]
+pub fn entry_api() {
+    use serde::{Deserialize, Serialize};
+    use variant_map::common::VariantMap;
+    use variant_map::entry::Entry;
+    use variant_map_derive::VariantStore;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize, VariantStore)]
+    #[VariantStore(datastruct = "StructMap", keys(derive(PartialEq)))]
+    enum StructEnum {
+        A,
+        B(i32),
+        Pair(i32, String),
+    }
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, VariantStore)]
+    #[VariantStore(datastruct = "StructMap", keys(derive(PartialEq)))]
+    #[VariantStruct(storage = "payload")]
+    enum PayloadEnum {
+        B(i32),
+    }
+
+    let mut m = StructEnum::make_map();
+
+    m.entry(StructEnumKey::B).or_insert(StructEnum::B(1)).unwrap();
+    m.entry_for(&StructEnum::B(0))
+        .unwrap()
+        .and_modify(|b| *b = StructEnum::B(2))
+        .or_default();
+    assert_eq!(m.get_b(), Some(&2));
+
+    assert_eq!(&*m.entry(StructEnumKey::Pair).or_default(), &StructEnum::Pair(0, String::new()));
+    match m.entry(StructEnumKey::Pair) {
+        Entry::Occupied(mut entry) => assert_eq!(entry.insert(StructEnum::Pair(1, "one".to_string())), Ok(StructEnum::Pair(0, String::new()))),
+        Entry::Vacant(_) => unreachable!(),
+    }
+    assert_eq!(m.get_pair(), Some((&1, &"one".to_string())));
+    assert!(!m.contains_key(&StructEnumKey::A));
+
+    let mismatch = std::panic::catch_unwind(|| {
+        let mut m = StructEnum::make_map();
+        let _ = m.entry(StructEnumKey::A).or_insert(StructEnum::B(1));
+    }).unwrap_err();
+    assert_eq!(mismatch.downcast_ref::<String>().unwrap(), "entry of key `A` cannot hold a value of key `B`");
+
+    // the payloads are modified through the guard storing them back
+    let mut m = PayloadEnum::make_map();
+    m.entry(PayloadEnumKey::B)
+        .and_modify(|_| unreachable!())
+        .or_default();
+    m.entry(PayloadEnumKey::B).and_modify(|b| *b = PayloadEnum::B(3));
+    assert_eq!(m.get_b(), Some(&3));
+}
+
+#[test]
+pub fn key_consistency() {
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+    use serde::{Deserialize, Serialize};
+    use variant_map::common::VariantMap;
+    use variant_map_derive::VariantStore;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize, VariantStore)]
+    #[VariantStore(datastruct = "StructMap", keys(derive(PartialEq)))]
+    enum StructEnum {
+        A,
+        B(i32),
+    }
+
+    let mut m = StructEnum::make_map();
+    m.insert(StructEnum::B(1));
+    if let Some(StructEnum::B(b)) = VariantMap::get_mut(&mut m, &StructEnumKey::B).as_deref_mut() {
+        *b = 2;
+    }
+    assert_eq!(m.get_b(), Some(&2));
+    assert_eq!(m.validate(), Ok(()));
+
+    assert!(catch_unwind(AssertUnwindSafe(|| {
+        *VariantMap::get_mut(&mut m, &StructEnumKey::B).unwrap() = StructEnum::A;
+    })).is_err());
+    let mismatch = m.validate().unwrap_err();
+    assert_eq!((mismatch.key, &mismatch.found), (&StructEnumKey::B, &StructEnumKey::A));
+}
+
+#[test]
+pub fn indexing() {
+    use serde::{Deserialize, Serialize};
+    use variant_map::common::VariantMap;
+    use variant_map_derive::VariantStore;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize, VariantStore)]
+    #[VariantStore(datastruct = "StructMap")]
+    #[VariantStruct(features(index))]
+    enum StructEnum {
+        A,
+        B(i32),
+    }
+
+    let mut m = StructEnum::make_map();
+    m.insert(StructEnum::B(1));
+    assert_eq!(m[&StructEnumKey::B], Some(StructEnum::B(1)));
+    assert_eq!(m[StructEnumKey::A], None);
+
+    assert_eq!(&*m.get_or_insert(StructEnum::A).unwrap(), &StructEnum::A);
+    assert_eq!(&*m.get_or_insert_with(StructEnumKey::B, || StructEnum::B(2)).unwrap(), &StructEnum::B(1));
+}
+
+#[test]
+pub fn key_set() {
+    use serde::{Deserialize, Serialize};
+    use variant_map::common::VariantMap;
+    use variant_map::keyset::KeySet;
+    use variant_map_derive::VariantStore;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize, VariantStore)]
+    #[VariantStore(datastruct = "StructMap")]
+    enum StructEnum {
+        A,
+        B(i32),
+        Pair(i32, String),
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize, VariantStore)]
+    #[VariantStore(datastruct = "StructMap")]
+    #[VariantStruct(storage = "payload")]
+    enum PayloadEnum {
+        A,
+        B(i32),
+        Config { port: u16, host: String },
+    }
+
+    let mut m = StructEnum::make_map();
+    m.insert(StructEnum::B(1));
+    m.insert(StructEnum::Pair(2, "two".to_string()));
+    assert_eq!(m.keys_set(), KeySet::from_iter([StructEnumKey::B, StructEnumKey::Pair]));
+
+    let mut m = PayloadEnum::make_map();
+    m.insert(PayloadEnum::A);
+    let keys = m.keys_set();
+    assert!(keys.contains(&PayloadEnumKey::A));
+    assert_eq!(keys.complement(), KeySet::from_iter([PayloadEnumKey::B, PayloadEnumKey::Config]));
+}
+
+#[test]
+pub fn merge() {
+    use serde::{Deserialize, Serialize};
+    use variant_map::common::VariantMap;
+    use variant_map::merge::{KeepLeft, KeepRight};
+    use variant_map_derive::VariantStore;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize, VariantStore)]
+    #[VariantStore(datastruct = "StructMap")]
+    enum StructEnum {
+        A,
+        B(i32),
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize, VariantStore)]
+    #[VariantStore(datastruct = "StructMap")]
+    #[VariantStruct(storage = "payload")]
+    enum PayloadEnum {
+        A,
+        B(i32),
+    }
+
+    let mut m = StructEnum::make_map();
+    m.insert(StructEnum::B(1));
+    let mut other = StructEnum::make_map();
+    other.insert(StructEnum::A);
+    other.insert(StructEnum::B(2));
+    m.merge(other, KeepLeft).unwrap();
+    assert_eq!(m.get_b(), Some(&1));
+    assert_eq!(m.get(&StructEnumKey::A), Some(&StructEnum::A));
+
+    let mut m = PayloadEnum::make_map();
+    m.insert(PayloadEnum::B(1));
+    let mut other = PayloadEnum::make_map();
+    other.insert(PayloadEnum::B(2));
+    m.merge_with(other, |_key, left, right| match (left, right) {
+        (PayloadEnum::B(l), PayloadEnum::B(r)) => PayloadEnum::B(l.max(r)),
+        (left, _) => left,
+    }).unwrap();
+    assert_eq!(m.get_b(), Some(&2));
+
+    let mut other = PayloadEnum::make_map();
+    other.insert(PayloadEnum::A);
+    other.insert(PayloadEnum::B(3));
+    m.merge(other, KeepRight).unwrap();
+    assert_eq!(m.len(), 2);
+    assert_eq!(m.get_b(), Some(&3));
+}
+
+#[test]
+pub fn map_diff() {
+    use serde::{Deserialize, Serialize};
+    use variant_map::common::VariantMap;
+    use variant_map::diff::{apply, diff};
+    use variant_map::keyset::KeySet;
+    use variant_map_derive::VariantStore;
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, VariantStore)]
+    #[VariantStore(datastruct = "StructMap")]
+    enum StructEnum {
+        A,
+        B(i32),
+        Pair(i32, String),
+    }
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, VariantStore)]
+    #[VariantStore(datastruct = "StructMap")]
+    #[VariantStruct(storage = "payload")]
+    enum PayloadEnum {
+        A,
+        B(i32),
+    }
+
+    let mut old = StructEnum::make_map();
+    old.insert(StructEnum::A);
+    old.insert(StructEnum::B(1));
+    let mut new = StructEnum::make_map();
+    new.insert(StructEnum::B(2));
+    new.insert(StructEnum::Pair(3, "three".to_string()));
+
+    let delta = diff(&old, &new);
+    assert_eq!(delta.added(), &[StructEnum::Pair(3, "three".to_string())]);
+    assert_eq!(delta.removed(), &KeySet::from_iter([StructEnumKey::A]));
+    assert_eq!(delta.changed(), &[StructEnum::B(2)]);
+
+    apply(&mut old, &delta);
+    assert!(diff(&old, &new).is_empty());
+    assert_eq!(old.get_b(), Some(&2));
+
+    let mut old = PayloadEnum::make_map();
+    old.insert(PayloadEnum::B(1));
+    let mut new = PayloadEnum::make_map();
+    new.insert(PayloadEnum::A);
+    new.insert(PayloadEnum::B(1));
+
+    let delta = diff(&old, &new);
+    assert_eq!(delta.added(), &[PayloadEnum::A]);
+    assert!(delta.changed().is_empty());
+    apply(&mut old, &delta);
+    assert_eq!(old.keys_set(), new.keys_set());
+}
+
+#[test]
+pub fn crate_path() {
+    use serde::{Deserialize, Serialize};
+    use variant_map_derive::VariantStore;
+
+    /// Facade crate re-exporting `variant_map`
+    mod platform {
+        pub use variant_map;
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize, VariantStore)]
+    #[VariantStore(datastruct = "StructMap", crate = "platform::variant_map")]
+    #[VariantStruct(features(index, serialize, deserialize))]
+    enum PlatformEnum {
+        A,
+        B(i32),
+    }
+
+    let mut m = PlatformEnumStructMap::default();
+    m.insert(PlatformEnum::B(1));
+    assert_eq!(m[PlatformEnumKey::B], Some(PlatformEnum::B(1)));
+
+    let json = serde_json::to_string(&m).unwrap();
+    assert_eq!(json, r#"[{"B":1}]"#);
+    let deserialized: PlatformEnumStructMap = serde_json::from_str(&json).unwrap();
+    assert_eq!(deserialized.get(&PlatformEnumKey::B), Some(&PlatformEnum::B(1)));
+}
+
+#[test]
+pub fn user_keys() {
+    use serde::{Deserialize, Serialize};
+    use variant_map::common::{MapValue, VariantMap};
+    use variant_map::keyset::KeySet;
+    use variant_map_derive::VariantStore;
+
+    #[derive(Debug, PartialEq, Eq)]
+    enum Sensor {
+        Temperature,
+        Humidity,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize, VariantStore)]
+    #[VariantStore(datastruct = "StructMap", keys(use = "Sensor", implement))]
+    #[VariantStruct(features(index, serialize, deserialize))]
+    enum SensorEnum {
+        #[key(Sensor::Temperature)]
+        Celsius(i16),
+        #[key(Sensor::Humidity)]
+        Percent(u8),
+    }
+
+    assert_eq!(SensorEnum::Percent(40).to_key(), Sensor::Humidity);
+
+    let mut m = SensorEnumStructMap::default();
+    m.insert(SensorEnum::Celsius(21));
+    assert_eq!(m[Sensor::Temperature], Some(SensorEnum::Celsius(21)));
+    assert_eq!(m.get(&Sensor::Humidity), None);
+    assert_eq!(m.keys_set(), KeySet::from_iter([Sensor::Temperature]));
+
+    let json = serde_json::to_string(&m).unwrap();
+    let deserialized: SensorEnumStructMap = serde_json::from_str(&json).unwrap();
+    assert_eq!(deserialized.get(&Sensor::Temperature), Some(&SensorEnum::Celsius(21)));
+}
+
+#[test]
+pub fn skipped_variants() {
+    use serde::{Deserialize, Serialize};
+    use variant_map::common::{MapValue, Skipped, VariantMap};
+    use variant_map::keyset::KeySet;
+    use variant_map_derive::VariantStore;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize, VariantStore)]
+    #[VariantStore(datastruct = "StructMap")]
+    #[VariantStruct(features(serialize, deserialize))]
+    enum ChannelEnum {
+        Audio(u8),
+        Video,
+        #[key_name(skip)]
+        Unknown { id: u32 },
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize, VariantStore)]
+    #[VariantStore(datastruct = "StructMap", skipped = "ignore")]
+    #[VariantStruct(storage = "payload", features(serialize, deserialize))]
+    enum LenientChannelEnum {
+        Audio(u8),
+        #[key_name(skip)]
+        Unknown { id: u32 },
+    }
+
+    assert_eq!(ChannelEnum::Unknown { id: 1 }.try_to_key().unwrap_err().variant, "Unknown");
+
+    let mut m = ChannelEnumStructMap::default();
+    assert_eq!(m.try_insert(ChannelEnum::Audio(2)), Ok(None));
+    assert_eq!(m.try_insert(ChannelEnum::Unknown { id: 1 }), Err(ChannelEnum::Unknown { id: 1 }));
+    assert_eq!(m.insert(ChannelEnum::Unknown { id: 2 }), Some(ChannelEnum::Unknown { id: 2 }));
+    assert_eq!(m.entry_for(&ChannelEnum::Unknown { id: 2 }).err(), Some(Skipped { variant: "Unknown" }));
+    assert_eq!(m.get_or_insert(ChannelEnum::Unknown { id: 2 }).unwrap_err(), ChannelEnum::Unknown { id: 2 });
+    assert_eq!(m.entry(ChannelEnumKey::Video).or_insert(ChannelEnum::Unknown { id: 3 }).err(), Some(ChannelEnum::Unknown { id: 3 }));
+    assert_eq!(m.keys_set(), KeySet::from_iter([ChannelEnumKey::Audio]));
+
+    let error = serde_json::from_str::<ChannelEnumStructMap>(r#"["Video",{"Unknown":{"id":1}}]"#).unwrap_err();
+    assert!(error.to_string().contains("variant `Unknown` is skipped"));
+
+    let mut lenient: LenientChannelEnumStructMap = serde_json::from_str(r#"[{"Audio":2},{"Unknown":{"id":1}}]"#).unwrap();
+    assert_eq!(lenient.len(), 1);
+    assert_eq!(lenient.try_insert(LenientChannelEnum::Unknown { id: 3 }), Err(LenientChannelEnum::Unknown { id: 3 }));
+    assert_eq!(lenient.insert(LenientChannelEnum::Unknown { id: 3 }), Some(LenientChannelEnum::Unknown { id: 3 }));
+
+    let mut other = LenientChannelEnumStructMap::default();
+    other.insert(LenientChannelEnum::Audio(5));
+    assert_eq!(lenient.merge_with(other, |_key, _left, _right| LenientChannelEnum::Unknown { id: 4 }), Err(Skipped { variant: "Unknown" }));
+    assert!(lenient.is_empty());
+}
+
+#[test]
+pub fn shared_keys() {
+    use serde::{Deserialize, Serialize};
+    use variant_map::common::VariantMap;
+    use variant_map::keyset::KeySet;
+    use variant_map_derive::VariantStore;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize, VariantStore)]
+    #[VariantStore(datastruct = "StructMap")]
+    #[VariantStruct(features(index, serialize, deserialize))]
+    enum ModeEnum {
+        #[key_name(code = "Power")]
+        Eco,
+        #[key_name(code = "Power")]
+        Boost(u8),
+        Brightness(u8),
+    }
+
+    let mut m = ModeEnumStructMap::default();
+    m.insert(ModeEnum::Eco);
+    m.insert(ModeEnum::Brightness(3));
+    assert_eq!(m.insert(ModeEnum::Boost(2)), Some(ModeEnum::Eco));
+    assert_eq!(m[ModeEnumKey::Power], Some(ModeEnum::Boost(2)));
+    assert_eq!(m.len(), 2);
+    assert_eq!(m.keys_set(), KeySet::from_iter([ModeEnumKey::Power, ModeEnumKey::Brightness]));
+
+    assert_eq!(m.set_eco(), Some(ModeEnum::Boost(2)));
+    assert_eq!(m.get_boost(), None);
+    assert_eq!(m.set_boost(4), Some(ModeEnum::Eco));
+    assert_eq!(m.set_brightness(5), Some(3));
+
+    let json = serde_json::to_string(&m).unwrap();
+    assert_eq!(json, r#"[{"Boost":4},{"Brightness":5}]"#);
+    let deserialized: ModeEnumStructMap = serde_json::from_str(r#"[{"Boost":2},"Eco"]"#).unwrap();
+    assert_eq!(deserialized.get(&ModeEnumKey::Power), Some(&ModeEnum::Eco));
+}
+
+#[test]
+pub fn renamed_fields() {
+    use serde::{Deserialize, Serialize};
+    use variant_map::common::VariantMap;
+    use variant_map::keyset::KeySet;
+    use variant_map_derive::VariantStore;
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, VariantStore)]
+    #[VariantStore(datastruct = "StructMap", keys(rename_all(serde = "kebab-case", code = "SCREAMING_SNAKE_CASE")))]
+    #[VariantStruct(storage = "payload", field_case = "snake_case", features(serialize, deserialize))]
+    enum DisplayEnum {
+        DarkMode(bool),
+        FontSize(u8),
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize, VariantStore)]
+    #[VariantStore(datastruct = "StructMap")]
+    #[VariantStruct(field_case = "snake_case", features(index, serialize))]
+    enum ProxyEnum {
+        HTTPProxy(String),
+        NoProxy,
+    }
+
+    let mut m = DisplayEnumStructMap::default();
+    m.insert(DisplayEnum::DarkMode(true));
+    assert_eq!(m.get(&DisplayEnumKey::DARK_MODE).as_deref(), Some(&DisplayEnum::DarkMode(true)));
+    assert_eq!(m.get_font_size(), None);
+    assert_eq!(format!("{m:?}"), "DisplayEnumStructMap { dark_mode: Some(true), font_size: None }");
+    assert_eq!(m.keys_set(), KeySet::from_iter([DisplayEnumKey::DARK_MODE]));
+
+    let mut m = ProxyEnumStructMap::default();
+    m.insert(ProxyEnum::HTTPProxy("localhost".to_string()));
+    assert_eq!(format!("{m:?}"), r#"ProxyEnumStructMap { h_t_t_p_proxy: Some(HTTPProxy("localhost")), no_proxy: None }"#);
+    assert_eq!(m[ProxyEnumKey::HTTPProxy], Some(ProxyEnum::HTTPProxy("localhost".to_string())));
+    assert_eq!(serde_json::to_string(&m).unwrap(), r#"[{"HTTPProxy":"localhost"}]"#);
+}
@@ -5,8 +5,7 @@
 #[allow(unused_imports)]
 use variant_map::derive as variant_map_derive;
 
-use variant_map;
-use variant_map::common::MapValue;
+use variant_map::common::{MapValue, VariantMap};
 use variant_map::derive::{VariantStore};
 use variant_map::{as_key, as_map};
 use serde::{Deserialize, Serialize};
@@ -35,7 +34,7 @@ pub fn normal_enum() {
     let _a = map.get(&<TestEnum as MapValue>::Key::A);
     let _d = map.get(&<TestEnum as MapValue>::Key::Dimitri);
     let _b = &map[<TestEnum as MapValue>::Key::B];
-    let _b = &mut map[<TestEnum as MapValue>::Key::B];
+    let _ = map.get_mut(&<TestEnum as MapValue>::Key::B);
 
     println!("{}", serde_json::to_string(&map).unwrap());
 }
@@ -75,7 +74,36 @@ pub fn generic_enum() {
     let _a = map.get(&<GenericEnum<G> as MapValue>::Key::A);
     let _d = map.get(&<GenericEnum<G> as MapValue>::Key::Dimitri);
     let _b = &map[<GenericEnum<G> as MapValue>::Key::B];
-    let _b = &mut map[<GenericEnum<G> as MapValue>::Key::B];
+    let _ = map.get_mut(&<GenericEnum<G> as MapValue>::Key::B);
+
+    println!("{}", serde_json::to_string(&map).unwrap());
+}
+
+/// A classic enum with unit and tuple variants
+/// [variant_map::arraymap::Map] using a fixed-size array indexed by key for this enum is derived and used
+pub fn array_enum() {
+    #[derive(Serialize, Deserialize, VariantStore)]
+    #[VariantStore(datastruct = "ArrayMap", keys(name = "TestKeys"))]
+    enum TestEnum {
+        A,
+        B,
+        C(i32),
+        #[key_name(code = "Dimitri")]
+        #[serde(rename = "dimitri")]
+        D(i32, u64, (u16, String)),
+    }
+
+    let mut map: as_map!(TestEnum) = TestEnum::make_map();
+    map.insert(TestEnum::A);
+    map.insert(TestEnum::B);
+    map.insert(TestEnum::C(0));
+    map.insert(TestEnum::D(0, 1, (2, "mdr".to_string())));
+    let _k = <as_key!(TestEnum)>::A;
+    let _k = as_key!(TestEnum, A);
+    let _a = map.get(&<TestEnum as MapValue>::Key::A);
+    let _d = map.get(&<TestEnum as MapValue>::Key::Dimitri);
+    let _b = &map[<TestEnum as MapValue>::Key::B];
+    let _ = map.get_mut(&<TestEnum as MapValue>::Key::B);
 
     println!("{}", serde_json::to_string(&map).unwrap());
 }
@@ -105,7 +133,7 @@ pub fn normal_enum_struct_map() {
     let _a = map.get(&<TestEnum as MapValue>::Key::A);
     let _d = map.get(&<TestEnum as MapValue>::Key::Dimitri);
     let _b = &map[<TestEnum as MapValue>::Key::B];
-    let _b = &mut map[<TestEnum as MapValue>::Key::B];
+    let _ = map.get_mut(&<TestEnum as MapValue>::Key::B);
 
     println!("{}", serde_json::to_string(&map).unwrap());
 }
@@ -139,7 +167,7 @@ pub fn generic_enum_struct_map() {
     let _a = map.get(&<TestEnum<i64> as MapValue>::Key::A);
     let _d = map.get(&<TestEnum<i64> as MapValue>::Key::Dimitri);
     let _b = &map[<TestEnum<i64> as MapValue>::Key::B];
-    let _b = &mut map[<TestEnum<i64> as MapValue>::Key::B];
+    let _ = map.get_mut(&<TestEnum<i64> as MapValue>::Key::B);
 
     println!("{}", serde_json::to_string(&map).unwrap());
 }
@@ -149,6 +177,7 @@ pub fn generic_enum_struct_map() {
 fn main() {
     normal_enum();
     generic_enum();
+    array_enum();
     normal_enum_struct_map();
     generic_enum_struct_map();
 }